@@ -8,15 +8,18 @@
 // Software.
 
 use crate::utilities::{
-    ActionTriggered, Attributes, Candidate, CandidateInfo, ChangeElder, ChurnNeeded, Event,
-    GenesisPfxInfo, LocalEvent, Name, Node, NodeChange, NodeState, ParsecVote, Proof, ProofRequest,
-    ProofSource, RelocatedInfo, Rpc, Section, SectionInfo, State, TestEvent,
+    ActionTriggered, Attributes, Authority, Candidate, CandidateInfo, ChangeElder, ChurnNeeded,
+    Event, GenesisPfxInfo, LocalEvent, Message, MessageId, Name, Node, NodeChange, NodeState,
+    ParsecVote, Proof, ProofRequest, ProofScheme, ProofSource, RelocatedInfo, Rpc, SecretKey,
+    Section, SectionElders, SectionInfo, SectionLoad, SectionProof, SectionProofChain, State,
+    TestEvent, ELDER_SIZE,
 };
 use itertools::Itertools;
 use std::{
     cell::RefCell,
     collections::{BTreeMap, BTreeSet},
     fmt::{self, Debug, Formatter},
+    mem,
     rc::Rc,
 };
 use unwrap::unwrap;
@@ -25,6 +28,11 @@ use unwrap::unwrap;
 pub struct InnerAction {
     pub our_attributes: Attributes,
     pub our_section: SectionInfo,
+    // Member: how our_section was authorised, from the first version we knew.
+    // Joining node: the destination section's history from the version we were relocated to.
+    pub section_proof_chain: SectionProofChain,
+    // Every version of our neighbours we trust: each later one was signed by the one before.
+    pub neighbour_infos: BTreeSet<SectionInfo>,
     pub our_current_nodes: BTreeMap<Name, NodeState>,
 
     pub our_events: Vec<Event>,
@@ -38,8 +46,19 @@ pub struct InnerAction {
 
     pub connected: BTreeSet<Name>,
 
+    // Elder: how we challenge candidates, and the nonce of our next resource proof session
+    pub resource_proof_scheme: ProofScheme,
+    pub next_proof_nonce: i32,
+
+    // Id of the next message we send
+    pub next_message_id: u64,
+
     // Proving node:
     pub resource_proofs_for_elder: BTreeMap<Name, ProofSource>,
+
+    // Joining node: the keys of the identity we were relocated from, and of our new one
+    pub old_secret_key: SecretKey,
+    pub our_secret_key: SecretKey,
 }
 
 impl InnerAction {
@@ -47,6 +66,8 @@ impl InnerAction {
         Self {
             our_attributes: name,
             our_section: Default::default(),
+            section_proof_chain: Default::default(),
+            neighbour_infos: Default::default(),
             our_current_nodes: Default::default(),
 
             our_events: Default::default(),
@@ -60,7 +81,15 @@ impl InnerAction {
 
             connected: Default::default(),
 
+            resource_proof_scheme: Default::default(),
+            next_proof_nonce: 0,
+
+            next_message_id: 0,
+
             resource_proofs_for_elder: Default::default(),
+
+            old_secret_key: Default::default(),
+            our_secret_key: Default::default(),
         }
     }
 
@@ -86,6 +115,17 @@ impl InnerAction {
         self.extend_current_nodes(&node_states)
     }
 
+    pub fn with_our_section(mut self, section: SectionInfo) -> Self {
+        self.our_section = section;
+        self.section_proof_chain = SectionProofChain::new(section);
+        self
+    }
+
+    pub fn with_neighbour_infos(mut self, infos: &[SectionInfo]) -> Self {
+        self.neighbour_infos.extend(infos.iter().copied());
+        self
+    }
+
     pub fn with_section_members(mut self, section: SectionInfo, nodes: &[Node]) -> Self {
         let inserted = self.section_members.insert(section, nodes.to_vec());
         assert!(inserted.is_none());
@@ -97,18 +137,36 @@ impl InnerAction {
         self
     }
 
+    pub fn with_secret_keys(
+        mut self,
+        old_secret_key: SecretKey,
+        our_secret_key: SecretKey,
+    ) -> Self {
+        self.old_secret_key = old_secret_key;
+        self.our_secret_key = our_secret_key;
+        self
+    }
+
+    pub fn with_resource_proof_scheme(mut self, scheme: ProofScheme) -> Self {
+        self.resource_proof_scheme = scheme;
+        self
+    }
+
     fn add_node(&mut self, node_state: NodeState) {
         self.our_events
             .push(NodeChange::AddWithState(node_state.node, node_state.state).to_event());
         let inserted = self
             .our_current_nodes
             .insert(node_state.node.name(), node_state);
-        assert!(inserted.is_none());
+        assert!(inserted.is_none(), "Added node already a member");
     }
 
     fn remove_node(&mut self, name: Name) {
         self.our_events.push(NodeChange::Remove(name).to_event());
-        unwrap!(self.our_current_nodes.remove(&name));
+        unwrap!(
+            self.our_current_nodes.remove(&name),
+            "Removed node not a member"
+        );
     }
 
     fn replace_node(&mut self, node_name: Name, node_state: NodeState) {
@@ -122,7 +180,7 @@ impl InnerAction {
 
         assert!(
             removed.is_some() && inserted.is_none(),
-            "{:?} - {:?}",
+            "Replaced node not a member, or replacement already one: {:?} - {:?}",
             removed,
             inserted
         );
@@ -145,11 +203,29 @@ impl InnerAction {
     }
 
     fn set_section_info(&mut self, section: SectionInfo) {
+        let proof = self.our_section.sign_successor(section);
+        let extended = self.section_proof_chain.extend(section, proof);
+        assert!(
+            extended,
+            "Our section proof chain does not end with our section."
+        );
         self.our_section = section;
         self.our_events
             .push(ActionTriggered::OurSectionChanged(section).to_event());
     }
 
+    fn trust_neighbour_update(&mut self, info: SectionInfo, proof: SectionProof) -> bool {
+        let previous = proof.section_info;
+        if !self.neighbour_infos.contains(&previous) || !previous.is_signed_successor(info, proof) {
+            return false;
+        }
+
+        let _ = self.neighbour_infos.insert(info);
+        self.our_events
+            .push(ActionTriggered::NeighbourUpdated(info).to_event());
+        true
+    }
+
     fn store_merge_infos(&mut self, merge_info: SectionInfo) {
         self.merge_infos = Some(merge_info);
         self.our_events
@@ -179,6 +255,10 @@ impl Action {
         (*self.0.borrow()).clone()
     }
 
+    pub fn take_our_events(&self) -> Vec<Event> {
+        mem::take(&mut self.0.borrow_mut().our_events)
+    }
+
     pub fn remove_processed_state(&self) {
         let inner = &mut self.0.borrow_mut();
         inner.our_events.clear();
@@ -216,8 +296,25 @@ impl Action {
         self.0.borrow_mut().our_events.push(vote.to_event());
     }
 
+    // Node to node: the content names the destination.
     pub fn send_rpc(&self, rpc: Rpc) {
-        self.0.borrow_mut().our_events.push(rpc.to_event());
+        let destination = Authority::Node(unwrap!(rpc.destination()));
+        self.send_message(Authority::Node(self.our_name()), destination, rpc);
+    }
+
+    // Sent by us on behalf of our section.
+    pub fn send_section_rpc(&self, destination: Authority, rpc: Rpc) {
+        let source = Authority::Section(self.our_section().0);
+        self.send_message(source, destination, rpc);
+    }
+
+    fn send_message(&self, source: Authority, destination: Authority, rpc: Rpc) {
+        let mut inner = self.0.borrow_mut();
+        let id = MessageId(inner.next_message_id);
+        inner.next_message_id += 1;
+        inner
+            .our_events
+            .push(Message::new(source, destination, id, rpc).to_event());
     }
 
     pub fn schedule_event(&self, event: LocalEvent) {
@@ -237,12 +334,15 @@ impl Action {
             expected_age: candidate.0.age.increment_by_one(),
             target_interval_centre,
             section_info: self.0.borrow().our_section,
+            source_proof: None,
         };
 
+        // Its key is only known once it sends its CandidateInfo.
         let state = NodeState {
             node: Node(Attributes {
                 name: info.target_interval_centre,
                 age: info.expected_age,
+                ..Attributes::default()
             }),
             state: State::WaitingCandidateInfo(info),
             ..NodeState::default()
@@ -297,18 +397,9 @@ impl Action {
         let inner = &self.0.borrow();
         let our_current_nodes = &inner.our_current_nodes;
 
-        let (new_elders, ex_elders, _elders) = {
-            let mut sorted_values = our_current_nodes
-                .values()
-                .cloned()
-                .sorted_by(|left, right| {
-                    left.state
-                        .cmp(&right.state)
-                        .then(left.node.0.age.cmp(&right.node.0.age).reverse())
-                        .then(left.node.0.name.cmp(&right.node.0.name))
-                })
-                .collect_vec();
-            let elder_size = std::cmp::min(3, sorted_values.len());
+        let (new_elders, ex_elders, elders) = {
+            let mut sorted_values = elders_first(our_current_nodes.values().cloned());
+            let elder_size = std::cmp::min(ELDER_SIZE, sorted_values.len());
             let adults = sorted_values.split_off(elder_size);
 
             let new_elders = sorted_values
@@ -337,7 +428,11 @@ impl Action {
         } else {
             Some(ChangeElder {
                 changes,
-                new_section: SectionInfo(inner.our_section.0, inner.our_section.1 + 1),
+                new_section: SectionInfo(
+                    inner.our_section.0,
+                    inner.our_section.1 + 1,
+                    SectionElders::new(&elders.iter().map(|elder| elder.node).collect_vec()),
+                ),
             })
         }
     }
@@ -350,9 +445,6 @@ impl Action {
                 true => ParsecVote::AddElderNode(*node),
                 false => ParsecVote::RemoveElderNode(*node),
             })
-            .chain(std::iter::once(ParsecVote::NewSectionInfo(
-                change_elder.new_section,
-            )))
             .collect_vec()
     }
 
@@ -365,15 +457,38 @@ impl Action {
         self.0
             .borrow_mut()
             .set_section_info(change_elder.new_section);
+        self.send_neighbour_update();
+    }
+
+    // Let our neighbours follow our new elders: the link signed by the previous ones.
+    fn send_neighbour_update(&self) {
+        let (info, proof) = *unwrap!(self.0.borrow().section_proof_chain.links().last());
+        self.send_section_rpc(
+            Authority::Neighbours(info.0),
+            Rpc::NeighbourUpdate { info, proof },
+        );
     }
 
     pub fn get_section_split_votes(&self) -> Vec<ParsecVote> {
         // The section name is currently just a signed number, so we just pick an arbirary rule to
-        // generate two new section names.
+        // generate two new section names, and give each the lower or upper half of our members.
         let our_section_name = (self.our_section().0).0;
+        let members = self
+            .0
+            .borrow()
+            .our_current_nodes
+            .values()
+            .cloned()
+            .collect_vec();
+        let (lower, upper) = members.split_at(members.len() / 2);
         (1..3)
-            .map(|name_offset| {
-                ParsecVote::NewSectionInfo(SectionInfo(Section(our_section_name + name_offset), 0))
+            .zip(&[lower, upper])
+            .map(|(name_offset, half)| {
+                ParsecVote::NewSectionInfo(SectionInfo(
+                    Section(our_section_name + name_offset),
+                    0,
+                    section_elders(half.iter().cloned()),
+                ))
             })
             .collect_vec()
     }
@@ -461,7 +576,7 @@ impl Action {
     }
 
     pub fn is_valid_waited_info(&self, info: CandidateInfo) -> bool {
-        if !info.valid {
+        if !info.has_valid_signatures() {
             return false;
         }
 
@@ -469,14 +584,60 @@ impl Action {
             .borrow()
             .our_current_nodes
             .get(&info.waiting_candidate_name)
-            .map(|state| state.state.waiting_candidate_info().is_some())
+            .and_then(|state| state.state.waiting_candidate_info())
+            .map(|relocated_info| {
+                relocated_info.candidate == info.old_public_id
+                    && self.is_valid_source_proof(info.source_proof, relocated_info)
+            })
             .unwrap_or(false)
     }
 
+    // The source section we know of signed the relocation we agreed to.
+    fn is_valid_source_proof(
+        &self,
+        source_proof: Option<SectionProof>,
+        relocated_info: RelocatedInfo,
+    ) -> bool {
+        source_proof
+            .and_then(|proof| {
+                self.0
+                    .borrow()
+                    .section_members
+                    .get(&proof.section_info)
+                    .map(|elders| proof.verify(elders, &relocated_info.signed_values()))
+            })
+            .unwrap_or(false)
+    }
+
+    pub fn sign_relocated_info(&self, info: RelocatedInfo) -> RelocatedInfo {
+        let inner = self.0.borrow();
+        let our_elders = inner
+            .our_current_nodes
+            .values()
+            .filter(|state| state.is_elder)
+            .map(|state| state.node)
+            .collect_vec();
+        info.signed_by(inner.our_section, &our_elders)
+    }
+
     pub fn is_our_name(&self, name: Name) -> bool {
         self.our_name() == name
     }
 
+    // Whether a message to `destination` is for us to handle. Our section covers the names of
+    // its members, and neighbours every section but itself.
+    pub fn is_our_destination(&self, destination: Authority) -> bool {
+        let inner = self.0.borrow();
+        match destination {
+            Authority::Node(name) => name == inner.our_attributes.name,
+            Authority::Section(section) => section == inner.our_section.0,
+            Authority::SectionOf(name) => {
+                name == inner.our_attributes.name || inner.our_current_nodes.contains_key(&name)
+            }
+            Authority::Neighbours(section) => section != inner.our_section.0,
+        }
+    }
+
     pub fn our_name(&self) -> Name {
         self.0.borrow().our_attributes.name
     }
@@ -489,18 +650,84 @@ impl Action {
         self.0.borrow().our_section
     }
 
-    pub fn send_node_approval_rpc(&self, candidate: Candidate) {
-        let section = GenesisPfxInfo(self.0.borrow().our_section);
-        self.send_rpc(Rpc::NodeApproval(candidate, section));
+    pub fn send_node_approval_rpc(&self, candidate: Candidate, trusted_section: SectionInfo) {
+        // Let the candidate catch up from the version it was relocated to.
+        let catch_up = self
+            .0
+            .borrow()
+            .section_proof_chain
+            .trimmed_to(trusted_section);
+        for (info, proof) in catch_up.iter().flat_map(|chain| chain.links()) {
+            self.send_section_rpc(
+                Authority::Node(candidate.name()),
+                Rpc::SectionUpdate {
+                    destination: candidate.name(),
+                    info: *info,
+                    proof: *proof,
+                },
+            );
+        }
+
+        let info = GenesisPfxInfo(self.0.borrow().our_section);
+        self.send_rpc(Rpc::NodeApproval {
+            candidate,
+            source: self.our_name(),
+            info,
+        });
+    }
+
+    pub fn trust_section(&self, section: SectionInfo) {
+        self.0.borrow_mut().section_proof_chain = SectionProofChain::new(section);
+    }
+
+    pub fn extend_section_proof_chain(&self, info: SectionInfo, proof: SectionProof) -> bool {
+        self.0.borrow_mut().section_proof_chain.extend(info, proof)
+    }
+
+    pub fn trust_neighbour_update(&self, info: SectionInfo, proof: SectionProof) -> bool {
+        self.0.borrow_mut().trust_neighbour_update(info, proof)
     }
 
+    pub fn is_trusted_neighbour(&self, info: SectionInfo) -> bool {
+        self.0.borrow().neighbour_infos.contains(&info)
+    }
+
+    pub fn is_trusted_section(&self, info: SectionInfo) -> bool {
+        self.0.borrow().section_proof_chain.contains(info)
+    }
+
+    // Answers go to the section the candidate is relocating from.
     pub fn send_relocate_response_rpc(&self, info: RelocatedInfo) {
-        self.send_rpc(Rpc::RelocateResponse(info));
+        self.send_section_rpc(
+            Authority::SectionOf(info.candidate.name()),
+            Rpc::RelocateResponse(info),
+        );
+    }
+
+    pub fn section_load(&self) -> SectionLoad {
+        SectionLoad {
+            section_size: self.0.borrow().our_current_nodes.len() as i32,
+        }
     }
 
-    pub fn send_candidate_proof_request(&self, candidate: Candidate) {
+    // Each request starts a new session: its nonce makes proofs from earlier sessions stale.
+    pub fn new_resource_proof_request(&self, candidate: Candidate) -> ProofRequest {
+        let (scheme, nonce) = {
+            let inner = &mut self.0.borrow_mut();
+            let nonce = inner.next_proof_nonce;
+            inner.next_proof_nonce += 1;
+            (inner.resource_proof_scheme, nonce)
+        };
+        scheme.scheme().request(
+            self.our_name(),
+            candidate.name(),
+            nonce,
+            self.section_load(),
+        )
+    }
+
+    pub fn send_candidate_proof_request(&self, candidate: Candidate, proof: ProofRequest) {
         let source = self.our_name();
-        let proof = ProofRequest { value: source.0 };
         self.send_rpc(Rpc::ResourceProof {
             candidate,
             proof,
@@ -508,12 +735,21 @@ impl Action {
         });
     }
 
-    pub fn send_candidate_proof_receipt(&self, candidate: Candidate) {
+    pub fn send_candidate_proof_receipt(&self, candidate: Candidate, index: i32) {
         let source = self.our_name();
-        self.send_rpc(Rpc::ResourceProofReceipt { candidate, source });
+        self.send_rpc(Rpc::ResourceProofReceipt {
+            candidate,
+            source,
+            index,
+        });
     }
 
-    pub fn start_compute_resource_proof(&self, source: Name, _proof: ProofRequest) {
+    pub fn start_compute_resource_proof(&self, source: Name, proof: ProofRequest) {
+        let _ = self
+            .0
+            .borrow_mut()
+            .resource_proofs_for_elder
+            .insert(source, ProofSource::new(proof));
         self.action_triggered(ActionTriggered::ComputeResourceProofForElder(source));
     }
 
@@ -524,6 +760,23 @@ impl Action {
             .partition(|name| self.0.borrow().connected.contains(name))
     }
 
+    pub fn is_our_elder(&self, name: Name) -> bool {
+        self.0
+            .borrow()
+            .our_current_nodes
+            .get(&name)
+            .is_some_and(|state| state.is_elder)
+    }
+
+    pub fn our_elder_count(&self) -> usize {
+        self.0
+            .borrow()
+            .our_current_nodes
+            .values()
+            .filter(|state| state.is_elder)
+            .count()
+    }
+
     pub fn get_section_elders(&self, info: SectionInfo) -> Vec<Node> {
         unwrap!(self.0.borrow().section_members.get(&info)).clone()
     }
@@ -536,6 +789,14 @@ impl Action {
             .and_then(ProofSource::next_part)
     }
 
+    pub fn get_current_resource_proof_part(&self, source: Name) -> Option<Proof> {
+        self.0
+            .borrow()
+            .resource_proofs_for_elder
+            .get(&source)
+            .and_then(|proof_source| proof_source.resend())
+    }
+
     pub fn send_connection_info_request(&self, destination: Name) {
         let source = self.our_name();
         self.send_rpc(Rpc::ConnectionInfoRequest {
@@ -545,7 +806,6 @@ impl Action {
         });
     }
 
-    #[allow(dead_code)]
     pub fn send_connection_info_response(&self, destination: Name) {
         let source = self.our_name();
         self.send_rpc(Rpc::ConnectionInfoResponse {
@@ -555,17 +815,32 @@ impl Action {
         });
     }
 
-    pub fn send_candidate_info(&self, destination: Name, relocated_info: RelocatedInfo) {
-        let _ = self.0.borrow_mut().connected.insert(destination);
+    pub fn mark_connected(&self, name: Name) {
+        let _ = self.0.borrow_mut().connected.insert(name);
+    }
 
-        let new_public_id = Candidate(self.0.borrow().our_attributes);
-        self.send_rpc(Rpc::CandidateInfo(CandidateInfo {
-            old_public_id: relocated_info.candidate,
-            new_public_id,
-            destination,
-            waiting_candidate_name: relocated_info.target_interval_centre,
-            valid: true,
-        }));
+    pub fn mark_disconnected(&self, name: Name) {
+        let _ = self.0.borrow_mut().connected.remove(&name);
+    }
+
+    pub fn is_connected(&self, name: Name) -> bool {
+        self.0.borrow().connected.contains(&name)
+    }
+
+    pub fn send_candidate_info(&self, destination: Name, relocated_info: RelocatedInfo) {
+        let candidate_info = {
+            let inner = self.0.borrow();
+            CandidateInfo::new(
+                relocated_info.candidate,
+                Candidate(inner.our_attributes),
+                destination,
+                relocated_info.target_interval_centre,
+                relocated_info.source_proof,
+                inner.old_secret_key,
+                inner.our_secret_key,
+            )
+        };
+        self.send_rpc(Rpc::CandidateInfo(candidate_info));
     }
 
     pub fn send_resource_proof_response(&self, destination: Name, proof: Proof) {
@@ -578,7 +853,11 @@ impl Action {
     }
 
     pub fn send_merge_rpc(&self) {
-        self.send_rpc(Rpc::Merge(self.our_section()));
+        let our_section = self.our_section();
+        self.send_section_rpc(
+            Authority::Neighbours(our_section.0),
+            Rpc::Merge(our_section),
+        );
     }
 
     pub fn increment_nodes_work_units(&self) {
@@ -628,11 +907,27 @@ impl Action {
     pub fn merge_sibling_info_to_new_section(&self) -> SectionInfo {
         let our_section = self.our_section();
         let their_section = self.0.borrow_mut().merge_infos.take();
-        let their_section = their_section.expect("Merge infos missing").0;
+        let their_section = their_section.expect("Merge infos missing");
+        // The merged elders are picked from our members and their elders.
+        let members = self
+            .0
+            .borrow()
+            .our_current_nodes
+            .values()
+            .cloned()
+            .chain(their_section.2.nodes().iter().map(|node| NodeState {
+                node: *node,
+                ..NodeState::default_elder()
+            }))
+            .collect_vec();
         // See comment in has_sibling_merge_info() about name of sections. Here we just pick a
         // simple rule to produce a new section name from the two old ones.
         // Should we switch over to use prefixes this would need to be updated.
-        SectionInfo(Section((our_section.0).0 + (their_section.0) + 1), 0)
+        SectionInfo(
+            Section((our_section.0).0 + (their_section.0).0 + 1),
+            0,
+            section_elders(members),
+        )
     }
 
     pub fn complete_split(&self) {
@@ -640,6 +935,28 @@ impl Action {
     }
 }
 
+// Best elder candidates first: by state, then oldest, then by name.
+fn elders_first(nodes: impl Iterator<Item = NodeState>) -> Vec<NodeState> {
+    nodes
+        .sorted_by(|left, right| {
+            left.state
+                .cmp(&right.state)
+                .then(left.node.0.age.cmp(&right.node.0.age).reverse())
+                .then(left.node.0.name.cmp(&right.node.0.name))
+        })
+        .collect_vec()
+}
+
+// The elders of a section with these members.
+fn section_elders(nodes: impl IntoIterator<Item = NodeState>) -> SectionElders {
+    let elders = elders_first(nodes.into_iter())
+        .iter()
+        .take(ELDER_SIZE)
+        .map(|elder| elder.node)
+        .collect_vec();
+    SectionElders::new(&elders)
+}
+
 impl Default for Action {
     fn default() -> Action {
         Action::new(InnerAction::new_with_our_attributes(Attributes::default()))
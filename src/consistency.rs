@@ -0,0 +1,280 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::diagrams::{self, label_text, Diagram};
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display, Formatter},
+};
+
+// The flows, whose routines handle events in match arms.
+pub const FLOWS_SOURCES: &[&str] = &[
+    include_str!("flows_dst.rs"),
+    include_str!("flows_elder.rs"),
+    include_str!("flows_node.rs"),
+    include_str!("flows_src.rs"),
+];
+
+// Each diagram of index.html, with the routine mirroring it.
+pub const DIAGRAM_ROUTINES: &[(&str, &str)] = &[
+    ("JoiningRelocateCandidate", "JoiningRelocateCandidate"),
+    (
+        "StartRespondToRelocateRequests",
+        "RespondToRelocateRequests",
+    ),
+    ("StartResourceProof", "StartResourceProof"),
+    (
+        "StartRelocatedNodeConnection",
+        "StartRelocatedNodeConnection",
+    ),
+    (
+        "StartDecidesOnNodeToRelocate",
+        "StartDecidesOnNodeToRelocate",
+    ),
+    ("StartRelocateSrc", "StartRelocateSrc"),
+    ("StartConnectionHandler", "StartConnectionHandler"),
+    (
+        "StartMergeSplitAndChangeElders",
+        "StartMergeSplitAndChangeElders",
+    ),
+    ("ProcessElderChange", "ProcessElderChange"),
+    ("ProcessMerge", "ProcessMerge"),
+    ("ProcessSplit", "ProcessSplit"),
+    ("CheckOnlineOffline", "CheckOnlineOffline"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EventKind {
+    Rpc,
+    ParsecVote,
+    LocalEvent,
+}
+
+impl EventKind {
+    // How the flows name the variants of this kind.
+    fn code_prefix(self) -> &'static str {
+        match self {
+            EventKind::Rpc => "Rpc::",
+            EventKind::ParsecVote => "ParsecVote::",
+            EventKind::LocalEvent => "LocalEvent::",
+        }
+    }
+}
+
+// An event a routine handles that its diagram does not, or the other way round.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Mismatch {
+    pub diagram: String,
+    pub kind: EventKind,
+    pub name: String,
+    // Whether only the diagram has it, rather than only the routine.
+    pub in_diagram: bool,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        let (has, lacks) = if self.in_diagram {
+            ("diagram", "routine")
+        } else {
+            ("routine", "diagram")
+        };
+        // Diagrams describe local events in words.
+        let event = if self.name.chars().all(char::is_alphanumeric) {
+            format!("{}{}", self.kind.code_prefix(), self.name)
+        } else {
+            format!("{:?} {:?}", self.kind, self.name)
+        };
+        write!(
+            formatter,
+            "{}: {} is handled by the {}, not the {}",
+            self.diagram, event, has, lacks
+        )
+    }
+}
+
+// Cross-checks the diagrams of index.html against the flows.
+pub fn check() -> Vec<Mismatch> {
+    let diagrams = DIAGRAM_ROUTINES
+        .iter()
+        .filter_map(|(diagram, routine)| Some((diagrams::diagram(diagram)?, *routine)))
+        .collect::<Vec<_>>();
+    check_sources(&diagrams, FLOWS_SOURCES)
+}
+
+// The events each diagram's edges handle against those matched on by its routine in `sources`.
+// Rpcs and votes must be named alike; local events, described in words, must use the words of
+// the variant.
+pub fn check_sources(diagrams: &[(&Diagram, &str)], sources: &[&str]) -> Vec<Mismatch> {
+    let mut mismatches = BTreeSet::new();
+
+    for (diagram, routine) in diagrams {
+        let in_code = sources
+            .iter()
+            .flat_map(|source| handled_events(source, routine))
+            .collect::<BTreeSet<_>>();
+        let in_diagram = diagram_events(diagram);
+        let mut mismatch = |kind, name: &str, in_diagram| {
+            let _ = mismatches.insert(Mismatch {
+                diagram: diagram.name.clone(),
+                kind,
+                name: name.to_string(),
+                in_diagram,
+            });
+        };
+
+        for (kind, name) in &in_diagram {
+            let handled = in_code
+                .iter()
+                .any(|(code_kind, variant)| code_kind == kind && names_event(*kind, name, variant));
+            if !handled {
+                mismatch(*kind, name, true);
+            }
+        }
+        for (kind, variant) in &in_code {
+            let drawn = in_diagram.iter().any(|(diagram_kind, name)| {
+                diagram_kind == kind && names_event(*kind, name, variant)
+            });
+            if !drawn {
+                mismatch(*kind, variant, false);
+            }
+        }
+    }
+    mismatches.into_iter().collect()
+}
+
+// Whether the diagram's `name` for an event stands for the `variant`.
+fn names_event(kind: EventKind, name: &str, variant: &str) -> bool {
+    match kind {
+        EventKind::Rpc | EventKind::ParsecVote => name == variant,
+        EventKind::LocalEvent => {
+            let name_words = words(name);
+            words(variant).iter().all(|word| name_words.contains(word))
+        }
+    }
+}
+
+// The Rpcs and votes an edge label starts with, and the local events labelling the edges out of
+// the diagram's event nodes. Further on, labels only mention events, as in "Response not from the
+// section we sent Rpc::ExpectCandidate to".
+fn diagram_events(diagram: &Diagram) -> BTreeSet<(EventKind, String)> {
+    let mut events = BTreeSet::new();
+    for edge in &diagram.edges {
+        let label = label_text(&edge.label);
+        let leading = label
+            .split_whitespace()
+            .map_while(|word| {
+                if word.starts_with("Rpc::") {
+                    Some((EventKind::Rpc, names_after(word, "Rpc::").pop()?))
+                } else if word.starts_with("Parsec::") {
+                    Some((EventKind::ParsecVote, names_after(word, "Parsec::").pop()?))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        let is_name = !label.is_empty() && label.chars().all(char::is_alphanumeric);
+
+        let from = diagram
+            .nodes
+            .get(&edge.from)
+            .map_or_else(|| edge.from.clone(), |node| label_text(&node.label));
+        let from = words(&from);
+        if !leading.is_empty() {
+            events.extend(leading);
+        } else if from == ["rpc"] && is_name {
+            // Out of the RPC node, the Rpc may go without its prefix.
+            let _ = events.insert((EventKind::Rpc, label));
+        } else if from == ["event"] || from == ["local", "event"] {
+            let _ = events.insert((EventKind::LocalEvent, label));
+        }
+    }
+    events
+}
+
+// The variants matched on in the `impl` of `routine` in `source`.
+fn handled_events(source: &str, routine: &str) -> BTreeSet<(EventKind, String)> {
+    let mut events = BTreeSet::new();
+    let header = format!("impl<'a> {}<'a> {{", routine);
+    let body = match source.split(&header).nth(1) {
+        Some(rest) => rest.split("\nimpl").next().unwrap_or_default(),
+        None => return events,
+    };
+
+    for kind in &[EventKind::Rpc, EventKind::ParsecVote, EventKind::LocalEvent] {
+        let prefix = kind.code_prefix();
+        for (index, _) in body.match_indices(prefix) {
+            // `Rpc::` of `ParsecRpc::` would not be ours.
+            let part_of_path = matches!(
+                body[..index].chars().last(),
+                Some(character) if character.is_alphanumeric() || character == '_'
+            );
+            let rest = &body[index + prefix.len()..];
+            if let Some(name) = names_after(&body[index..], prefix).into_iter().next() {
+                if !part_of_path && is_pattern(rest) {
+                    let _ = events.insert((*kind, name));
+                }
+            }
+        }
+    }
+    events
+}
+
+// Identifiers following each `prefix` in `text`.
+fn names_after(text: &str, prefix: &str) -> Vec<String> {
+    text.split(prefix)
+        .skip(1)
+        .filter_map(|rest| {
+            let name = rest
+                .split(|character: char| !character.is_alphanumeric() && character != '_')
+                .next()?;
+            Some(name.to_string()).filter(|name| !name.is_empty())
+        })
+        .collect()
+}
+
+// Whether the variant starting `rest` is matched on: a match arm pattern follows it up to `=>`,
+// where building it would end the expression first.
+fn is_pattern(rest: &str) -> bool {
+    let mut depth = 0;
+    for (index, character) in rest.char_indices() {
+        match character {
+            '(' | '[' | '{' => depth += 1,
+            // A pattern may be nested in another, like `WaitedEvent::LocalEvent(..)`.
+            ')' | ']' => depth -= 1,
+            '}' if depth == 0 => return false,
+            '}' => depth -= 1,
+            ';' => return false,
+            ',' if depth <= 0 => return false,
+            '=' if depth <= 0 && rest[index..].starts_with("=>") => return true,
+            _ => (),
+        }
+    }
+    false
+}
+
+// The lowercase words of a label or identifier: `CheckElder Timeout` has `check`, `elder` and
+// `timeout`.
+fn words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for part in text.split(|character: char| !character.is_alphanumeric()) {
+        let mut word = String::new();
+        let mut previous_lowercase = false;
+        for character in part.chars() {
+            if character.is_uppercase() && previous_lowercase {
+                words.push(word.split_off(0));
+            }
+            previous_lowercase = character.is_lowercase() || character.is_numeric();
+            word.extend(character.to_lowercase());
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+    }
+    words
+}
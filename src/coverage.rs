@@ -0,0 +1,539 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{
+    consistency::DIAGRAM_ROUTINES,
+    diagrams::{self, label_text, Diagram, Edge},
+    fuzz,
+    utilities::Event,
+};
+use lazy_static::lazy_static;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    env, fs,
+    sync::Mutex,
+    thread,
+};
+use unwrap::unwrap;
+
+// Set to a file to write the flow diagram coverage of the tests to: rewritten whenever it grows,
+// so it is complete once `cargo test` is done.
+const REPORT_ENV_VAR_NAME: &str = "ROUTING_MODEL_FLOW_COVERAGE";
+
+// The diagrams of index.html that the flows mirror, step by step.
+pub const ROUTINE_DIAGRAMS: &[&str] = &[
+    "JoiningRelocateCandidate",
+    "StartRespondToRelocateRequests",
+    "StartResourceProof",
+    "ResourceProof_Cancel",
+    "StartRelocatedNodeConnection",
+    "StartDecidesOnNodeToRelocate",
+    "StartRelocateSrc",
+    "StartConnectionHandler",
+    "StartMergeSplitAndChangeElders",
+    "ProcessElderChange",
+    "ProcessMerge",
+    "ProcessSplit",
+    "CheckOnlineOffline",
+];
+
+lazy_static! {
+    static ref REPORT_PATH: Option<String> = env::var(REPORT_ENV_VAR_NAME).ok();
+    static ref COVERAGE: Mutex<Coverage> = Mutex::new(Coverage::default());
+}
+
+thread_local! {
+    // How deep in events we are, the variant of the outermost one, and the steps they ran so far.
+    static EVENT_STEPS: RefCell<EventStepsState> = RefCell::new(EventStepsState::default());
+}
+
+#[derive(Default)]
+struct EventStepsState {
+    depth: usize,
+    event: Option<String>,
+    steps: Vec<Step>,
+}
+
+// A node of a diagram, by diagram name and node id.
+pub type Step = (&'static str, &'static str);
+
+// The node of its diagram that each method declared with `steps!` runs, by routine.
+pub const METHOD_NODES: &[(&str, &[(&str, &str)])] = &[
+    (
+        "RespondToRelocateRequests",
+        &[
+            ("reject_unexpected_authority", "RejectExpectCandidate"),
+            ("vote_parsec_expect_candidate", "VoteParsecExpectCandidate"),
+            ("send_expect_candidate_rpc", "RelocateToShorterPrefix"),
+            (
+                "resend_relocate_response_rpc",
+                "SendIdenticalExpectCandidateAcceptResponse",
+            ),
+            (
+                "add_node_and_send_relocate_response_rpc",
+                "SendExpectCandidateAcceptResponse",
+            ),
+            ("send_refuse_candidate_rpc", "SendRefuse"),
+        ],
+    ),
+    (
+        "StartRelocatedNodeConnection",
+        &[
+            ("node_connected", "NodeConnected"),
+            ("candidate_connected", "CandidateConnected"),
+            ("candidate_disconnected", "CandidateDisconnected"),
+        ],
+    ),
+    (
+        "StartResourceProof",
+        &[
+            ("start_event_loop", "StartCheckResourceProofTimeout"),
+            ("set_resource_proof_candidate", "SetCandidate"),
+            ("vote_parsec_purge_candidate", "VoteParsecPurgeCandidate"),
+            (
+                "vote_parsec_check_resource_proof",
+                "VoteParsecCheckResourceProofTimeout",
+            ),
+            ("cache_candidate_info_and_send_resource_proof", "RequestRP"),
+            ("make_node_online", "MakeOnline"),
+            ("purge_node_info", "RemoveNode"),
+            ("finish_resource_proof", "ReStartCheckResourceProofTimeout"),
+            ("schedule_proof_timeout", "ScheduleTimer"),
+            ("send_resource_proof_receipt_rpc", "SendProofReceipt"),
+        ],
+    ),
+    (
+        "StartMergeSplitAndChangeElders",
+        &[
+            ("start_event_loop", "StartCheckElderTimeout"),
+            ("vote_parsec_check_elder", "VoteCheckElderTimeout"),
+            ("store_merge_infos", "SetNeighbourMerge"),
+            ("vote_parsec_neighbour_merge", "VoteParsecNeighbourMerge"),
+            ("concurrent_transition_to_process_merge", "ProcessMerge"),
+            ("concurrent_transition_to_process_split", "ProcessSplit"),
+            (
+                "concurrent_transition_to_process_elder_change",
+                "ProcessElderChange",
+            ),
+        ],
+    ),
+    (
+        "ProcessElderChange",
+        &[
+            ("exit_event_loop", "EndRoutine"),
+            ("vote_for_elder_change", "MarkAndVoteSwapNewElder"),
+            ("abandon_elder_change", "DkgFailed"),
+        ],
+    ),
+    (
+        "ProcessMerge",
+        &[
+            ("exit_event_loop", "EndRoutine"),
+            ("update_elder_status", "MarkNewElderAdults"),
+        ],
+    ),
+    (
+        "ProcessSplit",
+        &[
+            ("exit_event_loop", "EndRoutine"),
+            ("vote_for_split_sections", "VoteNewSections"),
+            ("complete_split", "CompleteSplit"),
+            ("mark_elder_change", "MarkNewElderAdults"),
+        ],
+    ),
+    (
+        "CheckOnlineOffline",
+        &[
+            ("vote_parsec_offline", "VoteNodeOffline"),
+            ("vote_parsec_back_online", "VoteNodeBackOnline"),
+            ("make_node_offline", "SetOfflineState"),
+            ("make_node_back_online", "SetRelocating"),
+        ],
+    ),
+    (
+        "JoiningRelocateCandidate",
+        &[
+            ("start_event_loop", "InitialSendConnectionInfoRequest"),
+            ("rpc_section_update", "ExtendProofChain"),
+            ("exit", "EndRoutine"),
+            ("exit_failure", "EndRoutine"),
+            ("send_proof_response", "SendNextResourceProofPartForElder"),
+            ("connect_or_send_candidate_info", "CheckResend"),
+        ],
+    ),
+    (
+        "StartDecidesOnNodeToRelocate",
+        &[
+            ("start_event_loop", "StartWorkUnitTimeOut"),
+            (
+                "vote_parsec_work_unit_increment",
+                "VoteParsecRelocationTrigger",
+            ),
+            ("increment_nodes_work_units", "IncrementWorkUnit"),
+            ("set_relocating_candidate", "SetRelocatingNodeState"),
+        ],
+    ),
+    (
+        "StartRelocateSrc",
+        &[
+            ("start_event_loop", "StartCheckRelocateTimeOut"),
+            ("vote_parsec_check_relocate", "VoteParsecCheckRelocate"),
+            ("reject_unexpected_authority", "RejectResponse"),
+            (
+                "vote_parsec_refuse_candidate",
+                "VoteParsecExpectCandidateRefuseResponse",
+            ),
+            (
+                "vote_parsec_relocation_response",
+                "VoteParsecExpectCandidateAcceptResponse",
+            ),
+            (
+                "send_candidate_relocated_info_rpc",
+                "SendProvableRelocateInfo",
+            ),
+            ("purge_node_info", "PurgeNodeInfos"),
+            ("update_wait_and_allow_resend", "AllowResendCandidates"),
+            ("allow_resend", "RefusedCandidate"),
+            ("set_relocated_and_prepare_info", "VoteProvableRelocateInfo"),
+        ],
+    ),
+];
+
+// Methods that each run one node of the diagram of their routine, as listed in `METHOD_NODES`.
+macro_rules! steps {
+    ($(
+        $(#[$attribute:meta])*
+        $visibility:vis fn $name:ident($($parameters:tt)*) $(-> $output:ty)? $body:block
+    )*) => {$(
+        $(#[$attribute])*
+        $visibility fn $name($($parameters)*) $(-> $output)? {
+            $crate::coverage::method_step(std::any::type_name::<Self>(), stringify!($name));
+            $body
+        }
+    )*};
+}
+pub(crate) use steps;
+
+// Records that the flows ran `method` of the routine of type `routine`, when reporting coverage.
+// A method missing from `METHOD_NODES` is reported as a step not in the diagram.
+pub fn method_step(routine: &'static str, method: &'static str) {
+    if REPORT_PATH.is_none() {
+        return;
+    }
+    // `routing_model::flows_dst::StartResourceProof<'_>`
+    let routine = routine.split('<').next().unwrap_or(routine);
+    let routine = routine.rsplit("::").next().unwrap_or(routine);
+    let diagram = DIAGRAM_ROUTINES
+        .iter()
+        .find(|(_, name)| *name == routine)
+        .map_or(routine, |(diagram, _)| diagram);
+    let node = METHOD_NODES
+        .iter()
+        .filter(|(name, _)| *name == routine)
+        .flat_map(|(_, methods)| methods.iter())
+        .find(|(name, _)| *name == method)
+        .map_or(method, |(_, node)| node);
+    step(diagram, node)
+}
+
+// Records that the flows ran `node` of `diagram`, when reporting coverage.
+pub fn step(diagram: &'static str, node: &'static str) {
+    if REPORT_PATH.is_none() {
+        return;
+    }
+    let outside_event = EVENT_STEPS.with(|state| {
+        let state = &mut *state.borrow_mut();
+        state.steps.push((diagram, node));
+        state.depth == 0
+    });
+    if outside_event {
+        record_event_steps();
+    }
+}
+
+// The steps recorded until this is dropped ran for one event: `None` when starting a routine.
+pub struct EventSteps(());
+
+impl EventSteps {
+    pub fn start(event: Option<Event>) -> Self {
+        if REPORT_PATH.is_some() {
+            EVENT_STEPS.with(|state| {
+                let state = &mut *state.borrow_mut();
+                if state.depth == 0 {
+                    state.event = event.map(fuzz::kind);
+                }
+                state.depth += 1;
+            });
+        }
+        EventSteps(())
+    }
+}
+
+impl Drop for EventSteps {
+    fn drop(&mut self) {
+        if REPORT_PATH.is_none() {
+            return;
+        }
+        let depth = EVENT_STEPS.with(|state| {
+            let depth = &mut state.borrow_mut().depth;
+            *depth -= 1;
+            *depth
+        });
+        if depth == 0 {
+            record_event_steps();
+        }
+    }
+}
+
+fn record_event_steps() {
+    let (event, steps) = EVENT_STEPS.with(|state| {
+        let state = &mut *state.borrow_mut();
+        (state.event.take(), state.steps.split_off(0))
+    });
+    // A panicking event did not run the steps it would have ended with.
+    if thread::panicking() || steps.is_empty() {
+        return;
+    }
+
+    let mut coverage = unwrap!(COVERAGE.lock());
+    if coverage.record(event.as_deref(), &steps) {
+        if let Some(path) = REPORT_PATH.as_ref() {
+            unwrap!(
+                fs::write(path, coverage.report()),
+                "Cannot write the flow coverage to '{}'.",
+                path
+            );
+        }
+    }
+}
+
+// The nodes and edges of each diagram that events went through.
+#[derive(Debug, Default)]
+pub struct Coverage {
+    nodes: BTreeSet<(String, String)>,
+    // By diagram, from, to and label
+    edges: BTreeSet<(String, String, String, String)>,
+    // Steps that ran one after the other without a path between them in the diagram
+    no_path: BTreeSet<(String, String, String)>,
+    unknown_steps: BTreeSet<(String, String)>,
+}
+
+impl Coverage {
+    // Records the steps one event of variant `event` ran, in order, and whether that covered
+    // anything new.
+    pub fn record(&mut self, event: Option<&str>, steps: &[Step]) -> bool {
+        let mut by_diagram = BTreeMap::<&str, Vec<&str>>::new();
+        for (diagram, node) in steps {
+            let nodes = by_diagram.entry(diagram).or_default();
+            if nodes.last() != Some(node) {
+                nodes.push(node);
+            }
+        }
+
+        let mut covered_new = false;
+        for (name, nodes) in by_diagram {
+            covered_new |= match diagrams::diagram(name) {
+                Some(diagram) => self.record_diagram(diagram, event, &nodes),
+                None => self
+                    .unknown_steps
+                    .insert((name.to_string(), nodes.join(", "))),
+            };
+        }
+        covered_new
+    }
+
+    // From where the event arrived, through each step, to where the next one waits.
+    fn record_diagram(&mut self, diagram: &Diagram, event: Option<&str>, nodes: &[&str]) -> bool {
+        let name = &diagram.name;
+        let mut covered_new = false;
+        let mut from = diagram.entries();
+        let mut from_name = "the event";
+        // Only the way in depends on the event: `Rpc::NodeApproval` is labelled `NodeApproval`.
+        let mut hint = event
+            .and_then(|event| event.split_once("::"))
+            .map(|(_, variant)| variant);
+
+        for node in nodes {
+            if !diagram.has_node(node) {
+                covered_new |= self.unknown_steps.insert((name.clone(), node.to_string()));
+                continue;
+            }
+            covered_new |= self.nodes.insert((name.clone(), node.to_string()));
+            match path(diagram, &from, hint.take(), |id| id == *node) {
+                Some(edges) => covered_new |= self.cover_edges(diagram, edges),
+                None => {
+                    covered_new |=
+                        self.no_path
+                            .insert((name.clone(), from_name.to_string(), node.to_string()))
+                }
+            }
+            from = vec![node];
+            from_name = node;
+        }
+
+        let entries = diagram.entries();
+        if let Some(edges) = path(diagram, &from, None, |id| {
+            entries.contains(&id) || diagram.is_end(id)
+        }) {
+            covered_new |= self.cover_edges(diagram, edges);
+        }
+        covered_new
+    }
+
+    fn cover_edges(&mut self, diagram: &Diagram, edges: Vec<(String, String, String)>) -> bool {
+        let mut covered_new = false;
+        for (from, to, label) in edges {
+            covered_new |= self.nodes.insert((diagram.name.clone(), from.clone()));
+            covered_new |= self.nodes.insert((diagram.name.clone(), to.clone()));
+            covered_new |= self.edges.insert((diagram.name.clone(), from, to, label));
+        }
+        covered_new
+    }
+
+    // What the routine diagrams have that no event went through.
+    pub fn report(&self) -> String {
+        let mut totals = (0, 0, 0, 0);
+        let mut details = String::new();
+
+        for name in ROUTINE_DIAGRAMS {
+            let diagram = unwrap!(diagrams::diagram(name), "No diagram for {}", name);
+            let nodes = diagram_nodes(diagram);
+            let uncovered_nodes = nodes
+                .iter()
+                .filter(|node| !self.nodes.contains(&(name.to_string(), node.to_string())))
+                .collect::<Vec<_>>();
+            let uncovered_edges = diagram
+                .edges
+                .iter()
+                .filter(|edge| {
+                    !self.edges.contains(&(
+                        name.to_string(),
+                        edge.from.clone(),
+                        edge.to.clone(),
+                        edge.label.clone(),
+                    ))
+                })
+                .collect::<Vec<_>>();
+
+            totals.0 += nodes.len() - uncovered_nodes.len();
+            totals.1 += nodes.len();
+            totals.2 += diagram.edges.len() - uncovered_edges.len();
+            totals.3 += diagram.edges.len();
+
+            details += &format!(
+                "\n{}: {} of {} nodes, {} of {} edges\n",
+                name,
+                nodes.len() - uncovered_nodes.len(),
+                nodes.len(),
+                diagram.edges.len() - uncovered_edges.len(),
+                diagram.edges.len()
+            );
+            for node in uncovered_nodes {
+                details += &format!("    Node never exercised: {}\n", node);
+            }
+            for edge in uncovered_edges {
+                let label = label_text(&edge.label);
+                let arrow = if label.is_empty() {
+                    "-->".to_string()
+                } else {
+                    format!("--{}-->", label)
+                };
+                details += &format!(
+                    "    Edge never exercised: {} {} {}\n",
+                    edge.from, arrow, edge.to
+                );
+            }
+            for (_, from, to) in self.no_path.iter().filter(|(diagram, ..)| diagram == name) {
+                details += &format!("    No path from {} to {}\n", from, to);
+            }
+            for (_, node) in self
+                .unknown_steps
+                .iter()
+                .filter(|(diagram, _)| diagram == name)
+            {
+                details += &format!("    Step not in the diagram: {}\n", node);
+            }
+        }
+
+        for (diagram, nodes) in &self.unknown_steps {
+            if !ROUTINE_DIAGRAMS.contains(&diagram.as_str()) {
+                details += &format!("\nSteps of no routine diagram: {}: {}\n", diagram, nodes);
+            }
+        }
+
+        format!(
+            "Flow diagram coverage: {} of {} nodes, {} of {} edges exercised\n{}",
+            totals.0, totals.1, totals.2, totals.3, details
+        )
+    }
+}
+
+fn diagram_nodes(diagram: &Diagram) -> BTreeSet<&str> {
+    diagram
+        .nodes
+        .keys()
+        .map(String::as_str)
+        .chain(
+            diagram
+                .edges
+                .iter()
+                .flat_map(|edge| vec![edge.from.as_str(), edge.to.as_str()]),
+        )
+        .collect()
+}
+
+// The shortest path from one of `from` to a node `to` accepts, only branching and joining on the
+// way: a step in between would have been recorded. Of the shortest ones, the first with a label
+// mentioning `hint`, if any.
+fn path(
+    diagram: &Diagram,
+    from: &[&str],
+    hint: Option<&str>,
+    to: impl Fn(&str) -> bool,
+) -> Option<Vec<(String, String, String)>> {
+    let mut previous = BTreeMap::new();
+    let mut queue = from.iter().map(|node| (*node, 0)).collect::<VecDeque<_>>();
+    let mut visited = from.iter().copied().collect::<BTreeSet<_>>();
+    let mut found: Vec<Vec<&Edge>> = Vec::new();
+
+    while let Some((node, length)) = queue.pop_front() {
+        if matches!(found.first(), Some(path) if path.len() <= length) {
+            break;
+        }
+        for edge in diagram.edges.iter().filter(|edge| edge.from == node) {
+            let next = edge.to.as_str();
+            if to(next) {
+                let mut edges = vec![edge];
+                let mut current = node;
+                while let Some(edge) = previous.get(current) {
+                    edges.push(*edge);
+                    current = &edge.from;
+                }
+                edges.reverse();
+                found.push(edges);
+            } else if !diagram.is_step(next) && visited.insert(next) {
+                let _ = previous.insert(next, edge);
+                queue.push_back((next, length + 1));
+            }
+        }
+    }
+
+    let mentions_hint = |edges: &&Vec<&Edge>| match hint {
+        Some(hint) => edges.iter().any(|edge| edge.label.contains(hint)),
+        None => false,
+    };
+    let edges = found.iter().find(mentions_hint).or_else(|| found.first())?;
+    Some(
+        edges
+            .iter()
+            .map(|edge| (edge.from.clone(), edge.to.clone(), edge.label.clone()))
+            .collect(),
+    )
+}
@@ -0,0 +1,262 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+
+lazy_static! {
+    // The flow diagrams of index.html.
+    pub static ref DIAGRAMS: Vec<Diagram> = parse_html(include_str!("../index.html"));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    // A step: ["..."]
+    Box,
+    // A decision or wait: (("...")), ("...") or {"..."}
+    Circle,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub shape: Shape,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub label: String,
+}
+
+// A mermaid `graph TB`, named after the routine it starts with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagram {
+    pub name: String,
+    pub start: String,
+    // Only nodes given a shape: others, like LoopStart and LoopEnd, just join edges.
+    pub nodes: BTreeMap<String, Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl Diagram {
+    pub fn has_node(&self, id: &str) -> bool {
+        self.nodes.contains_key(id)
+            || self
+                .edges
+                .iter()
+                .any(|edge| edge.from == id || edge.to == id)
+    }
+
+    // Where events arrive: the nodes waiting for them, and the start of the diagram.
+    pub fn entries(&self) -> Vec<&str> {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| {
+                node.shape == Shape::Circle && label_text(&node.label).starts_with("Wait for")
+            })
+            .map(|(id, _)| id.as_str())
+            .chain(Some(self.start.as_str()))
+            .collect()
+    }
+
+    // Where a routine ends, if it does.
+    pub fn is_end(&self, id: &str) -> bool {
+        matches!(self.nodes.get(id), Some(node) if label_text(&node.label).starts_with("End"))
+    }
+
+    // Boxes stand for steps of the flows; circles, and nodes without a shape like LoopStart and
+    // LoopEnd, only branch and join.
+    pub fn is_step(&self, id: &str) -> bool {
+        matches!(self.nodes.get(id), Some(node) if node.shape == Shape::Box)
+    }
+}
+
+pub fn diagram(name: &str) -> Option<&'static Diagram> {
+    DIAGRAMS.iter().find(|diagram| diagram.name == name)
+}
+
+// Labels as rendered: without quotes, line breaks and entities.
+pub fn label_text(label: &str) -> String {
+    let text = label
+        .trim()
+        .trim_matches('"')
+        .replace("<br />", " ")
+        .replace("<br/>", " ")
+        .replace("&lt", "<")
+        .replace("&gt", ">");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn parse_html(html: &str) -> Vec<Diagram> {
+    html.split("<div class=\"mermaid\">")
+        .skip(1)
+        .filter_map(|block| block.split("</div>").next())
+        .filter(|block| block.trim_start().starts_with("graph TB"))
+        .map(parse_graph)
+        .collect()
+}
+
+fn parse_graph(graph: &str) -> Diagram {
+    let mut parser = Parser {
+        text: graph.trim_start().trim_start_matches("graph TB"),
+        nodes: BTreeMap::new(),
+        edges: Vec::new(),
+        styled: Vec::new(),
+    };
+    parser.parse();
+
+    // Routines start and end with a highlighted node.
+    let label = |id: &String| {
+        parser
+            .nodes
+            .get(id)
+            .map_or_else(|| id.clone(), |node| label_text(&node.label))
+    };
+    let start = parser
+        .styled
+        .iter()
+        .find(|id| !label(id).starts_with("End"))
+        .cloned()
+        .unwrap_or_default();
+    let name = label(&start)
+        .split([':', ' ', '('])
+        .next()
+        .unwrap_or_default()
+        .to_string();
+
+    Diagram {
+        name,
+        start,
+        nodes: parser.nodes,
+        edges: parser.edges,
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    nodes: BTreeMap<String, Node>,
+    edges: Vec<Edge>,
+    styled: Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(&mut self) {
+        let mut previous = None;
+        loop {
+            self.skip_whitespace();
+            if self.text.is_empty() {
+                return;
+            }
+
+            if self.text.starts_with("--") {
+                let label = self.edge_label();
+                self.skip_whitespace();
+                let to = self.node();
+                if let (Some(from), Some(to)) = (previous.clone(), to.clone()) {
+                    self.edges.push(Edge { from, to, label });
+                }
+                previous = to;
+                continue;
+            }
+
+            match self.node() {
+                Some(id) if id == "style" => {
+                    self.skip_whitespace();
+                    if let Some(styled) = self.node() {
+                        self.styled.push(styled);
+                    }
+                    self.skip_line();
+                    previous = None;
+                }
+                Some(id) => previous = Some(id),
+                // Not mermaid we know: skip the line.
+                None => {
+                    self.skip_line();
+                    previous = None;
+                }
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.text = self.text.trim_start();
+    }
+
+    fn skip_line(&mut self) {
+        self.text = self.text.split_once('\n').map_or("", |(_, rest)| rest);
+    }
+
+    // An id, with its shape and label if given here.
+    fn node(&mut self) -> Option<String> {
+        let length = self
+            .text
+            .find(|character: char| !character.is_alphanumeric() && character != '_')
+            .unwrap_or(self.text.len());
+        if length == 0 {
+            return None;
+        }
+        let id = self.text[..length].to_string();
+        self.text = &self.text[length..];
+
+        let shape = [
+            ("((", "))", Shape::Circle),
+            ("(", ")", Shape::Circle),
+            ("{", "}", Shape::Circle),
+            ("[", "]", Shape::Box),
+        ]
+        .iter()
+        .find(|(open, _, _)| self.text.starts_with(open));
+        if let Some((open, close, shape)) = shape {
+            self.text = &self.text[open.len()..];
+            let label = self.label_until(close);
+            let _ = self.nodes.insert(
+                id.clone(),
+                Node {
+                    shape: *shape,
+                    label,
+                },
+            );
+        }
+        Some(id)
+    }
+
+    // Text up to `close`, which quoted text may contain.
+    fn label_until(&mut self, close: &str) -> String {
+        let end = if self.text.starts_with('"') {
+            self.text[1..].find('"').map_or(0, |quote| quote + 2)
+        } else {
+            0
+        };
+        let end = self.text[end..]
+            .find(close)
+            .map_or(self.text.len(), |index| end + index);
+        let label = self.text[..end].to_string();
+        self.text = &self.text[(end + close.len()).min(self.text.len())..];
+        label
+    }
+
+    // `-->`, `--label-->`, `-- label -->` or `--"label" -->`.
+    fn edge_label(&mut self) -> String {
+        self.text = &self.text[2..];
+        let end = self.text.find("-->").unwrap_or(self.text.len());
+        // `-->` itself: the arrow has no label.
+        let (label, rest) = if self.text.starts_with('>') {
+            ("", &self.text[1..])
+        } else {
+            (
+                &self.text[..end],
+                &self.text[(end + 3).min(self.text.len())..],
+            )
+        };
+        self.text = rest;
+        label.trim().to_string()
+    }
+}
@@ -8,10 +8,12 @@
 // Software.
 
 use crate::{
-    state::{MemberState, StartResourceProofState},
+    coverage,
+    state::{MemberState, StartRelocatedNodeConnectionState, StartResourceProofState},
     utilities::{
-        Candidate, CandidateInfo, LocalEvent, Name, ParsecVote, Proof, RelocatedInfo, Rpc,
-        TryResult, WaitedEvent,
+        is_quorum, ActionTriggered, Authority, Candidate, CandidateInfo, LocalEvent, Message, Name,
+        ParsecVote, Proof, ProofRequest, RelocatedInfo, Rpc, Section, TryResult, WaitedEvent,
+        MAX_WAITING_CANDIDATES,
     },
 };
 use unwrap::unwrap;
@@ -22,14 +24,19 @@ pub struct RespondToRelocateRequests<'a>(pub &'a mut MemberState);
 impl<'a> RespondToRelocateRequests<'a> {
     pub fn try_next(&mut self, event: WaitedEvent) -> TryResult {
         match event {
-            WaitedEvent::Rpc(rpc) => self.try_rpc(rpc),
+            WaitedEvent::Rpc(message) => self.try_rpc(message),
             WaitedEvent::ParsecConsensus(vote) => self.try_consensus(vote),
             _ => TryResult::Unhandled,
         }
     }
 
-    fn try_rpc(&mut self, rpc: Rpc) -> TryResult {
-        match rpc {
+    fn try_rpc(&mut self, message: Message) -> TryResult {
+        match message.rpc {
+            // Only a section may relocate one of its members to us.
+            Rpc::ExpectCandidate(_) if !message.source.is_section() => {
+                self.reject_unexpected_authority();
+                TryResult::Handled
+            }
             Rpc::ExpectCandidate(candidate) => {
                 self.vote_parsec_expect_candidate(candidate);
                 TryResult::Handled
@@ -51,8 +58,8 @@ impl<'a> RespondToRelocateRequests<'a> {
     }
 
     fn consensused_expect_candidate(&mut self, candidate: Candidate) {
-        if self.0.action.check_shortest_prefix().is_some() {
-            self.send_expect_candidate_rpc(candidate);
+        if let Some(target) = self.0.action.check_shortest_prefix() {
+            self.send_expect_candidate_rpc(candidate, target);
             return;
         }
 
@@ -61,7 +68,7 @@ impl<'a> RespondToRelocateRequests<'a> {
             return;
         }
 
-        if 0 == self.0.action.count_waiting_proofing_or_hop() {
+        if self.0.action.count_waiting_proofing_or_hop() < MAX_WAITING_CANDIDATES {
             self.add_node_and_send_relocate_response_rpc(candidate);
             return;
         }
@@ -69,27 +76,151 @@ impl<'a> RespondToRelocateRequests<'a> {
         self.send_refuse_candidate_rpc(candidate);
     }
 
-    fn add_node_and_send_relocate_response_rpc(&mut self, candidate: Candidate) {
-        let relocated_info = self.0.action.add_node_waiting_candidate_info(candidate);
-        self.0.action.send_relocate_response_rpc(relocated_info);
+    coverage::steps! {
+        fn add_node_and_send_relocate_response_rpc(&mut self, candidate: Candidate) {
+            let relocated_info = self.0.action.add_node_waiting_candidate_info(candidate);
+            self.0.action.send_relocate_response_rpc(relocated_info);
+        }
+
+        fn resend_relocate_response_rpc(&mut self, relocated_info: RelocatedInfo) {
+            self.0.action.send_relocate_response_rpc(relocated_info);
+        }
+
+        fn send_refuse_candidate_rpc(&mut self, candidate: Candidate) {
+            self.0.action.send_section_rpc(
+                Authority::SectionOf(candidate.name()),
+                Rpc::RefuseCandidate(candidate),
+            );
+        }
+
+        fn send_expect_candidate_rpc(&mut self, candidate: Candidate, target: Section) {
+            self.0
+                .action
+                .send_section_rpc(Authority::Section(target), Rpc::ExpectCandidate(candidate));
+        }
+
+        fn reject_unexpected_authority(&mut self) {
+            self.0
+                .action
+                .action_triggered(ActionTriggered::UnexpectedAuthorityRejected);
+        }
+
+        fn vote_parsec_expect_candidate(&mut self, candidate: Candidate) {
+            self.0
+                .action
+                .vote_parsec(ParsecVote::ExpectCandidate(candidate));
+        }
     }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct StartRelocatedNodeConnection<'a>(pub &'a mut MemberState);
 
-    fn resend_relocate_response_rpc(&mut self, relocated_info: RelocatedInfo) {
-        self.0.action.send_relocate_response_rpc(relocated_info);
+impl<'a> StartRelocatedNodeConnection<'a> {
+    pub fn try_next(&mut self, event: WaitedEvent) -> TryResult {
+        match event {
+            WaitedEvent::Rpc(message) => self.try_rpc(message.rpc),
+            WaitedEvent::LocalEvent(local_event) => self.try_local_event(local_event),
+            _ => TryResult::Unhandled,
+        }
     }
 
-    fn send_refuse_candidate_rpc(&mut self, candidate: Candidate) {
-        self.0.action.send_rpc(Rpc::RefuseCandidate(candidate));
+    fn try_rpc(&mut self, rpc: Rpc) -> TryResult {
+        match rpc {
+            Rpc::ConnectionInfoResponse { source, .. } => {
+                self.node_connected(source);
+                TryResult::Handled
+            }
+            Rpc::CandidateInfo(info) => {
+                self.rpc_info(info);
+                TryResult::Handled
+            }
+            _ => TryResult::Unhandled,
+        }
     }
 
-    fn send_expect_candidate_rpc(&mut self, candidate: Candidate) {
-        self.0.action.send_rpc(Rpc::ExpectCandidate(candidate));
+    fn try_local_event(&mut self, local_event: LocalEvent) -> TryResult {
+        match local_event {
+            LocalEvent::NodeDisconnected(name)
+                if self.routine_state().candidates_info.contains_key(&name) =>
+            {
+                self.candidate_disconnected(name);
+                TryResult::Handled
+            }
+            _ => TryResult::Unhandled,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self.routine_state_mut() = StartRelocatedNodeConnectionState::default();
     }
 
-    fn vote_parsec_expect_candidate(&mut self, candidate: Candidate) {
+    fn rpc_info(&mut self, info: CandidateInfo) {
+        if !self.0.action.is_valid_waited_info(info) {
+            coverage::step("StartRelocatedNodeConnection", "DiscardRPC");
+            self.discard();
+            return;
+        }
+
+        coverage::step("StartRelocatedNodeConnection", "CacheInfo");
+        let name = info.new_public_id.name();
+        let _ = self.routine_state_mut().candidates_info.insert(name, info);
+
+        if self.0.action.is_connected(name) {
+            self.candidate_connected(name);
+        } else {
+            coverage::step("StartRelocatedNodeConnection", "SendConnectionInfoRequest");
+            self.0.action.send_connection_info_request(name);
+        }
+    }
+
+    coverage::steps! {
+        fn node_connected(&mut self, name: Name) {
+            self.0.action.mark_connected(name);
+
+            if self.routine_state().candidates_info.contains_key(&name) {
+                self.candidate_connected(name);
+            } else {
+                self.discard();
+            }
+        }
+
+        fn candidate_connected(&mut self, name: Name) {
+            let _ = self.routine_state_mut().candidates.insert(name);
+
+            if self.routine_state_mut().candidates_voted.insert(name) {
+                self.vote_parsec_candidate_connected(name);
+            }
+        }
+
+        fn candidate_disconnected(&mut self, name: Name) {
+            self.0.action.mark_disconnected(name);
+            let _ = self.routine_state_mut().candidates.remove(&name);
+        }
+    }
+
+    fn routine_state(&self) -> &StartRelocatedNodeConnectionState {
+        &self.0.start_relocated_node_connection_state
+    }
+
+    fn routine_state_mut(&mut self) -> &mut StartRelocatedNodeConnectionState {
+        &mut self.0.start_relocated_node_connection_state
+    }
+
+    fn discard(&mut self) {}
+
+    fn vote_parsec_candidate_connected(&mut self, name: Name) {
+        // Every elder must vote the same info: address it to the waiting candidate, not to us.
+        let info = *unwrap!(
+            self.routine_state().candidates_info.get(&name),
+            "Candidate info not held"
+        );
         self.0
             .action
-            .vote_parsec(ParsecVote::ExpectCandidate(candidate));
+            .vote_parsec(ParsecVote::CandidateConnected(CandidateInfo {
+                destination: info.waiting_candidate_name,
+                ..info
+            }));
     }
 }
 
@@ -97,17 +228,19 @@ impl<'a> RespondToRelocateRequests<'a> {
 pub struct StartResourceProof<'a>(pub &'a mut MemberState);
 
 impl<'a> StartResourceProof<'a> {
-    // TODO - remove the `allow` once we have a test for this method.
-    #[allow(dead_code)]
-    fn start_event_loop(&mut self) {
-        self.0
-            .action
-            .schedule_event(LocalEvent::CheckResourceProofTimeout);
+    coverage::steps! {
+        // TODO - remove the `allow` once we have a test for this method.
+        #[allow(dead_code)]
+        fn start_event_loop(&mut self) {
+            self.0
+                .action
+                .schedule_event(LocalEvent::CheckResourceProofTimeout);
+        }
     }
 
     pub fn try_next(&mut self, event: WaitedEvent) -> TryResult {
         match event {
-            WaitedEvent::Rpc(rpc) => self.try_rpc(rpc),
+            WaitedEvent::Rpc(message) => self.try_rpc(message.rpc),
             WaitedEvent::ParsecConsensus(vote) => self.try_consensus(vote),
             WaitedEvent::LocalEvent(local_event) => self.try_local_event(local_event),
         }
@@ -121,10 +254,6 @@ impl<'a> StartResourceProof<'a> {
                 self.rpc_proof(candidate, proof);
                 TryResult::Handled
             }
-            Rpc::CandidateInfo(info) => {
-                self.rpc_info(info);
-                TryResult::Handled
-            }
             _ => TryResult::Unhandled,
         }
     }
@@ -138,15 +267,20 @@ impl<'a> StartResourceProof<'a> {
                 self.check_request_resource_proof();
                 TryResult::Handled
             }
-            ParsecVote::Online(_, new_candidate) if for_candidate => {
-                self.make_node_online(new_candidate);
+            ParsecVote::CandidateConnected(info) => {
+                self.consensused_candidate_info(info);
+                TryResult::Handled
+            }
+            ParsecVote::Online(_, new_candidate, voter) if for_candidate => {
+                self.consensused_online(new_candidate, voter);
                 TryResult::Handled
             }
             ParsecVote::PurgeCandidate(_) if for_candidate => {
                 self.purge_node_info();
                 TryResult::Handled
             }
-            ParsecVote::Online(_, _) | ParsecVote::PurgeCandidate(_) => {
+            ParsecVote::Online(_, _, _) | ParsecVote::PurgeCandidate(_) => {
+                coverage::step("StartResourceProof", "DiscardParsec");
                 self.discard();
                 TryResult::Handled
             }
@@ -158,10 +292,16 @@ impl<'a> StartResourceProof<'a> {
 
     fn try_local_event(&mut self, local_event: LocalEvent) -> TryResult {
         match local_event {
-            LocalEvent::TimeoutAccept => {
+            LocalEvent::TimeoutAccept if self.has_candidate() => {
                 self.vote_parsec_purge_candidate();
                 TryResult::Handled
             }
+            // Left from a session that already ended.
+            LocalEvent::TimeoutAccept => {
+                coverage::step("StartResourceProof", "DiscardTimeout");
+                self.discard();
+                TryResult::Handled
+            }
             LocalEvent::CheckResourceProofTimeout => {
                 self.vote_parsec_check_resource_proof();
                 TryResult::Handled
@@ -170,29 +310,85 @@ impl<'a> StartResourceProof<'a> {
         }
     }
 
-    fn rpc_info(&mut self, info: CandidateInfo) {
-        if self.has_candidate()
-            && self.candidate() == info.old_public_id
-            && self.0.action.is_valid_waited_info(info)
+    fn consensused_candidate_info(&mut self, info: CandidateInfo) {
+        if !self.has_candidate() || self.candidate() != info.old_public_id {
+            coverage::step("StartResourceProof", "DiscardParsec");
+            self.discard();
+            return;
+        }
+
+        if self.has_candidate_info() && self.candidate_info() == info {
+            // Same session: the candidate may not have received our request.
+            coverage::step("StartResourceProof", "ResendRP");
+            self.send_resource_proof_request_rpc();
+            return;
+        }
+
+        // Another info once our session started, or one signed for an earlier relocation.
+        if self.has_candidate_info() || info.waiting_candidate_name != self.waiting_candidate_name()
         {
+            coverage::step("StartResourceProof", "RejectReplay");
+            self.reject_replayed_message();
+            return;
+        }
+
+        if self.0.action.is_valid_waited_info(info) {
             self.cache_candidate_info_and_send_resource_proof(info)
         } else {
+            coverage::step("StartResourceProof", "DiscardParsec");
             self.discard()
         }
     }
 
     fn rpc_proof(&mut self, candidate: Candidate, proof: Proof) {
         let from_candidate = self.has_candidate_info() && candidate == self.new_candidate();
+        if !from_candidate {
+            coverage::step("StartResourceProof", "DiscardRPC");
+            self.discard();
+            return;
+        }
 
-        if from_candidate && !self.routine_state().voted_online && proof.is_valid() {
-            if proof == Proof::ValidEnd {
-                self.set_voted_online(true);
-                self.vote_parsec_online_candidate();
-            }
-            self.send_resource_proof_receipt_rpc();
-        } else {
-            self.discard()
+        if proof.nonce != self.proof_request().nonce {
+            coverage::step("StartResourceProof", "RejectReplay");
+            self.reject_replayed_message();
+            return;
+        }
+
+        if !self.is_valid_proof(proof) {
+            coverage::step("StartResourceProof", "DiscardRPC");
+            self.discard();
+            return;
+        }
+
+        let parts_checked = self.routine_state().proof_parts_checked;
+        if proof.index < parts_checked {
+            // Not counted again, but only the receipt may have been lost: this includes the end of
+            // the proof resent once we voted the candidate online.
+            coverage::step("StartResourceProof", "RejectReplayedPart");
+            self.reject_replayed_message();
+            self.send_resource_proof_receipt_rpc(proof.index);
+            return;
         }
+
+        if proof.index > parts_checked {
+            // Chunks must come in order: do not skip the ones not received.
+            coverage::step("StartResourceProof", "DiscardRPC");
+            self.discard();
+            return;
+        }
+
+        coverage::step("StartResourceProof", "CheckPart");
+        self.routine_state_mut().proof_parts_checked += 1;
+        if self.routine_state().proof_parts_checked == self.proof_request().data_size {
+            coverage::step("StartResourceProof", "VoteParsecOnline");
+            self.set_voted_online(true);
+            self.vote_parsec_online_candidate();
+        }
+        self.send_resource_proof_receipt_rpc(proof.index);
+    }
+
+    fn is_valid_proof(&self, proof: Proof) -> bool {
+        self.proof_request().is_valid(proof)
     }
 
     fn routine_state(&self) -> &StartResourceProofState {
@@ -205,51 +401,100 @@ impl<'a> StartResourceProof<'a> {
 
     fn discard(&mut self) {}
 
-    fn set_resource_proof_candidate(&mut self) {
-        self.routine_state_mut().candidate = self.0.action.resource_proof_candidate();
+    fn reject_replayed_message(&mut self) {
+        self.0
+            .action
+            .action_triggered(ActionTriggered::ReplayedMessageRejected);
+    }
+
+    coverage::steps! {
+        fn set_resource_proof_candidate(&mut self) {
+            self.routine_state_mut().candidate = self.0.action.resource_proof_candidate();
+        }
     }
 
     fn set_voted_online(&mut self, value: bool) {
         self.routine_state_mut().voted_online = value;
     }
 
-    fn vote_parsec_purge_candidate(&mut self) {
-        self.0
-            .action
-            .vote_parsec(ParsecVote::PurgeCandidate(self.candidate()));
-    }
+    coverage::steps! {
+        fn vote_parsec_purge_candidate(&mut self) {
+            self.0
+                .action
+                .vote_parsec(ParsecVote::PurgeCandidate(self.candidate()));
+        }
 
-    fn vote_parsec_check_resource_proof(&mut self) {
-        self.0.action.vote_parsec(ParsecVote::CheckResourceProof);
+        fn vote_parsec_check_resource_proof(&mut self) {
+            self.0.action.vote_parsec(ParsecVote::CheckResourceProof);
+        }
     }
 
     fn vote_parsec_online_candidate(&mut self) {
-        self.0
-            .action
-            .vote_parsec(ParsecVote::Online(self.candidate(), self.new_candidate()));
+        self.0.action.vote_parsec(ParsecVote::Online(
+            self.candidate(),
+            self.new_candidate(),
+            self.0.action.our_name(),
+        ));
     }
 
-    fn make_node_online(&mut self, new_public_id: Candidate) {
-        self.0
-            .action
-            .set_candidate_online_state(self.waiting_candidate_name(), new_public_id);
-        self.0.action.send_node_approval_rpc(new_public_id);
-        self.finish_resource_proof()
-    }
+    fn consensused_online(&mut self, new_public_id: Candidate, voter: Name) {
+        if !self.0.action.is_our_elder(voter)
+            || self.routine_state().online_votes.contains_key(&voter)
+        {
+            coverage::step("StartResourceProof", "DiscardParsec");
+            self.discard();
+            return;
+        }
 
-    fn purge_node_info(&mut self) {
-        self.0.action.purge_node_info(self.waiting_candidate_name());
-        self.finish_resource_proof()
+        coverage::step("StartResourceProof", "CountOnline");
+        let _ = self
+            .routine_state_mut()
+            .online_votes
+            .insert(voter, new_public_id);
+
+        let count = self
+            .routine_state()
+            .online_votes
+            .values()
+            .filter(|candidate| **candidate == new_public_id)
+            .count();
+        if is_quorum(count, self.0.action.our_elder_count()) {
+            self.make_node_online(new_public_id);
+        }
     }
 
-    fn finish_resource_proof(&mut self) {
-        self.routine_state_mut().candidate = None;
-        self.routine_state_mut().candidate_info = None;
-        self.routine_state_mut().voted_online = false;
+    coverage::steps! {
+        fn make_node_online(&mut self, new_public_id: Candidate) {
+            let relocated_info = unwrap!(
+                self.0.action.get_waiting_candidate_info(self.candidate()),
+                "Candidate not waiting"
+            );
+            self.0
+                .action
+                .set_candidate_online_state(self.waiting_candidate_name(), new_public_id);
+            self.0
+                .action
+                .send_node_approval_rpc(new_public_id, relocated_info.section_info);
+            self.finish_resource_proof()
+        }
 
-        self.0
-            .action
-            .schedule_event(LocalEvent::CheckResourceProofTimeout);
+        fn purge_node_info(&mut self) {
+            self.0.action.purge_node_info(self.waiting_candidate_name());
+            self.finish_resource_proof()
+        }
+
+        fn finish_resource_proof(&mut self) {
+            self.routine_state_mut().candidate = None;
+            self.routine_state_mut().candidate_info = None;
+            self.routine_state_mut().proof_request = None;
+            self.routine_state_mut().proof_parts_checked = 0;
+            self.routine_state_mut().voted_online = false;
+            self.routine_state_mut().online_votes.clear();
+
+            self.0
+                .action
+                .schedule_event(LocalEvent::CheckResourceProofTimeout);
+        }
     }
 
     fn check_request_resource_proof(&mut self) {
@@ -260,22 +505,24 @@ impl<'a> StartResourceProof<'a> {
         }
     }
 
-    fn schedule_proof_timeout(&mut self) {
-        self.0.action.schedule_event(LocalEvent::TimeoutAccept);
-    }
+    coverage::steps! {
+        fn schedule_proof_timeout(&mut self) {
+            self.0.action.schedule_event(LocalEvent::TimeoutAccept);
+        }
 
-    fn send_resource_proof_receipt_rpc(&mut self) {
-        self.0
-            .action
-            .send_candidate_proof_receipt(self.new_candidate());
+        fn send_resource_proof_receipt_rpc(&mut self, index: i32) {
+            self.0
+                .action
+                .send_candidate_proof_receipt(self.new_candidate(), index);
+        }
     }
 
     fn candidate(&self) -> Candidate {
-        unwrap!(self.routine_state().candidate).1
+        unwrap!(self.routine_state().candidate, "No candidate").1
     }
 
     fn waiting_candidate_name(&self) -> Name {
-        unwrap!(self.routine_state().candidate).0
+        unwrap!(self.routine_state().candidate, "No candidate").0
     }
 
     fn has_candidate(&self) -> bool {
@@ -283,7 +530,7 @@ impl<'a> StartResourceProof<'a> {
     }
 
     fn candidate_info(&self) -> CandidateInfo {
-        unwrap!(self.routine_state().candidate_info)
+        unwrap!(self.routine_state().candidate_info, "No candidate info")
     }
 
     fn has_candidate_info(&self) -> bool {
@@ -294,10 +541,24 @@ impl<'a> StartResourceProof<'a> {
         self.candidate_info().new_public_id
     }
 
-    fn cache_candidate_info_and_send_resource_proof(&mut self, info: CandidateInfo) {
-        self.routine_state_mut().candidate_info = Some(info);
+    fn proof_request(&self) -> ProofRequest {
+        unwrap!(self.routine_state().proof_request, "No proof request")
+    }
+
+    coverage::steps! {
+        fn cache_candidate_info_and_send_resource_proof(&mut self, info: CandidateInfo) {
+            let proof_request = self.0.action.new_resource_proof_request(info.new_public_id);
+
+            self.routine_state_mut().candidate_info = Some(info);
+            self.routine_state_mut().proof_request = Some(proof_request);
+            self.routine_state_mut().proof_parts_checked = 0;
+            self.send_resource_proof_request_rpc();
+        }
+    }
+
+    fn send_resource_proof_request_rpc(&mut self) {
         self.0
             .action
-            .send_candidate_proof_request(self.new_candidate());
+            .send_candidate_proof_request(self.new_candidate(), self.proof_request());
     }
 }
@@ -8,31 +8,41 @@
 // Software.
 
 use crate::{
-    state::{MemberState, ProcessElderChangeState, ProcessSplitState},
+    coverage,
+    state::{
+        MemberState, ProcessElderChangeState, ProcessSplitState,
+        StartMergeSplitAndChangeEldersState,
+    },
     utilities::{
-        ChangeElder, LocalEvent, Node, ParsecVote, Rpc, SectionInfo, TryResult, WaitedEvent,
+        ActionTriggered, ChangeElder, LocalEvent, Name, Node, ParsecVote, Rpc, SectionInfo,
+        TryResult, WaitedEvent,
     },
 };
+use std::mem;
 use unwrap::unwrap;
 
 #[derive(Debug, PartialEq)]
 pub struct StartMergeSplitAndChangeElders<'a>(pub &'a mut MemberState);
 
 impl<'a> StartMergeSplitAndChangeElders<'a> {
-    // TODO - remove the `allow` once we have a test for this method.
-    #[allow(dead_code)]
-    fn start_event_loop(&mut self) {
-        self.start_check_elder_timeout()
+    coverage::steps! {
+        // TODO - remove the `allow` once we have a test for this method.
+        #[allow(dead_code)]
+        fn start_event_loop(&mut self) {
+            self.start_check_elder_timeout()
+        }
     }
 
     pub fn try_next(&mut self, event: WaitedEvent) -> TryResult {
         match event {
             WaitedEvent::ParsecConsensus(vote) => self.try_consensus(&vote),
-            WaitedEvent::Rpc(rpc) => self.try_rpc(rpc),
+            WaitedEvent::Rpc(message) => self.try_rpc(message.rpc),
             WaitedEvent::LocalEvent(LocalEvent::TimeoutCheckElder) => {
                 self.vote_parsec_check_elder();
                 TryResult::Handled
             }
+            // Left over from a key generation that completed or was abandoned.
+            WaitedEvent::LocalEvent(LocalEvent::TimeoutDkg(_)) => TryResult::Handled,
             _ => TryResult::Unhandled,
         }
     }
@@ -47,6 +57,8 @@ impl<'a> StartMergeSplitAndChangeElders<'a> {
                 self.check_merge();
                 TryResult::Handled
             }
+            // Left over from a key generation that completed or was abandoned.
+            ParsecVote::DkgFailed(_) => TryResult::Handled,
             _ => TryResult::Unhandled,
         }
     }
@@ -54,7 +66,30 @@ impl<'a> StartMergeSplitAndChangeElders<'a> {
     fn try_rpc(&mut self, rpc: Rpc) -> TryResult {
         match rpc {
             Rpc::Merge(section_info) => {
-                self.vote_parsec_neighbour_merge(section_info);
+                if self.0.action.is_trusted_neighbour(section_info) {
+                    self.vote_parsec_neighbour_merge(section_info);
+                } else {
+                    coverage::step("StartMergeSplitAndChangeElders", "RejectMerge");
+                    self.reject_unexpected_authority();
+                }
+                TryResult::Handled
+            }
+            Rpc::NeighbourUpdate { info, proof } => {
+                if self.0.action.trust_neighbour_update(info, proof) {
+                    coverage::step("StartMergeSplitAndChangeElders", "TrustNeighbourUpdate");
+                } else {
+                    coverage::step("StartMergeSplitAndChangeElders", "RejectNeighbourUpdate");
+                    self.reject_unexpected_authority();
+                }
+                TryResult::Handled
+            }
+            // From a faster new elder, kept until our own elder change starts, or left over from
+            // a key generation that completed or was abandoned.
+            Rpc::DkgContribution { source, info, .. } => {
+                let _ = self
+                    .routine_state_mut()
+                    .early_dkg_contributions
+                    .insert((info, source));
                 TryResult::Handled
             }
 
@@ -62,8 +97,20 @@ impl<'a> StartMergeSplitAndChangeElders<'a> {
         }
     }
 
-    fn store_merge_infos(&mut self, merge_info: SectionInfo) {
-        self.0.action.store_merge_infos(merge_info);
+    coverage::steps! {
+        fn store_merge_infos(&mut self, merge_info: SectionInfo) {
+            self.0.action.store_merge_infos(merge_info);
+        }
+    }
+
+    fn reject_unexpected_authority(&mut self) {
+        self.0
+            .action
+            .action_triggered(ActionTriggered::UnexpectedAuthorityRejected);
+    }
+
+    fn routine_state_mut(&mut self) -> &mut StartMergeSplitAndChangeEldersState {
+        &mut self.0.start_merge_split_and_change_elders
     }
 
     fn has_merge_infos(&mut self) -> bool {
@@ -93,52 +140,72 @@ impl<'a> StartMergeSplitAndChangeElders<'a> {
                 if self.split_needed() {
                     self.concurrent_transition_to_process_split();
                 } else {
+                    coverage::step("StartMergeSplitAndChangeElders", "RestartTimeout");
                     self.start_check_elder_timeout();
                 }
             }
         }
     }
 
-    fn concurrent_transition_to_process_merge(&mut self) {
-        self.0.as_process_merge().start_event_loop()
-    }
+    coverage::steps! {
+        fn concurrent_transition_to_process_merge(&mut self) {
+            self.0.as_process_merge().start_event_loop()
+        }
 
-    fn concurrent_transition_to_process_split(&mut self) {
-        self.0.as_process_split().start_event_loop()
-    }
+        fn concurrent_transition_to_process_split(&mut self) {
+            self.0.as_process_split().start_event_loop()
+        }
 
-    fn concurrent_transition_to_process_elder_change(&mut self, change_elder: ChangeElder) {
-        self.0
-            .as_process_elder_change()
-            .start_event_loop(change_elder)
+        fn concurrent_transition_to_process_elder_change(&mut self, change_elder: ChangeElder) {
+            self.0
+                .as_process_elder_change()
+                .start_event_loop(change_elder)
+        }
     }
 
     fn transition_exit_process_elder_change(&mut self) {
         // TODO: ResourceProof_Cancel
-        // TODO: RelocatedNodeConnection_Reset
+        coverage::step(
+            "StartMergeSplitAndChangeElders",
+            "ResetRelocatedNodeConnection",
+        );
+        self.0.as_start_relocated_node_connection().reset();
+        coverage::step("StartMergeSplitAndChangeElders", "RestartTimeout");
         self.start_check_elder_timeout()
     }
 
-    fn transition_exit_process_split(&self) {
+    fn transition_exit_process_split(&mut self) {
         // TODO: ResourceProof_Cancel
-        // TODO: RelocatedNodeConnection_Reset
+        coverage::step(
+            "StartMergeSplitAndChangeElders",
+            "ResetRelocatedNodeConnection",
+        );
+        self.0.as_start_relocated_node_connection().reset();
+        coverage::step("StartMergeSplitAndChangeElders", "RestartTimeout");
         self.start_check_elder_timeout()
     }
 
-    fn transition_exit_process_merge(&self) {
+    fn transition_exit_process_merge(&mut self) {
         // TODO: ResourceProof_Cancel
-        // TODO: RelocatedNodeConnection_Reset
+        coverage::step(
+            "StartMergeSplitAndChangeElders",
+            "ResetRelocatedNodeConnection",
+        );
+        self.0.as_start_relocated_node_connection().reset();
+        coverage::step("StartMergeSplitAndChangeElders", "RestartTimeout");
         self.start_check_elder_timeout()
     }
 
-    fn vote_parsec_check_elder(&mut self) {
-        self.0.action.vote_parsec(ParsecVote::CheckElder);
-    }
+    coverage::steps! {
+        fn vote_parsec_check_elder(&mut self) {
+            self.0.action.vote_parsec(ParsecVote::CheckElder);
+        }
 
-    fn vote_parsec_neighbour_merge(&mut self, section_info: SectionInfo) {
-        self.0
-            .action
-            .vote_parsec(ParsecVote::NeighbourMerge(section_info));
+        fn vote_parsec_neighbour_merge(&mut self, section_info: SectionInfo) {
+            self.0
+                .action
+                .vote_parsec(ParsecVote::NeighbourMerge(section_info));
+        }
     }
 
     fn start_check_elder_timeout(&self) {
@@ -153,49 +220,200 @@ impl<'a> ProcessElderChange<'a> {
     pub fn start_event_loop(&mut self, change_elder: ChangeElder) {
         self.routine_state_mut().is_active = true;
         self.routine_state_mut().change_elder = Some(change_elder.clone());
+        self.store_early_dkg_contributions();
         self.vote_for_elder_change(change_elder)
     }
 
-    fn exit_event_loop(&mut self) {
-        self.routine_state_mut().is_active = false;
-        self.routine_state_mut().change_elder = None;
-        self.0
-            .as_start_merge_split_and_change_elders()
-            .transition_exit_process_elder_change()
+    coverage::steps! {
+        fn exit_event_loop(&mut self) {
+            *self.routine_state_mut() = ProcessElderChangeState::default();
+            self.0
+                .as_start_merge_split_and_change_elders()
+                .transition_exit_process_elder_change()
+        }
     }
 
     pub fn try_next(&mut self, event: WaitedEvent) -> TryResult {
         match event {
             WaitedEvent::ParsecConsensus(vote) => self.try_consensus(&vote),
-            _ => TryResult::Unhandled,
+            WaitedEvent::Rpc(message) => self.try_rpc(message.rpc),
+            WaitedEvent::LocalEvent(local_event) => self.try_local_event(local_event),
         }
     }
 
     fn try_consensus(&mut self, vote: &ParsecVote) -> TryResult {
+        if self.routine_state().dkg_started {
+            return self.try_dkg_consensus(vote);
+        }
+
         if !self.routine_state().wait_votes.contains(&vote) {
             return TryResult::Unhandled;
         }
 
+        coverage::step("ProcessElderChange", "OneVoteConsensused");
         let wait_votes = &mut self.routine_state_mut().wait_votes;
         wait_votes.retain(|wait_vote| wait_vote != vote);
 
         if wait_votes.is_empty() {
-            self.mark_elder_change();
-            self.exit_event_loop();
+            self.start_dkg();
         }
         TryResult::Handled
     }
 
-    fn vote_for_elder_change(&mut self, change_elder: ChangeElder) {
-        let votes = self.0.action.get_elder_change_votes(&change_elder);
-        self.routine_state_mut().change_elder = Some(change_elder);
-        self.routine_state_mut().wait_votes = votes;
+    // Consensus is reached by the elders, whether or not we are one of the new ones: apply it
+    // even if our own key generation did not complete.
+    fn try_dkg_consensus(&mut self, vote: &ParsecVote) -> TryResult {
+        match vote {
+            ParsecVote::NewSectionInfo(info) if *info == self.new_section() => {
+                self.mark_elder_change();
+                self.exit_event_loop();
+                TryResult::Handled
+            }
+            ParsecVote::DkgFailed(info) if *info == self.new_section() => {
+                self.abandon_elder_change();
+                TryResult::Handled
+            }
+            _ => TryResult::Unhandled,
+        }
+    }
+
+    fn try_rpc(&mut self, rpc: Rpc) -> TryResult {
+        match rpc {
+            Rpc::DkgContribution { source, info, .. } if info == self.new_section() => {
+                coverage::step("ProcessElderChange", "StoreContribution");
+                self.store_dkg_contribution(source);
+                TryResult::Handled
+            }
+            _ => TryResult::Unhandled,
+        }
+    }
+
+    fn try_local_event(&mut self, local_event: LocalEvent) -> TryResult {
+        match local_event {
+            LocalEvent::TimeoutDkg(info) if info == self.new_section() => {
+                if self.is_new_elder() && !self.routine_state().dkg_complete {
+                    coverage::step("ProcessElderChange", "VoteDkgFailed");
+                    self.0.action.vote_parsec(ParsecVote::DkgFailed(info));
+                }
+                TryResult::Handled
+            }
+            _ => TryResult::Unhandled,
+        }
+    }
+
+    coverage::steps! {
+        fn vote_for_elder_change(&mut self, change_elder: ChangeElder) {
+            let votes = self.0.action.get_elder_change_votes(&change_elder);
+            self.routine_state_mut().change_elder = Some(change_elder);
+            self.routine_state_mut().wait_votes = votes;
 
-        for vote in &self.routine_state().wait_votes {
-            self.0.action.vote_parsec(*vote);
+            for vote in &self.routine_state().wait_votes {
+                self.0.action.vote_parsec(*vote);
+            }
         }
     }
 
+    // The new elders are agreed: generate their section key before voting NewSectionInfo.
+    fn start_dkg(&mut self) {
+        let new_section = self.new_section();
+        let our_name = self.0.action.our_name();
+
+        coverage::step("ProcessElderChange", "StartDkg");
+        self.routine_state_mut().dkg_started = true;
+        if !self.is_new_elder() {
+            // Not generating the key: wait for the new elders to consensus its outcome.
+            return;
+        }
+
+        for participant in new_section.2.nodes() {
+            if participant.name() != our_name {
+                self.0.action.send_rpc(Rpc::DkgContribution {
+                    source: our_name,
+                    destination: participant.name(),
+                    info: new_section,
+                });
+            }
+        }
+        self.store_dkg_contribution(our_name);
+
+        self.check_dkg_complete();
+        if !self.routine_state().dkg_complete {
+            self.0
+                .action
+                .schedule_event(LocalEvent::TimeoutDkg(new_section));
+        }
+    }
+
+    fn store_early_dkg_contributions(&mut self) {
+        let new_section = self.new_section();
+        let early_dkg_contributions = mem::take(
+            &mut self
+                .0
+                .start_merge_split_and_change_elders
+                .early_dkg_contributions,
+        );
+        for (info, source) in early_dkg_contributions {
+            if info == new_section {
+                self.store_dkg_contribution(source);
+            }
+        }
+    }
+
+    // Contributions may arrive before our own key generation started.
+    fn store_dkg_contribution(&mut self, source: Name) {
+        if !self.new_section().2.contains(source) {
+            return;
+        }
+
+        let _ = self.routine_state_mut().dkg_contributions.insert(source);
+        if self.routine_state().dkg_started {
+            self.check_dkg_complete();
+        }
+    }
+
+    fn check_dkg_complete(&mut self) {
+        let new_section = self.new_section();
+        let routine_state = self.routine_state();
+        if routine_state.dkg_complete
+            || !new_section
+                .2
+                .nodes()
+                .iter()
+                .all(|node| routine_state.dkg_contributions.contains(&node.name()))
+        {
+            return;
+        }
+
+        coverage::step("ProcessElderChange", "VoteNewSectionInfo");
+        let vote = ParsecVote::NewSectionInfo(new_section);
+        self.routine_state_mut().dkg_complete = true;
+        self.routine_state_mut().wait_votes = vec![vote];
+        self.0.action.vote_parsec(vote);
+    }
+
+    coverage::steps! {
+        // A new elder did not contribute in time: leave our elders unchanged and check again later.
+        fn abandon_elder_change(&mut self) {
+            let new_section = self.new_section();
+            self.0
+                .action
+                .action_triggered(ActionTriggered::DkgFailed(new_section));
+            self.exit_event_loop();
+        }
+    }
+
+    fn is_new_elder(&self) -> bool {
+        self.new_section().2.contains(self.0.action.our_name())
+    }
+
+    fn new_section(&self) -> SectionInfo {
+        unwrap!(
+            self.routine_state().change_elder.as_ref(),
+            "No elder change"
+        )
+        .new_section
+    }
+
     fn routine_state(&self) -> &ProcessElderChangeState {
         &self
             .0
@@ -211,7 +429,11 @@ impl<'a> ProcessElderChange<'a> {
     }
 
     fn mark_elder_change(&mut self) {
-        let change_elder = unwrap!(self.routine_state_mut().change_elder.take());
+        let change_elder = unwrap!(
+            self.routine_state_mut().change_elder.take(),
+            "No elder change"
+        );
+        coverage::step("ProcessElderChange", "MarkNewElderAdults");
         self.0.action.mark_elder_change(change_elder);
     }
 }
@@ -222,15 +444,18 @@ pub struct ProcessMerge<'a>(pub &'a mut MemberState);
 impl<'a> ProcessMerge<'a> {
     pub fn start_event_loop(&mut self) {
         self.set_is_active(true);
+        coverage::step("ProcessMerge", "SendMergeRpc");
         self.0.action.send_merge_rpc();
         self.check_sibling_merge_info();
     }
 
-    fn exit_event_loop(&mut self) {
-        self.set_is_active(false);
-        self.0
-            .as_start_merge_split_and_change_elders()
-            .transition_exit_process_merge()
+    coverage::steps! {
+        fn exit_event_loop(&mut self) {
+            self.set_is_active(false);
+            self.0
+                .as_start_merge_split_and_change_elders()
+                .transition_exit_process_merge()
+        }
     }
 
     fn set_is_active(&mut self, is_active: bool) {
@@ -241,6 +466,7 @@ impl<'a> ProcessMerge<'a> {
 
     fn check_sibling_merge_info(&self) {
         if self.0.action.has_sibling_merge_info() {
+            coverage::step("ProcessMerge", "VotForNewSectionInfo");
             let new_section = self.0.action.merge_sibling_info_to_new_section();
             self.0
                 .action
@@ -258,12 +484,14 @@ impl<'a> ProcessMerge<'a> {
     fn try_consensus(&mut self, vote: ParsecVote) -> TryResult {
         match vote {
             ParsecVote::NewSectionInfo(_) => {
+                coverage::step("ProcessMerge", "CompleteMerge");
                 self.0.action.complete_merge();
                 self.update_elder_status();
                 self.exit_event_loop();
                 TryResult::Handled
             }
             ParsecVote::NeighbourMerge(merge_info) => {
+                coverage::step("ProcessMerge", "SetNeighbourMerge");
                 self.0.action.store_merge_infos(merge_info);
                 self.check_sibling_merge_info();
                 TryResult::Handled
@@ -272,8 +500,10 @@ impl<'a> ProcessMerge<'a> {
         }
     }
 
-    fn update_elder_status(&self) {
-        // TODO
+    coverage::steps! {
+        fn update_elder_status(&self) {
+            // TODO
+        }
     }
 }
 
@@ -286,11 +516,13 @@ impl<'a> ProcessSplit<'a> {
         self.vote_for_split_sections();
     }
 
-    fn exit_event_loop(&mut self) {
-        self.routine_state_mut().is_active = false;
-        self.0
-            .as_start_merge_split_and_change_elders()
-            .transition_exit_process_split()
+    coverage::steps! {
+        fn exit_event_loop(&mut self) {
+            self.routine_state_mut().is_active = false;
+            self.0
+                .as_start_merge_split_and_change_elders()
+                .transition_exit_process_split()
+        }
     }
 
     pub fn try_next(&mut self, event: WaitedEvent) -> TryResult {
@@ -305,6 +537,7 @@ impl<'a> ProcessSplit<'a> {
             return TryResult::Unhandled;
         }
 
+        coverage::step("ProcessSplit", "OneVoteConsensused");
         let wait_votes = &mut self.routine_state_mut().wait_votes;
         wait_votes.retain(|wait_vote| wait_vote != vote);
 
@@ -316,12 +549,14 @@ impl<'a> ProcessSplit<'a> {
         TryResult::Handled
     }
 
-    fn vote_for_split_sections(&mut self) {
-        let votes = self.0.action.get_section_split_votes();
-        self.routine_state_mut().wait_votes = votes;
+    coverage::steps! {
+        fn vote_for_split_sections(&mut self) {
+            let votes = self.0.action.get_section_split_votes();
+            self.routine_state_mut().wait_votes = votes;
 
-        for vote in &self.routine_state().wait_votes {
-            self.0.action.vote_parsec(*vote);
+            for vote in &self.routine_state().wait_votes {
+                self.0.action.vote_parsec(*vote);
+            }
         }
     }
 
@@ -339,13 +574,15 @@ impl<'a> ProcessSplit<'a> {
             .sub_routine_process_split
     }
 
-    fn complete_split(&self) {
-        // TODO: start parsec with new genesis ...
-        self.0.action.complete_split();
-    }
+    coverage::steps! {
+        fn complete_split(&self) {
+            // TODO: start parsec with new genesis ...
+            self.0.action.complete_split();
+        }
 
-    fn mark_elder_change(&mut self) {
-        // TODO: update elder status
+        fn mark_elder_change(&mut self) {
+            // TODO: update elder status
+        }
     }
 }
 
@@ -392,20 +629,22 @@ impl<'a> CheckOnlineOffline<'a> {
         }
     }
 
-    fn vote_parsec_offline(&mut self, node: Node) {
-        self.0.action.vote_parsec(ParsecVote::Offline(node));
-    }
+    coverage::steps! {
+        fn vote_parsec_offline(&mut self, node: Node) {
+            self.0.action.vote_parsec(ParsecVote::Offline(node));
+        }
 
-    fn vote_parsec_back_online(&mut self, node: Node) {
-        self.0.action.vote_parsec(ParsecVote::BackOnline(node));
-    }
+        fn vote_parsec_back_online(&mut self, node: Node) {
+            self.0.action.vote_parsec(ParsecVote::BackOnline(node));
+        }
 
-    fn make_node_offline(&mut self, node: Node) {
-        self.0.action.set_node_offline_state(node);
-    }
+        fn make_node_offline(&mut self, node: Node) {
+            self.0.action.set_node_offline_state(node);
+        }
 
-    /// A member of a section that was lost connection to became offline, but is now online again
-    fn make_node_back_online(&mut self, node: Node) {
-        self.0.action.set_node_back_online_state(node);
+        /// A member of a section that was lost connection to became offline, but is now online again
+        fn make_node_back_online(&mut self, node: Node) {
+            self.0.action.set_node_back_online_state(node);
+        }
     }
 }
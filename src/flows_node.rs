@@ -8,9 +8,12 @@
 // Software.
 
 use crate::{
-    state::JoiningState,
+    actions::Action,
+    coverage,
+    state::{JoiningProofProgress, JoiningRelocateCandidateState, JoiningState},
     utilities::{
-        GenesisPfxInfo, LocalEvent, Name, ProofRequest, RelocatedInfo, Rpc, TryResult, WaitedEvent,
+        is_quorum, GenesisPfxInfo, JoiningFailure, LocalEvent, Name, Node, Proof, ProofRequest,
+        RelocatedInfo, Rpc, SectionInfo, SectionProof, TryResult, WaitedEvent,
     },
 };
 use unwrap::unwrap;
@@ -19,16 +22,24 @@ use unwrap::unwrap;
 pub struct JoiningRelocateCandidate<'a>(pub &'a mut JoiningState);
 
 impl<'a> JoiningRelocateCandidate<'a> {
-    pub fn start_event_loop(&mut self, relocated_info: RelocatedInfo) {
-        self.0.join_routine.relocated_info = Some(relocated_info);
+    coverage::steps! {
+        pub fn start_event_loop(&mut self, relocated_info: RelocatedInfo) {
+            self.0.join_routine.relocated_info = Some(relocated_info);
+            self.0.action.trust_section(relocated_info.section_info);
 
-        self.connect_or_send_candidate_info();
-        self.start_refused_timeout();
+            self.connect_or_send_candidate_info();
+            self.start_refused_timeout();
+        }
     }
 
     pub fn try_next(&mut self, event: WaitedEvent) -> TryResult {
+        if self.has_exited() {
+            self.discard();
+            return TryResult::Handled;
+        }
+
         let result = match event {
-            WaitedEvent::Rpc(rpc) => self.try_rpc(rpc),
+            WaitedEvent::Rpc(message) => self.try_rpc(message.rpc),
             WaitedEvent::LocalEvent(local_event) => self.try_local_event(local_event),
             _ => TryResult::Unhandled,
         };
@@ -49,11 +60,17 @@ impl<'a> JoiningRelocateCandidate<'a> {
         }
 
         match rpc {
-            Rpc::NodeApproval(_, info) => {
-                self.exit(info);
+            Rpc::NodeApproval { source, info, .. } => {
+                self.rpc_approval(source, info);
+                TryResult::Handled
+            }
+            Rpc::SectionUpdate { info, proof, .. } => {
+                self.rpc_section_update(info, proof);
                 TryResult::Handled
             }
             Rpc::ConnectionInfoResponse { source, .. } => {
+                coverage::step("JoiningRelocateCandidate", "ConnectAndSendCandidateInfo");
+                self.0.action.mark_connected(source);
                 self.send_candidate_info(source);
                 TryResult::Handled
             }
@@ -61,8 +78,8 @@ impl<'a> JoiningRelocateCandidate<'a> {
                 self.start_compute_resource_proof(source, proof);
                 TryResult::Handled
             }
-            Rpc::ResourceProofReceipt { source, .. } => {
-                self.send_next_proof_response(source);
+            Rpc::ResourceProofReceipt { source, index, .. } => {
+                self.receipt_proof_response(source, index);
                 TryResult::Handled
             }
             _ => TryResult::Unhandled,
@@ -72,53 +89,194 @@ impl<'a> JoiningRelocateCandidate<'a> {
     fn try_local_event(&mut self, local_event: LocalEvent) -> TryResult {
         match local_event {
             LocalEvent::ResourceProofForElderReady(source) => {
-                self.send_next_proof_response(source);
+                self.start_send_proof_responses(source);
                 TryResult::Handled
             }
             LocalEvent::JoiningTimeoutResendInfo => {
                 self.connect_or_send_candidate_info();
                 TryResult::Handled
             }
+            LocalEvent::JoiningTimeoutProofRefused => {
+                self.exit_failure(JoiningFailure::ProofRefusedTimeout);
+                TryResult::Handled
+            }
             _ => TryResult::Unhandled,
         }
     }
 
-    fn exit(&mut self, info: GenesisPfxInfo) {
-        self.0.join_routine.routine_complete_output = Some(info);
+    coverage::steps! {
+        fn rpc_section_update(&mut self, info: SectionInfo, proof: SectionProof) {
+            if !self.0.action.extend_section_proof_chain(info, proof) {
+                self.discard();
+            }
+        }
+    }
+
+    fn rpc_approval(&mut self, source: Name, info: GenesisPfxInfo) {
+        // The section we were relocated to, or a version we caught up with since.
+        if !self.0.action.is_trusted_section(info.0) {
+            self.discard();
+            return;
+        }
+
+        let elders = self.approving_elders(info.0);
+        let from_elder = elders.iter().any(|elder| elder.name() == source);
+        let conflicting = self
+            .routine_state()
+            .approvals
+            .get(&source)
+            .is_some_and(|approved| *approved != info);
+
+        if !from_elder || conflicting {
+            self.discard();
+            return;
+        }
+
+        let _ = self.routine_state_mut().approvals.insert(source, info);
+        self.check_approval_quorum(info, elders.len());
+    }
+
+    // The elders we know for the section we were relocated to, or those listed by a later
+    // version we caught up with.
+    fn approving_elders(&self, info: SectionInfo) -> Vec<Node> {
+        let relocated_info = unwrap!(self.routine_state().relocated_info, "Not relocated");
+        if info == relocated_info.section_info {
+            self.0.action.get_section_elders(info)
+        } else {
+            info.2.nodes().to_vec()
+        }
+    }
+
+    fn check_approval_quorum(&mut self, info: GenesisPfxInfo, elder_count: usize) {
+        let approved_count = self
+            .routine_state()
+            .approvals
+            .values()
+            .filter(|approved| **approved == info)
+            .count();
+
+        if is_quorum(approved_count, elder_count) {
+            self.exit(info);
+        }
+    }
+
+    coverage::steps! {
+        fn exit(&mut self, info: GenesisPfxInfo) {
+            self.0.join_routine.routine_complete_output = Some(info);
+        }
+
+        fn exit_failure(&mut self, failure: JoiningFailure) {
+            self.0.join_routine.routine_failure_output = Some(failure);
+        }
+    }
+
+    fn has_exited(&self) -> bool {
+        self.0.join_routine.routine_complete_output.is_some()
+            || self.0.join_routine.routine_failure_output.is_some()
     }
 
     fn discard(&mut self) {}
 
+    fn routine_state(&self) -> &JoiningRelocateCandidateState {
+        &self.0.join_routine
+    }
+
+    fn routine_state_mut(&mut self) -> &mut JoiningRelocateCandidateState {
+        &mut self.0.join_routine
+    }
+
+    fn has_resource_proof_for(&self, name: Name) -> bool {
+        self.routine_state().proofs_progress.contains_key(&name)
+    }
+
+    fn receipt_proof_response(&mut self, source: Name, index: i32) {
+        // Only the receipt for the part we last sent moves the proof on: a part resent before its
+        // receipt arrived is receipted twice, and the second receipt must not skip the next part.
+        let receipted = match self.routine_state().proofs_progress.get(&source) {
+            Some(JoiningProofProgress::Sent(part)) => part.index == index,
+            _ => false,
+        };
+        if !receipted {
+            self.discard();
+            return;
+        }
+
+        let _ = self.routine_state_mut().need_resend_proofs.remove(&source);
+
+        match self.0.action.get_next_resource_proof_part(source) {
+            Some(next_part) => self.send_proof_response(source, next_part),
+            None => self.set_proof_progress(source, JoiningProofProgress::Completed),
+        }
+    }
+
+    fn start_send_proof_responses(&mut self, source: Name) {
+        match self.routine_state().proofs_progress.get(&source) {
+            None | Some(JoiningProofProgress::Computing) => self.send_next_proof_response(source),
+            Some(JoiningProofProgress::Sent(_)) | Some(JoiningProofProgress::Completed) => {
+                self.discard()
+            }
+        }
+    }
+
     fn send_next_proof_response(&mut self, source: Name) {
         if let Some(next_part) = self.0.action.get_next_resource_proof_part(source) {
-            self.0
-                .action
-                .send_resource_proof_response(source, next_part);
+            self.send_proof_response(source, next_part);
         }
     }
 
+    fn resend_proof_responses(&mut self) {
+        let need_resend_proofs = self.routine_state().need_resend_proofs.clone();
+        for source in need_resend_proofs {
+            if let Some(part) = self.0.action.get_current_resource_proof_part(source) {
+                self.0.action.send_resource_proof_response(source, part);
+            }
+        }
+    }
+
+    coverage::steps! {
+        fn send_proof_response(&mut self, source: Name, part: Proof) {
+            self.set_proof_progress(source, JoiningProofProgress::Sent(part));
+            let _ = self.routine_state_mut().need_resend_proofs.insert(source);
+            self.0.action.send_resource_proof_response(source, part);
+        }
+    }
+
+    fn set_proof_progress(&mut self, source: Name, progress: JoiningProofProgress) {
+        let _ = self
+            .routine_state_mut()
+            .proofs_progress
+            .insert(source, progress);
+    }
+
     fn send_candidate_info(&mut self, destination: Name) {
-        self.0
-            .action
-            .send_candidate_info(destination, unwrap!(self.0.join_routine.relocated_info));
+        self.0.action.send_candidate_info(
+            destination,
+            unwrap!(self.0.join_routine.relocated_info, "Not relocated"),
+        );
     }
 
-    fn connect_or_send_candidate_info(&mut self) {
-        let relocated_info = unwrap!(self.0.join_routine.relocated_info);
+    coverage::steps! {
+        fn connect_or_send_candidate_info(&mut self) {
+            let relocated_info = unwrap!(self.0.join_routine.relocated_info, "Not relocated");
 
-        let (connected, unconnected) = self.0.action.get_connected_and_unconnected(relocated_info);
+            let (connected, unconnected) = self.0.action.get_connected_and_unconnected(relocated_info);
 
-        for name in unconnected {
-            self.0.action.send_connection_info_request(name);
-        }
+            for name in unconnected {
+                self.0.action.send_connection_info_request(name);
+            }
 
-        for name in connected {
-            self.0.action.send_candidate_info(name, relocated_info);
-        }
+            for name in connected {
+                if !self.has_resource_proof_for(name) {
+                    self.0.action.send_candidate_info(name, relocated_info);
+                }
+            }
 
-        self.0
-            .action
-            .schedule_event(LocalEvent::JoiningTimeoutResendInfo);
+            self.resend_proof_responses();
+
+            self.0
+                .action
+                .schedule_event(LocalEvent::JoiningTimeoutResendInfo);
+        }
     }
 
     fn start_refused_timeout(&mut self) {
@@ -128,6 +286,61 @@ impl<'a> JoiningRelocateCandidate<'a> {
     }
 
     fn start_compute_resource_proof(&mut self, source: Name, proof: ProofRequest) {
+        if self.has_resource_proof_for(source) {
+            self.discard();
+            return;
+        }
+
+        coverage::step(
+            "JoiningRelocateCandidate",
+            "StartComputeResourceProofForElder",
+        );
+        self.set_proof_progress(source, JoiningProofProgress::Computing);
         self.0.action.start_compute_resource_proof(source, proof);
     }
 }
+
+// Connections handling shared by every node: members and joining nodes alike.
+#[derive(Debug, PartialEq)]
+pub struct StartConnectionHandler<'a>(pub &'a Action);
+
+impl<'a> StartConnectionHandler<'a> {
+    pub fn try_next(&mut self, event: WaitedEvent) -> TryResult {
+        match event {
+            WaitedEvent::Rpc(message) => self.try_rpc(message.rpc),
+            WaitedEvent::LocalEvent(local_event) => self.try_local_event(local_event),
+            _ => TryResult::Unhandled,
+        }
+    }
+
+    fn try_rpc(&mut self, rpc: Rpc) -> TryResult {
+        match rpc {
+            Rpc::ConnectionInfoRequest {
+                source,
+                destination,
+                ..
+            } if self.0.is_our_name(destination) => {
+                coverage::step("StartConnectionHandler", "OnConnectionInfoRequest");
+                self.send_connection_info_response(source);
+                self.0.mark_connected(source);
+                TryResult::Handled
+            }
+            _ => TryResult::Unhandled,
+        }
+    }
+
+    fn try_local_event(&mut self, local_event: LocalEvent) -> TryResult {
+        match local_event {
+            LocalEvent::NodeDisconnected(name) => {
+                coverage::step("StartConnectionHandler", "OnNodeDisconnected");
+                self.0.mark_disconnected(name);
+                TryResult::Handled
+            }
+            _ => TryResult::Unhandled,
+        }
+    }
+
+    fn send_connection_info_response(&mut self, destination: Name) {
+        self.0.send_connection_info_response(destination);
+    }
+}
@@ -8,19 +8,24 @@
 // Software.
 
 use crate::{
+    coverage,
     state::{MemberState, StartRelocateSrcState},
-    utilities::{Candidate, LocalEvent, ParsecVote, RelocatedInfo, Rpc, TryResult, WaitedEvent},
+    utilities::{
+        ActionTriggered, Authority, Candidate, LocalEvent, Message, ParsecVote, RelocatedInfo, Rpc,
+        TryResult, WaitedEvent,
+    },
 };
-use unwrap::unwrap;
 
 #[derive(Debug, PartialEq)]
 pub struct StartDecidesOnNodeToRelocate<'a>(pub &'a mut MemberState);
 
 impl<'a> StartDecidesOnNodeToRelocate<'a> {
-    // TODO - remove the `allow` once we have a test for this method.
-    #[allow(dead_code)]
-    fn start_event_loop(&mut self) {
-        self.start_work_unit_timeout()
+    coverage::steps! {
+        // TODO - remove the `allow` once we have a test for this method.
+        #[allow(dead_code)]
+        fn start_event_loop(&mut self) {
+            self.start_work_unit_timeout()
+        }
     }
 
     pub fn try_next(&mut self, event: WaitedEvent) -> TryResult {
@@ -68,12 +73,14 @@ impl<'a> StartDecidesOnNodeToRelocate<'a> {
     //
     // Actions
     //
-    fn increment_nodes_work_units(&mut self) {
-        self.0.action.increment_nodes_work_units();
-    }
+    coverage::steps! {
+        fn increment_nodes_work_units(&mut self) {
+            self.0.action.increment_nodes_work_units();
+        }
 
-    fn set_relocating_candidate(&mut self, candidate: Candidate) {
-        self.0.action.set_candidate_relocating_state(candidate);
+        fn set_relocating_candidate(&mut self, candidate: Candidate) {
+            self.0.action.set_candidate_relocating_state(candidate);
+        }
     }
 
     fn start_work_unit_timeout(&mut self) {
@@ -84,8 +91,10 @@ impl<'a> StartDecidesOnNodeToRelocate<'a> {
     // Votes
     //
 
-    fn vote_parsec_work_unit_increment(&mut self) {
-        self.0.action.vote_parsec(ParsecVote::WorkUnitIncrement);
+    coverage::steps! {
+        fn vote_parsec_work_unit_increment(&mut self) {
+            self.0.action.vote_parsec(ParsecVote::WorkUnitIncrement);
+        }
     }
 }
 
@@ -94,16 +103,18 @@ pub struct StartRelocateSrc<'a>(pub &'a mut MemberState);
 
 // StartRelocateSrc Sub Routine
 impl<'a> StartRelocateSrc<'a> {
-    // TODO - remove the `allow` once we have a test for this method.
-    #[allow(dead_code)]
-    fn start_event_loop(&mut self) {
-        self.start_check_relocate_timeout()
+    coverage::steps! {
+        // TODO - remove the `allow` once we have a test for this method.
+        #[allow(dead_code)]
+        fn start_event_loop(&mut self) {
+            self.start_check_relocate_timeout()
+        }
     }
 
     pub fn try_next(&mut self, event: WaitedEvent) -> TryResult {
         match event {
             WaitedEvent::LocalEvent(local_event) => self.try_local_event(local_event),
-            WaitedEvent::Rpc(rpc) => self.try_rpc(rpc),
+            WaitedEvent::Rpc(message) => self.try_rpc(message),
             WaitedEvent::ParsecConsensus(vote) => self.try_consensus(vote),
         }
     }
@@ -119,8 +130,15 @@ impl<'a> StartRelocateSrc<'a> {
         }
     }
 
-    fn try_rpc(&mut self, rpc: Rpc) -> TryResult {
-        match rpc {
+    fn try_rpc(&mut self, message: Message) -> TryResult {
+        match message.rpc {
+            Rpc::RefuseCandidate(candidate)
+            | Rpc::RelocateResponse(RelocatedInfo { candidate, .. })
+                if !self.is_from_relocation_target(candidate, message.source) =>
+            {
+                self.reject_unexpected_authority();
+                TryResult::Handled
+            }
             Rpc::RefuseCandidate(candidate) => {
                 self.vote_parsec_refuse_candidate(candidate);
                 TryResult::Handled
@@ -156,29 +174,47 @@ impl<'a> StartRelocateSrc<'a> {
     }
 
     fn check_need_relocate(&mut self) {
-        if let Some((candidate, _)) = self
+        if let Some((candidate, target)) = self
             .0
             .action
             .get_best_relocating_node_and_target(&self.routine_state().already_relocating)
         {
-            self.0.action.send_rpc(Rpc::ExpectCandidate(candidate));
+            coverage::step("StartRelocateSrc", "SendExpectCandidate");
+            self.0
+                .action
+                .send_section_rpc(Authority::Section(target), Rpc::ExpectCandidate(candidate));
             let inserted = self
                 .routine_state_mut()
                 .already_relocating
                 .insert(candidate, 0);
-            assert!(inserted.is_none());
+            assert!(inserted.is_none(), "Candidate already relocating");
+            let _ = self
+                .routine_state_mut()
+                .relocation_targets
+                .insert(candidate, target);
         }
     }
 
-    fn update_wait_and_allow_resend(&mut self) {
-        let new_already_relocating = self
-            .routine_state()
-            .already_relocating
-            .iter()
-            .map(|(node, count)| (*node, *count + 1))
-            .filter(|(_, count)| *count < 3)
-            .collect();
-        self.routine_state_mut().already_relocating = new_already_relocating;
+    // Without a target we are lagging behind the other elders: any section may answer and
+    // consensus decides.
+    fn is_from_relocation_target(&self, candidate: Candidate, source: Authority) -> bool {
+        match self.routine_state().relocation_targets.get(&candidate) {
+            Some(target) => source == Authority::Section(*target),
+            None => source.is_section(),
+        }
+    }
+
+    coverage::steps! {
+        fn update_wait_and_allow_resend(&mut self) {
+            let new_already_relocating = self
+                .routine_state()
+                .already_relocating
+                .iter()
+                .map(|(node, count)| (*node, *count + 1))
+                .filter(|(_, count)| *count < 3)
+                .collect();
+            self.routine_state_mut().already_relocating = new_already_relocating;
+        }
     }
 
     fn check_is_our_relocating_node(&mut self, vote: ParsecVote, candidate: Candidate) {
@@ -189,20 +225,28 @@ impl<'a> StartRelocateSrc<'a> {
                 _ => panic!("Unexpected vote"),
             }
         } else {
+            coverage::step("StartRelocateSrc", "DiscardVote");
             self.discard()
         }
     }
 
-    fn allow_resend(&mut self, candidate: Candidate) {
-        unwrap!(self
-            .routine_state_mut()
-            .already_relocating
-            .remove(&candidate));
-    }
+    coverage::steps! {
+        // Already allowed if the wait expired, or if we never asked: the refusal is then stale.
+        fn allow_resend(&mut self, candidate: Candidate) {
+            if self
+                .routine_state_mut()
+                .already_relocating
+                .remove(&candidate)
+                .is_none()
+            {
+                self.discard()
+            }
+        }
 
-    fn set_relocated_and_prepare_info(&mut self, info: RelocatedInfo) {
-        self.0.action.set_candidate_relocated_state(info);
-        self.0.action.vote_parsec(ParsecVote::RelocatedInfo(info));
+        fn set_relocated_and_prepare_info(&mut self, info: RelocatedInfo) {
+            self.0.action.set_candidate_relocated_state(info);
+            self.0.action.vote_parsec(ParsecVote::RelocatedInfo(info));
+        }
     }
 
     //
@@ -227,8 +271,20 @@ impl<'a> StartRelocateSrc<'a> {
             .schedule_event(LocalEvent::TimeoutCheckRelocate);
     }
 
-    fn purge_node_info(&mut self, info: RelocatedInfo) {
-        self.0.action.purge_node_info(info.candidate.name());
+    coverage::steps! {
+        fn purge_node_info(&mut self, info: RelocatedInfo) {
+            let _ = self
+                .routine_state_mut()
+                .relocation_targets
+                .remove(&info.candidate);
+            self.0.action.purge_node_info(info.candidate.name());
+        }
+
+        fn reject_unexpected_authority(&mut self) {
+            self.0
+                .action
+                .action_triggered(ActionTriggered::UnexpectedAuthorityRejected);
+        }
     }
 
     fn discard(&mut self) {}
@@ -237,27 +293,35 @@ impl<'a> StartRelocateSrc<'a> {
     // RPCs
     //
 
-    fn send_candidate_relocated_info_rpc(&mut self, info: RelocatedInfo) {
-        self.0.action.send_rpc(Rpc::RelocatedInfo(info));
+    coverage::steps! {
+        fn send_candidate_relocated_info_rpc(&mut self, info: RelocatedInfo) {
+            let signed_info = self.0.action.sign_relocated_info(info);
+            self.0.action.send_section_rpc(
+                Authority::Node(info.candidate.name()),
+                Rpc::RelocatedInfo(signed_info),
+            );
+        }
     }
 
     //
     // Votes
     //
 
-    fn vote_parsec_check_relocate(&mut self) {
-        self.0.action.vote_parsec(ParsecVote::CheckRelocate);
-    }
+    coverage::steps! {
+        fn vote_parsec_check_relocate(&mut self) {
+            self.0.action.vote_parsec(ParsecVote::CheckRelocate);
+        }
 
-    fn vote_parsec_refuse_candidate(&mut self, candidate: Candidate) {
-        self.0
-            .action
-            .vote_parsec(ParsecVote::RefuseCandidate(candidate));
-    }
+        fn vote_parsec_refuse_candidate(&mut self, candidate: Candidate) {
+            self.0
+                .action
+                .vote_parsec(ParsecVote::RefuseCandidate(candidate));
+        }
 
-    fn vote_parsec_relocation_response(&mut self, info: RelocatedInfo) {
-        self.0
-            .action
-            .vote_parsec(ParsecVote::RelocateResponse(info));
+        fn vote_parsec_relocation_response(&mut self, info: RelocatedInfo) {
+            self.0
+                .action
+                .vote_parsec(ParsecVote::RelocateResponse(info));
+        }
     }
 }
@@ -9,15 +9,35 @@
 
 use crate::{
     actions::{Action, InnerAction},
-    state::MemberState,
+    consistency::{self, EventKind, Mismatch},
+    coverage::{Coverage, METHOD_NODES, ROUTINE_DIAGRAMS},
+    diagrams::{self, label_text},
+    fuzz::Harness,
+    generators, invariants,
+    liveness::LivenessBound,
+    model_checker::{Invariant, ModelChecker, EVERY_EVENT_HANDLED},
+    replicas::ReplicaChecker,
+    shrinker,
+    simulation::Simulation,
+    state::{JoiningState, MemberState},
+    temporal,
     utilities::{
-        Candidate, CandidateInfo, Event, Node, NodeState, ParsecVote, Proof, RelocatedInfo, Rpc,
-        State, TestEvent, TryResult,
+        Age, Attributes, Authority, Candidate, CandidateInfo, Event, GenesisPfxInfo,
+        JoiningFailure, LocalEvent, Message, Name, Node, NodeChange, NodeState, ParsecVote,
+        ProofRequest, ProofScheme, PublicKey, RelocatedInfo, Rpc, SecretKey, Section, SectionInfo,
+        State, TestEvent, TryResult, ELDER_SIZE, NODES_PER_DIFFICULTY_STEP,
+        RESOURCE_PROOF_DATA_SIZE,
     },
 };
+use proptest::{
+    collection,
+    prelude::*,
+    sample::Index,
+    test_runner::{Config, RngAlgorithm, TestRng, TestRunner},
+};
 use rand::{self, seq::SliceRandom, Rng, SeedableRng};
 use rand_xorshift::XorShiftRng;
-use std::{env, iter, thread};
+use std::{collections::BTreeSet, env, iter, thread};
 use unwrap::unwrap;
 
 fn get_rng() -> XorShiftRng {
@@ -41,6 +61,14 @@ fn get_rng() -> XorShiftRng {
     XorShiftRng::seed_from_u64(seed)
 }
 
+// Every event the tests deliver must be handled, and keep the invariants when checking them.
+fn handle(member_state: &mut MemberState, event: Event) {
+    assert_eq!(TryResult::Handled, member_state.try_next(event));
+    if invariants::check_enabled() {
+        invariants::assert_invariants(member_state, event);
+    }
+}
+
 struct RandomEvents(Vec<Event>);
 
 impl RandomEvents {
@@ -48,34 +76,54 @@ impl RandomEvents {
     fn handle<T: Rng>(&self, member_state: &mut MemberState, rng: &mut T) {
         for optional_event in &self.0 {
             if rng.gen() {
-                assert_eq!(TryResult::Handled, member_state.try_next(*optional_event));
+                handle(member_state, *optional_event);
             }
         }
     }
 }
 
-#[test]
-fn relocate_adult_src() {
-    let mut rng = get_rng();
-    let nodes = iter::repeat_with(|| rng.gen())
+// An elder section of six with one adult, that relocates once its work units are enough.
+fn relocating_section<T: Rng>(rng: &mut T) -> (MemberState, Node) {
+    // Names drawn at random collide for some seeds, and a section cannot hold the same name twice.
+    let mut names = BTreeSet::new();
+    let nodes = iter::repeat_with(|| Node(distinct_attributes(rng, &mut names)))
         .take(6)
         .collect::<Vec<Node>>();
+    let our_attributes = distinct_attributes(rng, &mut names);
+    let next_target_interval = distinct_attributes(rng, &mut names).name;
 
     let action = Action::new(
-        InnerAction::new_with_our_attributes(rng.gen())
-            .with_next_target_interval(rng.gen())
+        InnerAction::new_with_our_attributes(our_attributes)
+            .with_next_target_interval(next_target_interval)
             .extend_current_nodes_with(&NodeState::default_elder(), &nodes),
     );
 
     // Sort into elders and adults.
     let to_become_adults = unwrap!(action.check_elder());
-    let relocating_node = unwrap!(to_become_adults.changes.choose(&mut rng)).0;
+    let relocating_node = unwrap!(to_become_adults.changes.choose(rng)).0;
     action.mark_elder_change(to_become_adults);
 
-    let mut member_state = MemberState {
+    let member_state = MemberState {
         action,
         ..Default::default()
     };
+    (member_state, relocating_node)
+}
+
+fn relocated_info_for<T: Rng>(rng: &mut T, node: Node) -> RelocatedInfo {
+    RelocatedInfo {
+        candidate: Candidate(node.0),
+        expected_age: node.0.age.increment_by_one(),
+        target_interval_centre: rng.gen(),
+        section_info: rng.gen(),
+        source_proof: None,
+    }
+}
+
+#[test]
+fn relocate_adult_src() {
+    let mut rng = get_rng();
+    let (mut member_state, relocating_node) = relocating_section(&mut rng);
 
     assert!(member_state
         .action
@@ -83,12 +131,7 @@ fn relocate_adult_src() {
         .our_current_nodes
         .contains_key(&relocating_node.0.name));
 
-    let relocated_info = RelocatedInfo {
-        candidate: Candidate(relocating_node.0),
-        expected_age: relocating_node.0.age.increment_by_one(),
-        target_interval_centre: rng.gen(),
-        section_info: rng.gen(),
-    };
+    let relocated_info = relocated_info_for(&mut rng, relocating_node);
 
     let required_events = [
         TestEvent::SetWorkUnitEnoughToRelocate(relocating_node).to_event(),
@@ -100,11 +143,16 @@ fn relocate_adult_src() {
     let optional_random_events = RandomEvents(vec![
         ParsecVote::WorkUnitIncrement.to_event(),
         ParsecVote::CheckRelocate.to_event(),
-        Rpc::RelocateResponse(relocated_info).to_event(),
+        Rpc::RelocateResponse(relocated_info)
+            .to_message(
+                Authority::Section(Section::default()),
+                Authority::SectionOf(relocating_node.0.name),
+            )
+            .to_event(),
     ]);
 
     for required_event in &required_events {
-        assert_eq!(TryResult::Handled, member_state.try_next(*required_event));
+        handle(&mut member_state, *required_event);
         optional_random_events.handle(&mut member_state, &mut rng);
     }
 
@@ -113,9 +161,9 @@ fn relocate_adult_src() {
         unwrap!(member_state.action.node_state(relocating_node.0.name)).state
     );
 
-    assert_eq!(
-        TryResult::Handled,
-        member_state.try_next(ParsecVote::RelocatedInfo(relocated_info).to_event())
+    handle(
+        &mut member_state,
+        ParsecVote::RelocatedInfo(relocated_info).to_event(),
     );
     assert!(member_state
         .action
@@ -125,6 +173,614 @@ fn relocate_adult_src() {
     optional_random_events.handle(&mut member_state, &mut rng);
 }
 
+const AT_MOST_ONE_RELOCATED: Invariant = Invariant {
+    name: "at most one node relocated",
+    check: |state| {
+        let relocated = state
+            .action
+            .inner()
+            .our_current_nodes
+            .values()
+            .filter(|node| matches!(node.state, State::Relocated(_)))
+            .count();
+        if relocated <= 1 {
+            Ok(())
+        } else {
+            Err(format!("{} nodes are relocated", relocated))
+        }
+    },
+};
+
+const NONE_RELOCATED: Invariant = Invariant {
+    name: "no node relocated",
+    check: |state| match state
+        .action
+        .inner()
+        .our_current_nodes
+        .values()
+        .find(|node| matches!(node.state, State::Relocated(_)))
+    {
+        Some(node) => Err(format!("{:?} is relocated", node.node)),
+        None => Ok(()),
+    },
+};
+
+#[test]
+fn model_check_relocate_adult_src() {
+    let mut rng = get_rng();
+    let (member_state, relocating_node) = relocating_section(&mut rng);
+    let relocated_info = relocated_info_for(&mut rng, relocating_node);
+    let other_relocated_info = relocated_info_for(&mut rng, relocating_node);
+
+    let checker = ModelChecker {
+        events: vec![
+            TestEvent::SetWorkUnitEnoughToRelocate(relocating_node).to_event(),
+            ParsecVote::WorkUnitIncrement.to_event(),
+            ParsecVote::CheckRelocate.to_event(),
+            ParsecVote::RelocateResponse(relocated_info).to_event(),
+            ParsecVote::RelocateResponse(other_relocated_info).to_event(),
+            ParsecVote::RelocatedInfo(relocated_info).to_event(),
+        ],
+        max_depth: 5,
+    };
+
+    let result = checker.check(
+        &member_state,
+        &[&invariants::ALL[..], &[AT_MOST_ONE_RELOCATED]].concat(),
+    );
+    if let Err(counterexample) = result {
+        panic!("{}", counterexample);
+    }
+}
+
+#[test]
+fn model_check_reports_shortest_trace() {
+    let mut rng = get_rng();
+    let (member_state, relocating_node) = relocating_section(&mut rng);
+    let relocated_info = relocated_info_for(&mut rng, relocating_node);
+
+    let set_work_units = TestEvent::SetWorkUnitEnoughToRelocate(relocating_node).to_event();
+    let checker = ModelChecker {
+        events: vec![
+            ParsecVote::CheckRelocate.to_event(),
+            ParsecVote::RelocateResponse(relocated_info).to_event(),
+            ParsecVote::WorkUnitIncrement.to_event(),
+            set_work_units,
+        ],
+        max_depth: 4,
+    };
+
+    let counterexample = unwrap!(checker.check(&member_state, &[NONE_RELOCATED]).err());
+    assert_eq!(
+        vec![
+            set_work_units,
+            ParsecVote::WorkUnitIncrement.to_event(),
+            ParsecVote::RelocateResponse(relocated_info).to_event(),
+        ],
+        counterexample.trace,
+        "{}",
+        counterexample
+    );
+}
+
+#[test]
+fn model_check_unhandled_events_only_when_asked() {
+    let mut rng = get_rng();
+    let (member_state, _) = relocating_section(&mut rng);
+
+    // A joining node's timeout is not for members to handle.
+    let unhandled = LocalEvent::JoiningTimeoutResendInfo.to_event();
+    let checker = ModelChecker {
+        events: vec![ParsecVote::CheckRelocate.to_event(), unhandled],
+        max_depth: 2,
+    };
+
+    let _ = unwrap!(checker.check(&member_state, &invariants::ALL));
+    let counterexample = unwrap!(checker.check(&member_state, &[EVERY_EVENT_HANDLED]).err());
+    assert_eq!(EVERY_EVENT_HANDLED.name, counterexample.invariant);
+    assert_eq!(vec![unhandled], counterexample.trace, "{}", counterexample);
+}
+
+#[test]
+fn invariants_explain_broken_state() {
+    let mut rng = get_rng();
+    let nodes = iter::repeat_with(|| rng.gen())
+        .take(ELDER_SIZE + 1)
+        .collect::<Vec<Node>>();
+
+    // Every node an elder, before CheckElder sorted them.
+    let member_state = MemberState {
+        action: Action::new(
+            InnerAction::new_with_our_attributes(rng.gen())
+                .extend_current_nodes_with(&NodeState::default_elder(), &nodes),
+        ),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        Err(format!(
+            "{} elders, at most {} expected",
+            ELDER_SIZE + 1,
+            ELDER_SIZE
+        )),
+        (invariants::ELDERS_WITHIN_ELDER_SIZE.check)(&member_state)
+    );
+
+    let mut elder_change_state = member_state.clone();
+    elder_change_state
+        .start_merge_split_and_change_elders
+        .sub_routine_process_elder_change
+        .is_active = true;
+    assert_eq!(
+        Err("active without votes or a key to wait for".to_string()),
+        (invariants::ELDER_CHANGE_ACTIVE_WITH_WAIT_VOTES.check)(&elder_change_state)
+    );
+
+    let mut merge_state = member_state;
+    merge_state
+        .start_merge_split_and_change_elders
+        .sub_routine_process_merge_active = true;
+    merge_state
+        .start_merge_split_and_change_elders
+        .sub_routine_process_split
+        .is_active = true;
+    assert_eq!(
+        Err("ProcessMerge, ProcessSplit all active".to_string()),
+        (invariants::ONE_SECTION_CHANGE_ACTIVE.check)(&merge_state)
+    );
+}
+
+#[test]
+fn shrink_random_relocate_src() {
+    let mut rng = get_rng();
+    let (member_state, relocating_node) = relocating_section(&mut rng);
+    let relocated_info = relocated_info_for(&mut rng, relocating_node);
+
+    let set_work_units = TestEvent::SetWorkUnitEnoughToRelocate(relocating_node).to_event();
+    let required_events = [
+        set_work_units,
+        ParsecVote::WorkUnitIncrement.to_event(),
+        ParsecVote::CheckRelocate.to_event(),
+        ParsecVote::RelocateResponse(relocated_info).to_event(),
+    ];
+    let optional_events = [
+        ParsecVote::WorkUnitIncrement.to_event(),
+        ParsecVote::CheckRelocate.to_event(),
+        Rpc::RelocateResponse(relocated_info)
+            .to_message(
+                Authority::Section(Section::default()),
+                Authority::SectionOf(relocating_node.0.name),
+            )
+            .to_event(),
+    ];
+
+    let mut events = Vec::new();
+    for required_event in &required_events {
+        events.push(*required_event);
+        events.extend(optional_events.iter().filter(|_| rng.gen::<bool>()));
+    }
+
+    let relocates = |events: &[Event]| {
+        let mut member_state = MemberState {
+            action: Action::new(member_state.action.inner()),
+            ..member_state.clone()
+        };
+        for event in events {
+            let _ = member_state.try_next(*event);
+        }
+        (NONE_RELOCATED.check)(&member_state).is_err()
+    };
+
+    assert_eq!(
+        vec![
+            set_work_units,
+            ParsecVote::WorkUnitIncrement.to_event(),
+            ParsecVote::RelocateResponse(relocated_info).to_event(),
+        ],
+        shrinker::shrink(&events, relocates)
+    );
+}
+
+#[test]
+fn shrunk_events_print_as_scenario_syntax() {
+    let node = Node(Attributes {
+        age: Age(9),
+        name: Name(1001),
+        key: PublicKey(7),
+    });
+    let candidate = Candidate(node.0);
+    let events = [
+        ParsecVote::CheckRelocate.to_event(),
+        Rpc::ResourceProofReceipt {
+            candidate,
+            source: Name(132),
+            index: 1,
+        }
+        .to_event(),
+        Rpc::RefuseCandidate(candidate)
+            .to_message(
+                Authority::Section(Section(1)),
+                Authority::SectionOf(Name(1001)),
+            )
+            .to_event(),
+        LocalEvent::TimeoutAccept.to_event(),
+        NodeChange::State(node, State::Online).to_event(),
+    ];
+
+    assert_eq!(
+        "&[\n\
+         \x20   ParsecVote::CheckRelocate.to_event(),\n\
+         \x20   Rpc::ResourceProofReceipt { candidate: Candidate(Attributes { age: Age(9), \
+         name: Name(1001), key: PublicKey(7) }), source: Name(132), index: 1 }.to_event(),\n\
+         \x20   Rpc::RefuseCandidate(Candidate(Attributes { age: Age(9), name: Name(1001), \
+         key: PublicKey(7) }))\
+         .to_message(Authority::Section(Section(1)), Authority::SectionOf(Name(1001)))\
+         .to_event(),\n\
+         \x20   LocalEvent::TimeoutAccept.to_event(),\n\
+         \x20   NodeChange::State(Node(Attributes { age: Age(9), name: Name(1001), \
+         key: PublicKey(7) }), \
+         State::Online).to_event(),\n\
+         ]",
+        shrinker::regression_test_events(&events)
+    );
+}
+
+// Runs `test` on values from `strategy`, seeded from ROUTING_MODEL_SEED like the other tests.
+// A failure is shrunk, and reported with the smallest value still failing.
+fn check_property<S: Strategy>(strategy: S, test: impl Fn(S::Value) -> Result<(), TestCaseError>) {
+    let seed = get_rng().gen::<[u8; 16]>();
+    let config = Config {
+        failure_persistence: None,
+        ..Config::with_cases(64)
+    };
+    let mut runner =
+        TestRunner::new_with_rng(config, TestRng::from_seed(RngAlgorithm::XorShift, &seed));
+    if let Err(failure) = runner.run(&strategy, test) {
+        panic!("{}", failure);
+    }
+}
+
+fn check_invariants(member_state: &MemberState) -> Result<(), TestCaseError> {
+    for invariant in &invariants::ALL {
+        prop_assert_eq!(
+            Ok(()),
+            (invariant.check)(member_state),
+            "{}",
+            invariant.name
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn generated_sections_are_consistent() {
+    check_property(generators::section_params(), |params| {
+        let member_state = params.member_state();
+        prop_assert!(member_state.action.check_elder().is_none());
+        check_invariants(&member_state)
+    });
+}
+
+#[test]
+fn plausible_events_keep_invariants() {
+    check_property(generators::section_and_events(20), |(params, events)| {
+        let mut member_state = params.member_state();
+        for event in events {
+            prop_assert_eq!(
+                TryResult::Handled,
+                member_state.try_next(event),
+                "{:?}",
+                event
+            );
+            check_invariants(&member_state)?;
+        }
+        Ok(())
+    });
+}
+
+#[test]
+fn shrink_keeps_only_the_failing_event() {
+    let events = collection::vec(generators::event(), 1..20);
+    check_property((events, any::<Index>()), |(events, index)| {
+        let culprit = events[index.index(events.len())];
+        let contains_culprit = |events: &[Event]| events.contains(&culprit);
+        prop_assert_eq!(vec![culprit], shrinker::shrink(&events, contains_culprit));
+        Ok(())
+    });
+}
+
+// Every elder of the section, each with the same other events to interleave.
+fn replicas(member_state: &MemberState, events: &[Event]) -> Vec<(MemberState, Vec<Event>)> {
+    let inner = member_state.action.inner();
+    inner
+        .our_current_nodes
+        .values()
+        .filter(|node_state| node_state.is_elder)
+        .map(|node_state| {
+            let action = Action::new(InnerAction {
+                our_attributes: node_state.node.0,
+                ..inner.clone()
+            });
+            let replica = MemberState {
+                action,
+                ..member_state.clone()
+            };
+            (replica, events.to_vec())
+        })
+        .collect()
+}
+
+#[test]
+fn replicas_agree_on_consensus() {
+    let section_and_events = generators::section_and_events(30);
+    check_property(
+        (section_and_events, any::<u64>()),
+        |((params, events), seed)| {
+            // Test events change the state directly: they stand for consensus we do not deliver.
+            let (consensus, others): (Vec<Event>, Vec<Event>) = events
+                .into_iter()
+                .filter(|event| !matches!(event, Event::TestEvent(_)))
+                .partition(|event| matches!(event, Event::ParsecConsensus(_)));
+            let checker = ReplicaChecker {
+                consensus: consensus
+                    .into_iter()
+                    .filter_map(|event| match event {
+                        Event::ParsecConsensus(vote) => Some(vote),
+                        _ => None,
+                    })
+                    .collect(),
+            };
+
+            let replicas = replicas(&params.member_state(), &others);
+            let mut rng = XorShiftRng::seed_from_u64(seed);
+            checker
+                .check(&mut rng, replicas)
+                .map_err(|divergence| TestCaseError::fail(divergence.to_string()))
+        },
+    );
+}
+
+#[test]
+fn replicas_diverging_report_first_field() {
+    let mut rng = get_rng();
+    let (member_state, relocating_node) = relocating_section(&mut rng);
+    let mut replicas = replicas(&member_state, &[]);
+    replicas[1]
+        .1
+        .push(TestEvent::SetWorkUnitEnoughToRelocate(relocating_node).to_event());
+
+    let names = replicas
+        .iter()
+        .map(|(replica, _)| replica.action.our_name())
+        .collect::<Vec<_>>();
+
+    let checker = ReplicaChecker {
+        consensus: Vec::new(),
+    };
+    let divergence = unwrap!(checker.check(&mut rng, replicas).err());
+
+    assert_eq!(
+        format!(
+            "our_current_nodes[{:?}].work_units_done",
+            relocating_node.name()
+        ),
+        divergence.field,
+        "{}",
+        divergence
+    );
+    assert_eq!(0, divergence.consensus_count, "{}", divergence);
+    assert_eq!((names[0], names[1]), (divergence.first, divergence.replica));
+}
+
+// Runs to try per fuzz test: set ROUTING_MODEL_FUZZ_ITERATIONS to fuzz for longer.
+fn fuzz_iterations() -> usize {
+    env::var("ROUTING_MODEL_FUZZ_ITERATIONS")
+        .ok()
+        .map(|value| {
+            unwrap!(
+                value.parse::<usize>(),
+                "Env var 'ROUTING_MODEL_FUZZ_ITERATIONS={}' is not a valid usize.",
+                value
+            )
+        })
+        .unwrap_or(2000)
+}
+
+#[test]
+fn fuzz_member_state() {
+    let mut rng = get_rng();
+    let (member_state, relocating_node) = relocating_section(&mut rng);
+    let relocated_info = relocated_info_for(&mut rng, relocating_node);
+    let our_name = member_state.action.our_name();
+    let elder = unwrap!(member_state
+        .action
+        .inner()
+        .our_current_nodes
+        .values()
+        .find(|node_state| node_state.is_elder))
+    .node;
+
+    let src_section: SectionInfo = rng.gen();
+    let old_secret_key: SecretKey = rng.gen();
+    let new_secret_key: SecretKey = rng.gen();
+    let old_public_id = Candidate(Attributes {
+        key: old_secret_key.public_key(),
+        ..rng.gen()
+    });
+    let new_public_id = Candidate(Attributes {
+        age: old_public_id.0.age.increment_by_one(),
+        name: member_state.action.inner().next_target_interval,
+        key: new_secret_key.public_key(),
+    });
+    let candidate_info = CandidateInfo::new(
+        old_public_id,
+        new_public_id,
+        our_name,
+        new_public_id.name(),
+        None,
+        old_secret_key,
+        new_secret_key,
+    );
+
+    let harness = Harness {
+        start: member_state,
+        world: vec![
+            TestEvent::SetWorkUnitEnoughToRelocate(relocating_node).to_event(),
+            ParsecVote::WorkUnitIncrement.to_event(),
+            ParsecVote::CheckRelocate.to_event(),
+            ParsecVote::CheckElder.to_event(),
+            ParsecVote::CheckResourceProof.to_event(),
+            ParsecVote::RelocateResponse(relocated_info).to_event(),
+            ParsecVote::RefuseCandidate(relocated_info.candidate).to_event(),
+            Rpc::RelocateResponse(relocated_info)
+                .to_message(
+                    Authority::Section(Section::default()),
+                    Authority::SectionOf(relocating_node.name()),
+                )
+                .to_event(),
+            Rpc::RefuseCandidate(relocated_info.candidate)
+                .to_message(
+                    Authority::Section(Section::default()),
+                    Authority::SectionOf(relocating_node.name()),
+                )
+                .to_event(),
+            Rpc::ExpectCandidate(old_public_id)
+                .to_message(
+                    Authority::Section(src_section.0),
+                    Authority::Section(Section::default()),
+                )
+                .to_event(),
+            ParsecVote::ExpectCandidate(old_public_id).to_event(),
+            Rpc::CandidateInfo(candidate_info).to_event(),
+            ParsecVote::CandidateConnected(candidate_info).to_event(),
+            Rpc::ConnectionInfoRequest {
+                source: new_public_id.name(),
+                destination: our_name,
+                connection_info: 0,
+            }
+            .to_event(),
+            LocalEvent::NodeDetectedOffline(elder).to_event(),
+            ParsecVote::Offline(elder).to_event(),
+            LocalEvent::NodeDetectedBackOnline(elder).to_event(),
+            ParsecVote::BackOnline(elder).to_event(),
+        ],
+    };
+
+    if let Err(bug) = harness.fuzz(&mut rng, fuzz_iterations()) {
+        panic!("{}", bug);
+    }
+}
+
+#[test]
+fn fuzz_joining_state() {
+    let mut rng = get_rng();
+    let elders = iter::repeat_with(|| rng.gen())
+        .take(ELDER_SIZE)
+        .collect::<Vec<Node>>();
+    let section_info: SectionInfo = rng.gen();
+    let old_secret_key: SecretKey = rng.gen();
+    let new_secret_key: SecretKey = rng.gen();
+    let old_public_id = Candidate(Attributes {
+        key: old_secret_key.public_key(),
+        ..rng.gen()
+    });
+    let new_public_id = Candidate(Attributes {
+        age: old_public_id.0.age.increment_by_one(),
+        name: rng.gen(),
+        key: new_secret_key.public_key(),
+    });
+
+    let mut joining_state = JoiningState {
+        action: Action::new(
+            InnerAction::new_with_our_attributes(new_public_id.0)
+                .with_section_members(section_info, &elders)
+                .with_secret_keys(old_secret_key, new_secret_key),
+        ),
+        ..Default::default()
+    };
+    joining_state.start(RelocatedInfo {
+        candidate: old_public_id,
+        expected_age: new_public_id.0.age,
+        target_interval_centre: new_public_id.name(),
+        section_info,
+        source_proof: None,
+    });
+
+    let world = elders
+        .iter()
+        .flat_map(|elder| {
+            let source = elder.name();
+            vec![
+                Rpc::ConnectionInfoResponse {
+                    source,
+                    destination: new_public_id.name(),
+                    connection_info: 0,
+                }
+                .to_event(),
+                Rpc::ResourceProof {
+                    candidate: new_public_id,
+                    source,
+                    proof: ProofRequest {
+                        scheme: ProofScheme::Counting,
+                        nonce: 0,
+                        seed: ProofRequest::seed(source, new_public_id.name(), 0),
+                        difficulty: 1,
+                        data_size: RESOURCE_PROOF_DATA_SIZE,
+                    },
+                }
+                .to_event(),
+                Rpc::NodeApproval {
+                    candidate: new_public_id,
+                    source,
+                    info: GenesisPfxInfo(section_info),
+                }
+                .to_event(),
+            ]
+            .into_iter()
+            .chain((0..RESOURCE_PROOF_DATA_SIZE).map(move |index| {
+                Rpc::ResourceProofReceipt {
+                    candidate: new_public_id,
+                    source,
+                    index,
+                }
+                .to_event()
+            }))
+        })
+        .collect();
+
+    let harness = Harness {
+        start: joining_state,
+        world,
+    };
+    if let Err(bug) = harness.fuzz(&mut rng, fuzz_iterations()) {
+        panic!("{}", bug);
+    }
+}
+
+#[test]
+fn identical_messages_sent_have_their_own_ids() {
+    let mut rng = get_rng();
+    let action = Action::new(InnerAction::new_with_our_attributes(rng.gen()));
+    let rpc = Rpc::ConnectionInfoRequest {
+        source: action.our_name(),
+        destination: rng.gen(),
+        connection_info: 0,
+    };
+    action.send_rpc(rpc);
+    action.send_rpc(rpc);
+
+    let ids = action
+        .inner()
+        .our_events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Rpc(message) => Some(message.id),
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>();
+    assert_eq!(2, ids.len());
+}
+
 #[test]
 fn relocate_adult_dst() {
     let mut rng = get_rng();
@@ -133,9 +789,15 @@ fn relocate_adult_dst() {
         .take(6)
         .collect::<Vec<Node>>();
 
+    let src_section_info: SectionInfo = rng.gen();
+    let src_elders = iter::repeat_with(|| rng.gen())
+        .take(3)
+        .collect::<Vec<Node>>();
+
     let action = Action::new(
         InnerAction::new_with_our_attributes(rng.gen())
             .with_next_target_interval(rng.gen())
+            .with_section_members(src_section_info, &src_elders)
             .extend_current_nodes_with(&NodeState::default_elder(), &dst_nodes),
     );
     let dst_name = action.our_name();
@@ -149,32 +811,63 @@ fn relocate_adult_dst() {
         ..Default::default()
     };
 
-    let old_public_id = Candidate(rng.gen());
-    let new_public_id = {
-        let mut new_public_id = Candidate(rng.gen());
-        new_public_id.0.age.0 = old_public_id.0.age.0 + 1;
-        new_public_id
-    };
+    let old_secret_key: SecretKey = rng.gen();
+    let new_secret_key: SecretKey = rng.gen();
+    let old_public_id = Candidate(Attributes {
+        key: old_secret_key.public_key(),
+        ..rng.gen()
+    });
+    let new_public_id = Candidate(Attributes {
+        age: old_public_id.0.age.increment_by_one(),
+        key: new_secret_key.public_key(),
+        ..rng.gen()
+    });
 
-    let candidate_info = CandidateInfo {
+    // The relocation we will agree to, as the source section signs it.
+    let relocated_info = RelocatedInfo {
+        candidate: old_public_id,
+        expected_age: old_public_id.0.age.increment_by_one(),
+        target_interval_centre: member_state.action.inner().next_target_interval,
+        section_info: member_state.action.inner().our_section,
+        source_proof: None,
+    }
+    .signed_by(src_section_info, &src_elders);
+
+    let candidate_info = CandidateInfo::new(
         old_public_id,
         new_public_id,
-        destination: dst_name,
-        waiting_candidate_name: member_state.action.inner().next_target_interval,
-        valid: true,
-    };
+        dst_name,
+        relocated_info.target_interval_centre,
+        relocated_info.source_proof,
+        old_secret_key,
+        new_secret_key,
+    );
 
-    let required_events = [
-        ParsecVote::ExpectCandidate(old_public_id).to_event(),
-        ParsecVote::CheckResourceProof.to_event(),
-        ParsecVote::Online(old_public_id, new_public_id).to_event(),
-        ParsecVote::CheckElder.to_event(),
-    ];
+    // Every elder judged the proof valid.
+    let online_votes = member_state
+        .action
+        .inner()
+        .our_current_nodes
+        .values()
+        .filter(|state| state.is_elder)
+        .map(|state| ParsecVote::Online(old_public_id, new_public_id, state.node.name()).to_event())
+        .collect::<Vec<_>>();
+
+    let required_events = iter::once(ParsecVote::ExpectCandidate(old_public_id).to_event())
+        .chain(iter::once(ParsecVote::CheckResourceProof.to_event()))
+        .chain(online_votes)
+        .chain(iter::once(ParsecVote::CheckElder.to_event()))
+        .collect::<Vec<_>>();
 
     let optional_any_time = RandomEvents(vec![
         ParsecVote::WorkUnitIncrement.to_event(),
         ParsecVote::CheckRelocate.to_event(),
-        Rpc::ExpectCandidate(old_public_id).to_event(),
+        Rpc::ExpectCandidate(old_public_id)
+            .to_message(
+                Authority::Section(src_section_info.0),
+                Authority::Section(member_state.action.our_section().0),
+            )
+            .to_event(),
     ]);
 
     let optional_after_expect_candidate = RandomEvents(vec![
@@ -187,15 +880,18 @@ fn relocate_adult_dst() {
         .to_event(),
     ]);
 
+    let proof_request = member_state
+        .action
+        .new_resource_proof_request(new_public_id);
     let optional_after_check_resource_proof = RandomEvents(vec![Rpc::ResourceProofResponse {
         candidate: new_public_id,
         destination: dst_name,
-        proof: Proof::ValidPart,
+        proof: proof_request.chunk(0),
     }
     .to_event()]);
 
     for (i, required_event) in required_events.iter().enumerate() {
-        assert_eq!(TryResult::Handled, member_state.try_next(*required_event));
+        handle(&mut member_state, *required_event);
         optional_any_time.handle(&mut member_state, &mut rng);
         if i > 0 {
             optional_after_expect_candidate.handle(&mut member_state, &mut rng);
@@ -220,3 +916,403 @@ fn relocate_adult_dst() {
     optional_after_expect_candidate.handle(&mut member_state, &mut rng);
     optional_after_check_resource_proof.handle(&mut member_state, &mut rng);
 }
+
+// Random attributes named apart from `taken`, which the name is added to.
+fn distinct_attributes<T: Rng>(rng: &mut T, taken: &mut BTreeSet<Name>) -> Attributes {
+    loop {
+        let attributes: Attributes = rng.gen();
+        if taken.insert(attributes.name) {
+            return attributes;
+        }
+    }
+}
+
+// Join a section of `elder_count` elders challenging candidates with `scheme`.
+fn simulate_join<T: Rng>(
+    rng: &mut T,
+    elder_count: usize,
+    scheme: ProofScheme,
+) -> (Simulation, Candidate) {
+    // Names drawn at random collide often enough across this many nodes.
+    let mut names = BTreeSet::new();
+    let elders = iter::repeat_with(|| Node(distinct_attributes(rng, &mut names)))
+        .take(elder_count)
+        .collect::<Vec<Node>>();
+    let section_info: SectionInfo = rng.gen();
+    let next_target_interval = distinct_attributes(rng, &mut names).name;
+    let src_section_info: SectionInfo = rng.gen();
+    let src_elders = iter::repeat_with(|| Node(distinct_attributes(rng, &mut names)))
+        .take(elder_count)
+        .collect::<Vec<Node>>();
+
+    let mut simulation = Simulation::default();
+    for elder in &elders {
+        let inner = InnerAction::new_with_our_attributes(elder.0)
+            .with_our_section(section_info)
+            .with_next_target_interval(next_target_interval)
+            .with_resource_proof_scheme(scheme)
+            .with_section_members(src_section_info, &src_elders)
+            .extend_current_nodes_with(&NodeState::default_elder(), &elders);
+
+        simulation.add_elder(MemberState {
+            action: Action::new(inner),
+            ..Default::default()
+        });
+    }
+
+    let old_secret_key: SecretKey = rng.gen();
+    let new_secret_key: SecretKey = rng.gen();
+    let old_public_id = Candidate(Attributes {
+        key: old_secret_key.public_key(),
+        ..distinct_attributes(rng, &mut names)
+    });
+    let new_public_id = Candidate(Attributes {
+        age: old_public_id.0.age.increment_by_one(),
+        key: new_secret_key.public_key(),
+        ..distinct_attributes(rng, &mut names)
+    });
+
+    simulation.consensus(ParsecVote::ExpectCandidate(old_public_id));
+    simulation.consensus(ParsecVote::CheckResourceProof);
+    simulation.run();
+
+    // Sent to the candidate by its source section.
+    let first_elder = unwrap!(simulation.elders.values().next());
+    let relocated_info = unwrap!(first_elder.action.get_waiting_candidate_info(old_public_id))
+        .signed_by(src_section_info, &src_elders);
+
+    let mut joining_state = JoiningState {
+        action: Action::new(
+            InnerAction::new_with_our_attributes(new_public_id.0)
+                .with_section_members(section_info, &elders)
+                .with_secret_keys(old_secret_key, new_secret_key),
+        ),
+        ..Default::default()
+    };
+    joining_state.start(relocated_info);
+    simulation.add_joining(joining_state);
+    simulation.run();
+
+    (simulation, new_public_id)
+}
+
+#[test]
+fn join_dst_in_simulation() {
+    let mut rng = get_rng();
+    let (mut simulation, new_public_id) = simulate_join(&mut rng, 3, ProofScheme::default());
+
+    for (name, elder) in &simulation.elders {
+        assert!(simulation.was_delivered(*name, |event| match event {
+            Event::Rpc(Message {
+                rpc: Rpc::CandidateInfo(info),
+                ..
+            }) => info.new_public_id == new_public_id,
+            _ => false,
+        }));
+        assert_eq!(
+            State::Online,
+            unwrap!(elder.action.node_state(new_public_id.name())).state
+        );
+    }
+
+    let joining_state = &simulation.joining[&new_public_id.name()];
+    assert!(joining_state.join_routine.routine_complete_output.is_some());
+
+    let stuck = simulation.stuck(LivenessBound::Steps(0));
+    assert!(stuck.is_empty(), "{}", stuck[0]);
+    // Nothing is left waiting, however long we wait.
+    simulation.elapse(60);
+    let stuck = simulation.stuck(LivenessBound::Time(0));
+    assert!(stuck.is_empty(), "{}", stuck[0]);
+
+    for property in &[
+        temporal::expect_candidate_answered(),
+        temporal::no_approval_before_online(),
+    ] {
+        if let Err(explanation) = property.check(&simulation.trace) {
+            panic!("{}", explanation);
+        }
+    }
+}
+
+#[test]
+fn candidate_never_joining_in_simulation() {
+    let mut rng = get_rng();
+    let mut names = BTreeSet::new();
+    let elders = iter::repeat_with(|| Node(distinct_attributes(&mut rng, &mut names)))
+        .take(3)
+        .collect::<Vec<Node>>();
+    let section_info: SectionInfo = rng.gen();
+
+    let mut simulation = Simulation::default();
+    for elder in &elders {
+        let inner = InnerAction::new_with_our_attributes(elder.0)
+            .with_our_section(section_info)
+            .with_next_target_interval(distinct_attributes(&mut rng, &mut names).name)
+            .extend_current_nodes_with(&NodeState::default_elder(), &elders);
+        simulation.add_elder(MemberState {
+            action: Action::new(inner),
+            ..Default::default()
+        });
+    }
+
+    // The candidate is accepted, but its source section never signed its relocation: the elders
+    // discard its CandidateInfo.
+    let old_secret_key: SecretKey = rng.gen();
+    let new_secret_key: SecretKey = rng.gen();
+    let old_public_id = Candidate(Attributes {
+        key: old_secret_key.public_key(),
+        ..distinct_attributes(&mut rng, &mut names)
+    });
+    let new_public_id = Candidate(Attributes {
+        age: old_public_id.0.age.increment_by_one(),
+        key: new_secret_key.public_key(),
+        ..distinct_attributes(&mut rng, &mut names)
+    });
+    simulation.consensus(ParsecVote::ExpectCandidate(old_public_id));
+    simulation.consensus(ParsecVote::CheckResourceProof);
+    simulation.run();
+
+    let first_elder = unwrap!(simulation.elders.values().next());
+    let relocated_info = unwrap!(first_elder.action.get_waiting_candidate_info(old_public_id));
+    let mut joining_state = JoiningState {
+        action: Action::new(
+            InnerAction::new_with_our_attributes(new_public_id.0)
+                .with_section_members(section_info, &elders)
+                .with_secret_keys(old_secret_key, new_secret_key),
+        ),
+        ..Default::default()
+    };
+    joining_state.start(relocated_info);
+    simulation.add_joining(joining_state);
+    simulation.run();
+    simulation.fire(new_public_id.name(), LocalEvent::JoiningTimeoutProofRefused);
+    simulation.run();
+
+    for elder in simulation.elders.values() {
+        assert_eq!(None, elder.action.node_state(new_public_id.name()));
+    }
+    assert_eq!(
+        Some(JoiningFailure::ProofRefusedTimeout),
+        simulation.joining[&new_public_id.name()]
+            .join_routine
+            .routine_failure_output
+    );
+}
+
+#[test]
+fn resource_proof_difficulty_in_simulation() {
+    let mut rng = get_rng();
+
+    for scheme in &[ProofScheme::Counting, ProofScheme::Seeded] {
+        let work_per_elder = [3, NODES_PER_DIFFICULTY_STEP as usize]
+            .iter()
+            .map(|elder_count| {
+                let (simulation, new_public_id) = simulate_join(&mut rng, *elder_count, *scheme);
+                let joining_state = &simulation.joining[&new_public_id.name()];
+                assert!(joining_state.join_routine.routine_complete_output.is_some());
+
+                simulation.proof_work / *elder_count as i64
+            })
+            .collect::<Vec<_>>();
+
+        assert!(work_per_elder[0] < work_per_elder[1]);
+    }
+}
+
+#[test]
+fn flow_diagrams_parse() {
+    for name in ROUTINE_DIAGRAMS {
+        assert!(diagrams::diagram(name).is_some(), "No diagram for {}", name);
+    }
+
+    let diagrams = diagrams::parse_html(
+        r#"<div class="mermaid">
+graph TB
+    Routine["Routine:<br/>No exit"] --> LoopStart
+    LoopStart --> WaitFor(("Wait for 0:"))
+    WaitFor --"Parsec::Vote" --> Step["vote_for(Parsec::Vote)"]
+    Step --> LoopEnd
+    LoopEnd --> LoopStart
+    style Routine fill:#f9f
+</div>"#,
+    );
+
+    assert_eq!(1, diagrams.len());
+    let diagram = &diagrams[0];
+    assert_eq!("Routine", diagram.name);
+    assert_eq!(vec!["WaitFor", "Routine"], diagram.entries());
+    assert_eq!(5, diagram.edges.len());
+    assert_eq!("Parsec::Vote", label_text(&diagram.edges[2].label));
+    assert!(diagram.is_step("Step"));
+    assert!(!diagram.is_step("LoopEnd"));
+    assert!(diagram.has_node("LoopEnd"));
+}
+
+#[test]
+fn flow_coverage_follows_diagram_paths() {
+    let diagram = "JoiningRelocateCandidate";
+    let approval = "Edge never exercised: Rpc --Rpc::NodeApproval--> EndRoutine";
+    let refused = "Edge never exercised: LocalEvent --TimeoutRefused triggered--> EndRoutine";
+
+    let mut coverage = Coverage::default();
+    assert!(coverage.record(Some("Rpc::NodeApproval"), &[(diagram, "EndRoutine")]));
+    assert!(!coverage.record(Some("Rpc::NodeApproval"), &[(diagram, "EndRoutine")]));
+
+    // WaitFor, Rpc and EndRoutine, through the edges labelled for the event.
+    let report = coverage.report();
+    assert!(report.contains("JoiningRelocateCandidate: 3 of 14 nodes, 2 of 21 edges"));
+    assert!(!report.contains(approval));
+    assert!(report.contains(refused));
+
+    assert!(coverage.record(None, &[(diagram, "NoSuchStep")]));
+    assert!(coverage
+        .report()
+        .contains("Step not in the diagram: NoSuchStep"));
+}
+
+#[test]
+fn flow_step_methods_run_diagram_nodes() {
+    for (routine, methods) in METHOD_NODES {
+        let name = unwrap!(consistency::DIAGRAM_ROUTINES
+            .iter()
+            .find(|(_, name)| name == routine)
+            .map(|(diagram, _)| *diagram));
+        let diagram = unwrap!(diagrams::diagram(name), "No diagram for {}", name);
+        for (method, node) in *methods {
+            assert!(
+                diagram.has_node(node),
+                "{}::{} runs {}, not in the {} diagram",
+                routine,
+                method,
+                node,
+                name
+            );
+        }
+    }
+}
+
+#[test]
+fn flow_diagrams_consistency_with_routines() {
+    let diagrams = diagrams::parse_html(
+        r#"<div class="mermaid">
+graph TB
+    Routine["Routine"] --> WaitFor(("Wait for 0:"))
+    WaitFor --> RPC((RPC))
+    RPC --Rpc::Merge--> VoteMerge["vote_for(Parsec::NeighbourMerge)"]
+    RPC --"Rpc::NodeApproval from a section not Rpc::SectionUpdate" --> Discard["discard()"]
+    WaitFor --> Event((Event))
+    Event --"CheckElder Timeout" --> VoteCheckElder["vote_for(Parsec::CheckElder)"]
+    style Routine fill:#f9f
+</div>"#,
+    );
+    let source = r#"
+impl<'a> Routine<'a> {
+    fn try_next(&mut self, event: WaitedEvent) -> TryResult {
+        match event {
+            WaitedEvent::LocalEvent(LocalEvent::TimeoutCheckElder) => {
+                self.0.action.vote_parsec(ParsecVote::CheckElder);
+                TryResult::Handled
+            }
+            WaitedEvent::Rpc(message) => match message.rpc {
+                Rpc::NodeApproval { source, .. } if source != self.our_name() => {
+                    self.0.action.send_rpc(Rpc::Merge(self.our_section()));
+                    TryResult::Handled
+                }
+                _ => TryResult::Unhandled,
+            },
+            WaitedEvent::LocalEvent(LocalEvent::TimeoutDkg(_)) => TryResult::Handled,
+            _ => TryResult::Unhandled,
+        }
+    }
+}
+"#;
+
+    let mismatches = consistency::check_sources(&[(&diagrams[0], "Routine")], &[source])
+        .iter()
+        .map(|mismatch| mismatch.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            "Routine: Rpc::Merge is handled by the diagram, not the routine",
+            "Routine: LocalEvent::TimeoutDkg is handled by the routine, not the diagram",
+        ],
+        mismatches
+    );
+}
+
+#[test]
+fn flow_diagrams_drift_from_routines() {
+    use EventKind::{LocalEvent, ParsecVote, Rpc};
+    // The events a diagram and its routine disagree on, and whether only the diagram has each.
+    type Drift = &'static [(EventKind, &'static str, bool)];
+
+    // Every known drift: a new one must be fixed, or added here with its reason.
+    let known: &[(&str, Drift)] = &[
+        (
+            "JoiningRelocateCandidate",
+            &[
+                // The joining node does not cache the relocation RPCs yet.
+                (Rpc, "ExpectCandidate", true),
+                (Rpc, "ExpectCandidateAcceptResponse", true),
+                (Rpc, "ExpectCandidateRefuseResponse", true),
+                // The diagram describes its local events in words.
+                (LocalEvent, "JoiningTimeoutProofRefused", false),
+                (LocalEvent, "JoiningTimeoutResendInfo", false),
+                (LocalEvent, "TimeoutRefused triggered", true),
+                (LocalEvent, "TimeoutResendInfo triggered", true),
+            ],
+        ),
+        (
+            "StartDecidesOnNodeToRelocate",
+            &[
+                (LocalEvent, "TimeoutWorkUnit", false),
+                (LocalEvent, "WorkUnitTimeOut Trigger", true),
+            ],
+        ),
+        (
+            "StartMergeSplitAndChangeElders",
+            &[
+                // Left over from a key generation.
+                (Rpc, "DkgContribution", false),
+                (ParsecVote, "DkgFailed", false),
+                (LocalEvent, "TimeoutDkg", false),
+                // The check vote was renamed in the flows only.
+                (ParsecVote, "CheckElder", false),
+                (ParsecVote, "CheckElderTimeout", true),
+            ],
+        ),
+        (
+            "StartRelocateSrc",
+            &[
+                // The candidate responses were renamed in the flows only.
+                (Rpc, "ExpectCandidateAcceptResponse", true),
+                (Rpc, "ExpectCandidateRefuseResponse", true),
+                (Rpc, "RefuseCandidate", false),
+                (Rpc, "RelocateResponse", false),
+                (ParsecVote, "ExpectCandidateAcceptResponse", true),
+                (ParsecVote, "ExpectCandidateRefuseResponse", true),
+                (ParsecVote, "RefuseCandidate", false),
+                (ParsecVote, "RelocateResponse", false),
+                (LocalEvent, "CheckRelocateTimeOut Trigger", true),
+                (LocalEvent, "TimeoutCheckRelocate", false),
+            ],
+        ),
+    ];
+    let known = known
+        .iter()
+        .flat_map(|(diagram, mismatches)| {
+            mismatches
+                .iter()
+                .map(move |(kind, name, in_diagram)| Mismatch {
+                    diagram: diagram.to_string(),
+                    kind: *kind,
+                    name: name.to_string(),
+                    in_diagram: *in_diagram,
+                })
+        })
+        .collect::<BTreeSet<_>>();
+
+    let mismatches = consistency::check().into_iter().collect::<BTreeSet<_>>();
+    assert_eq!(known, mismatches);
+}
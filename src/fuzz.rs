@@ -0,0 +1,356 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{
+    actions::Action,
+    model_checker::panic_message,
+    shrinker,
+    state::{JoiningState, MemberState},
+    utilities::{ActionTriggered, Event, LocalEvent, ParsecVote, Rpc, TryResult},
+};
+use rand::Rng;
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display, Formatter},
+    panic::{self, AssertUnwindSafe},
+};
+
+// Panics that assert what the protocol guarantees, rather than check what the network sends,
+// matched against the panic message. Reaching one still is a bug: either the fuzzer delivered an
+// event the protocol never delivers, or the guarantee does not hold.
+pub const INTENTIONAL_PANICS: &[(&str, &str)] = &[
+    (
+        "Output event delivered",
+        "NodeChange and ActionTriggered events are only ever output, never input",
+    ),
+    (
+        "Added node already a member",
+        "relocated nodes and candidates are named in the next target interval, kept free",
+    ),
+    (
+        "Removed node not a member",
+        "only consensused changes remove nodes, after checking membership",
+    ),
+    (
+        "Replaced node not a member",
+        "candidates are replaced once: the Online vote is handled by the candidate's routine only",
+    ),
+    (
+        "Candidate already relocating",
+        "the best relocating node is chosen among those not already relocating",
+    ),
+    (
+        "Unexpected vote",
+        "only RefuseCandidate and RelocateResponse votes are checked for our relocating node",
+    ),
+    (
+        "No candidate",
+        "the candidate is asked for only while accepting one, between ExpectCandidate and Online",
+    ),
+    (
+        "Candidate not waiting",
+        "a candidate accepted is added as waiting until it is Online",
+    ),
+    (
+        "Candidate info not held",
+        "CandidateConnected is voted only for CandidateInfo just received",
+    ),
+    (
+        "No proof request",
+        "resource proof parts are only checked after the proof request is sent",
+    ),
+    (
+        "No elder change",
+        "ProcessElderChange only runs with the change it was started with",
+    ),
+    (
+        "Not relocated",
+        "a joining node is only started with the RelocatedInfo it joins with",
+    ),
+];
+
+// A state the fuzzer can drive.
+pub trait Target {
+    fn action(&self) -> &Action;
+    fn try_next(&mut self, event: Event) -> TryResult;
+    // Cloning a state shares its Action: each run needs its own.
+    fn fork(&self) -> Self;
+}
+
+impl Target for MemberState {
+    fn action(&self) -> &Action {
+        &self.action
+    }
+
+    fn try_next(&mut self, event: Event) -> TryResult {
+        MemberState::try_next(self, event)
+    }
+
+    fn fork(&self) -> Self {
+        MemberState {
+            action: Action::new(self.action.inner()),
+            ..self.clone()
+        }
+    }
+}
+
+impl Target for JoiningState {
+    fn action(&self) -> &Action {
+        &self.action
+    }
+
+    fn try_next(&mut self, event: Event) -> TryResult {
+        JoiningState::try_next(self, event)
+    }
+
+    fn fork(&self) -> Self {
+        JoiningState {
+            action: Action::new(self.action.inner()),
+            ..self.clone()
+        }
+    }
+}
+
+// A panic, and the fewest events that still cause it.
+#[derive(Debug, PartialEq)]
+pub struct Bug {
+    pub message: String,
+    pub trace: Vec<Event>,
+}
+
+impl Display for Bug {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        writeln!(formatter, "Panicked: {}", self.message)?;
+        if let Some(guarantee) = broken_guarantee(&self.message) {
+            writeln!(
+                formatter,
+                "Intentional assertion, on the guarantee that {}",
+                guarantee
+            )?;
+        }
+        writeln!(
+            formatter,
+            "After {}",
+            shrinker::regression_test_events(&self.trace)
+        )
+    }
+}
+
+// What running one input did.
+#[derive(Debug, Default)]
+pub struct Run {
+    pub trace: Vec<Event>,
+    // Each event delivered, with what it output: the coverage the fuzzer looks to grow.
+    pub features: BTreeSet<String>,
+    pub panic: Option<String>,
+}
+
+// Feeds byte strings to a state as event sequences. Each byte picks the next event from those
+// that may arrive: the `world` events, which may arrive any number of times, and the answers to
+// what the state output so far, which arrive once each: consensus on our votes, our scheduled
+// timeouts, and the parts of the resource proofs we asked for.
+pub struct Harness<S: Target> {
+    pub start: S,
+    pub world: Vec<Event>,
+}
+
+impl<S: Target> Harness<S> {
+    pub fn run(&self, bytes: &[u8]) -> Run {
+        let mut bytes = bytes.iter();
+        self.execute(|deliverable| {
+            let byte = *bytes.next()?;
+            deliverable
+                .get(byte as usize % deliverable.len().max(1))
+                .copied()
+        })
+    }
+
+    // Whether delivering `events` in order panics, and with what message. Only events that
+    // may arrive when they are delivered count: an answer before what it answers does not.
+    fn replay(&self, events: &[Event]) -> Option<String> {
+        let mut events = events.iter();
+        let mut stopped_early = false;
+        let run = self.execute(|deliverable| {
+            let event = events.next()?;
+            stopped_early = !deliverable.contains(event);
+            Some(*event).filter(|_| !stopped_early)
+        });
+        run.panic.filter(|_| !stopped_early)
+    }
+
+    // Delivers the events `next` picks from those that may arrive, until it picks none.
+    fn execute(&self, mut next: impl FnMut(&[Event]) -> Option<Event>) -> Run {
+        let mut state = self.start.fork();
+        let mut pending = answers(&state, state.action().take_our_events());
+        let mut run = Run::default();
+
+        while let Some(event) = next(&[&self.world[..], &pending[..]].concat()) {
+            if !self.world.contains(&event) {
+                let _ = pending
+                    .iter()
+                    .position(|answer| *answer == event)
+                    .map(|index| pending.remove(index));
+            }
+            run.trace.push(event);
+
+            match panic::catch_unwind(AssertUnwindSafe(|| state.try_next(event))) {
+                Ok(result) => {
+                    let output = state.action().take_our_events();
+                    let _ = run.features.insert(feature(event, result, &output));
+                    pending.extend(answers(&state, output));
+                }
+                Err(payload) => {
+                    run.panic = Some(panic_message(&*payload));
+                    break;
+                }
+            }
+        }
+        run
+    }
+
+    // Mutates inputs that reached new features, starting from an empty one, for `iterations`
+    // runs. Returns the number of features reached, or the first bug found, shrunk.
+    pub fn fuzz<R: Rng>(&self, rng: &mut R, iterations: usize) -> Result<usize, Bug> {
+        let mut corpus = vec![Vec::new()];
+        let mut coverage = BTreeSet::new();
+
+        for _ in 0..iterations {
+            let input = mutate(rng, &corpus);
+            let run = self.run(&input);
+
+            if let Some(message) = run.panic {
+                let trace = shrinker::shrink(&run.trace, |events| {
+                    self.replay(events).as_ref() == Some(&message)
+                });
+                return Err(Bug { message, trace });
+            }
+
+            let new_features = run
+                .features
+                .into_iter()
+                .filter(|feature| coverage.insert(feature.clone()))
+                .count();
+            if new_features > 0 {
+                corpus.push(input);
+            }
+        }
+
+        Ok(coverage.len())
+    }
+}
+
+fn answers<S: Target>(state: &S, output: Vec<Event>) -> Vec<Event> {
+    let elders = state
+        .action()
+        .inner()
+        .our_current_nodes
+        .values()
+        .filter(|node_state| node_state.is_elder)
+        .map(|node_state| node_state.node.name())
+        .collect::<Vec<_>>();
+
+    output
+        .into_iter()
+        .flat_map(|event| match event {
+            // Every elder judges the proof: any of them may vote the candidate online.
+            Event::ParsecConsensus(ParsecVote::Online(old, new, _)) => elders
+                .iter()
+                .map(|voter| ParsecVote::Online(old, new, *voter).to_event())
+                .collect(),
+            Event::ParsecConsensus(_) => vec![event],
+            Event::ActionTriggered(ActionTriggered::Scheduled(local_event)) => {
+                vec![local_event.to_event()]
+            }
+            Event::ActionTriggered(ActionTriggered::ComputeResourceProofForElder(source)) => {
+                vec![LocalEvent::ResourceProofForElderReady(source).to_event()]
+            }
+            Event::Rpc(message) => match message.rpc {
+                Rpc::ResourceProof {
+                    candidate,
+                    source,
+                    proof,
+                } => (0..proof.data_size)
+                    .map(|index| {
+                        Rpc::ResourceProofResponse {
+                            candidate,
+                            destination: source,
+                            proof: proof.chunk(index),
+                        }
+                        .to_event()
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+// The event delivered and the kinds of events output, without their content.
+fn feature(event: Event, result: TryResult, output: &[Event]) -> String {
+    let output = output
+        .iter()
+        .map(|event| kind(*event))
+        .collect::<BTreeSet<_>>();
+    format!("{} {:?} -> {:?}", kind(event), result, output)
+}
+
+// The variant of an event, like `Rpc::NodeApproval`, without its content.
+pub fn kind(event: Event) -> String {
+    let variant = |debug: String| {
+        debug
+            .split(|character: char| !character.is_alphanumeric())
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    };
+    match event {
+        Event::Rpc(message) => format!("Rpc::{}", variant(format!("{:?}", message.rpc))),
+        _ => {
+            let debug = format!("{:?}", event);
+            let outer = variant(debug.clone());
+            format!(
+                "{}::{}",
+                outer,
+                variant(debug[outer.len() + 1..].to_string())
+            )
+        }
+    }
+}
+
+const MAX_INPUT_LEN: usize = 64;
+
+// A corpus input with a few random edits.
+fn mutate<R: Rng>(rng: &mut R, corpus: &[Vec<u8>]) -> Vec<u8> {
+    let mut input = corpus[rng.gen_range(0, corpus.len())].clone();
+    for _ in 0..rng.gen_range(1, 5) {
+        let position = rng.gen_range(0, input.len() + 1);
+        match rng.gen_range(0, 4) {
+            0 if position < input.len() => input[position] = rng.gen(),
+            1 if position < input.len() => {
+                let _ = input.remove(position);
+            }
+            2 => {
+                let other = &corpus[rng.gen_range(0, corpus.len())];
+                input.truncate(position);
+                input.extend_from_slice(&other[position.min(other.len())..]);
+            }
+            _ => input.insert(position, rng.gen()),
+        }
+    }
+    input.truncate(MAX_INPUT_LEN);
+    input
+}
+
+fn broken_guarantee(message: &str) -> Option<&'static str> {
+    INTENTIONAL_PANICS
+        .iter()
+        .find(|(intentional, _)| message.contains(intentional))
+        .map(|(_, guarantee)| *guarantee)
+}
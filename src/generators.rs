@@ -0,0 +1,431 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+// Property testing strategies for the model types. Values shrink towards the start of each
+// range and towards the first variant listed, so list the simplest ones first. Unions of fewer
+// than 11 strategies are built without `prop_oneof!`, whose expansion allows a clippy lint the
+// crate forbids.
+
+use crate::{
+    actions::{Action, InnerAction},
+    state::MemberState,
+    utilities::{
+        Age, Attributes, Authority, Candidate, CandidateInfo, ChurnNeeded, Event, GenesisPfxInfo,
+        LocalEvent, Message, Name, Node, NodeState, ParsecVote, Proof, ProofRequest, ProofScheme,
+        ProofSource, RelocatedInfo, Rpc, SecretKey, Section, SectionElders, SectionInfo,
+        SectionProof, TestEvent, ELDER_SIZE, MAX_WAITING_CANDIDATES,
+    },
+};
+use proptest::{
+    collection::{btree_set, vec},
+    option,
+    prelude::*,
+    sample,
+    strategy::Union,
+};
+
+// Same ranges as the Distribution impls in utilities.
+pub fn name() -> impl Strategy<Value = Name> {
+    (-9999..10000).prop_map(Name)
+}
+
+pub fn age() -> impl Strategy<Value = Age> {
+    (5..101).prop_map(Age)
+}
+
+pub fn secret_key() -> impl Strategy<Value = SecretKey> {
+    any::<i32>().prop_map(SecretKey)
+}
+
+pub fn attributes() -> impl Strategy<Value = Attributes> {
+    (age(), name(), secret_key()).prop_map(|(age, name, secret_key)| Attributes {
+        age,
+        name,
+        key: secret_key.public_key(),
+    })
+}
+
+pub fn candidate() -> impl Strategy<Value = Candidate> {
+    attributes().prop_map(Candidate)
+}
+
+pub fn node() -> impl Strategy<Value = Node> {
+    attributes().prop_map(Node)
+}
+
+// Not the default Section(0), which is ours in the generated states.
+pub fn section() -> impl Strategy<Value = Section> {
+    (1..i32::MAX).prop_map(Section)
+}
+
+pub fn section_elders() -> impl Strategy<Value = SectionElders> {
+    vec(node(), 0..=ELDER_SIZE).prop_map(|nodes| SectionElders::new(&nodes))
+}
+
+pub fn section_info() -> impl Strategy<Value = SectionInfo> {
+    (section(), any::<i32>(), section_elders())
+        .prop_map(|(section, version, elders)| SectionInfo(section, version, elders))
+}
+
+// Signed by the elders of the section, as on consensus.
+pub fn section_proof() -> impl Strategy<Value = SectionProof> {
+    (section_info(), vec(any::<i32>(), 0..4))
+        .prop_map(|(info, message)| SectionProof::new(info, info.2.nodes(), &message))
+}
+
+pub fn relocated_info() -> impl Strategy<Value = RelocatedInfo> {
+    (candidate(), name(), section_info(), any::<bool>()).prop_map(
+        |(candidate, target_interval_centre, section_info, signed)| {
+            let info = RelocatedInfo {
+                candidate,
+                expected_age: candidate.0.age.increment_by_one(),
+                target_interval_centre,
+                section_info,
+                source_proof: None,
+            };
+            if signed {
+                info.signed_by(section_info, section_info.2.nodes())
+            } else {
+                info
+            }
+        },
+    )
+}
+
+// Signed by the relocated node, for the relocation it got the info for.
+pub fn candidate_info() -> impl Strategy<Value = CandidateInfo> {
+    (relocated_info(), name(), secret_key(), secret_key()).prop_map(
+        |(info, destination, old_secret_key, new_secret_key)| {
+            let old_public_id = Candidate(Attributes {
+                key: old_secret_key.public_key(),
+                ..info.candidate.0
+            });
+            let new_public_id = Candidate(Attributes {
+                age: info.expected_age,
+                name: info.target_interval_centre,
+                key: new_secret_key.public_key(),
+            });
+            CandidateInfo::new(
+                old_public_id,
+                new_public_id,
+                destination,
+                info.target_interval_centre,
+                info.source_proof,
+                old_secret_key,
+                new_secret_key,
+            )
+        },
+    )
+}
+
+pub fn proof_request() -> impl Strategy<Value = ProofRequest> {
+    (
+        sample::select(vec![ProofScheme::Counting, ProofScheme::Seeded]),
+        0..8,
+        any::<i32>(),
+        1..4,
+        1..4,
+    )
+        .prop_map(
+            |(scheme, nonce, seed, difficulty, data_size)| ProofRequest {
+                scheme,
+                nonce,
+                seed,
+                difficulty,
+                data_size,
+            },
+        )
+}
+
+pub fn proof() -> impl Strategy<Value = Proof> {
+    (0..8, 0..4, any::<i32>()).prop_map(|(nonce, index, value)| Proof {
+        nonce,
+        index,
+        value,
+    })
+}
+
+pub fn proof_source() -> impl Strategy<Value = ProofSource> {
+    proof_request().prop_flat_map(|request| {
+        (0..=request.data_size).prop_map(move |computed| ProofSource { request, computed })
+    })
+}
+
+pub fn authority() -> impl Strategy<Value = Authority> {
+    Union::new(vec![
+        name().prop_map(Authority::Node).boxed(),
+        section().prop_map(Authority::Section).boxed(),
+        name().prop_map(Authority::SectionOf).boxed(),
+        section().prop_map(Authority::Neighbours).boxed(),
+    ])
+}
+
+pub fn rpc() -> impl Strategy<Value = Rpc> {
+    prop_oneof![
+        candidate().prop_map(Rpc::RefuseCandidate),
+        relocated_info().prop_map(Rpc::RelocateResponse),
+        relocated_info().prop_map(Rpc::RelocatedInfo),
+        candidate().prop_map(Rpc::ExpectCandidate),
+        (candidate(), name(), proof_request()).prop_map(|(candidate, source, proof)| {
+            Rpc::ResourceProof {
+                candidate,
+                source,
+                proof,
+            }
+        }),
+        (candidate(), name(), 0..4).prop_map(|(candidate, source, index)| {
+            Rpc::ResourceProofReceipt {
+                candidate,
+                source,
+                index,
+            }
+        }),
+        (candidate(), name(), section_info()).prop_map(|(candidate, source, info)| {
+            Rpc::NodeApproval {
+                candidate,
+                source,
+                info: GenesisPfxInfo(info),
+            }
+        }),
+        (name(), section_info(), section_proof()).prop_map(|(destination, info, proof)| {
+            Rpc::SectionUpdate {
+                destination,
+                info,
+                proof,
+            }
+        }),
+        (section_info(), section_proof())
+            .prop_map(|(info, proof)| Rpc::NeighbourUpdate { info, proof }),
+        (candidate(), name(), proof()).prop_map(|(candidate, destination, proof)| {
+            Rpc::ResourceProofResponse {
+                candidate,
+                destination,
+                proof,
+            }
+        }),
+        candidate_info().prop_map(Rpc::CandidateInfo),
+        (name(), name(), any::<i32>()).prop_map(|(source, destination, connection_info)| {
+            Rpc::ConnectionInfoRequest {
+                source,
+                destination,
+                connection_info,
+            }
+        }),
+        (name(), name(), any::<i32>()).prop_map(|(source, destination, connection_info)| {
+            Rpc::ConnectionInfoResponse {
+                source,
+                destination,
+                connection_info,
+            }
+        }),
+        section_info().prop_map(Rpc::Merge),
+        (name(), name(), section_info()).prop_map(|(source, destination, info)| {
+            Rpc::DkgContribution {
+                source,
+                destination,
+                info,
+            }
+        }),
+    ]
+}
+
+// Half the time from who the Rpc claims sent it, otherwise from anyone.
+pub fn message() -> impl Strategy<Value = Message> {
+    (rpc(), authority(), authority(), any::<bool>()).prop_map(
+        |(rpc, source, destination, from_claimed_source)| {
+            let source = rpc
+                .claimed_source()
+                .filter(|_| from_claimed_source)
+                .unwrap_or(source);
+            rpc.to_message(source, destination)
+        },
+    )
+}
+
+pub fn parsec_vote() -> impl Strategy<Value = ParsecVote> {
+    prop_oneof![
+        Just(ParsecVote::CheckResourceProof),
+        Just(ParsecVote::WorkUnitIncrement),
+        Just(ParsecVote::CheckRelocate),
+        Just(ParsecVote::CheckElder),
+        candidate().prop_map(ParsecVote::ExpectCandidate),
+        candidate_info().prop_map(ParsecVote::CandidateConnected),
+        (candidate(), candidate(), name())
+            .prop_map(|(old, new, voter)| ParsecVote::Online(old, new, voter)),
+        candidate().prop_map(ParsecVote::PurgeCandidate),
+        node().prop_map(ParsecVote::AddElderNode),
+        node().prop_map(ParsecVote::RemoveElderNode),
+        section_info().prop_map(ParsecVote::NewSectionInfo),
+        section_info().prop_map(ParsecVote::DkgFailed),
+        candidate().prop_map(ParsecVote::RefuseCandidate),
+        relocated_info().prop_map(ParsecVote::RelocateResponse),
+        relocated_info().prop_map(ParsecVote::RelocatedInfo),
+        node().prop_map(ParsecVote::Offline),
+        node().prop_map(ParsecVote::BackOnline),
+        section_info().prop_map(ParsecVote::NeighbourMerge),
+    ]
+}
+
+pub fn local_event() -> impl Strategy<Value = LocalEvent> {
+    prop_oneof![
+        Just(LocalEvent::TimeoutAccept),
+        Just(LocalEvent::CheckResourceProofTimeout),
+        Just(LocalEvent::TimeoutWorkUnit),
+        Just(LocalEvent::TimeoutCheckRelocate),
+        Just(LocalEvent::TimeoutCheckElder),
+        Just(LocalEvent::JoiningTimeoutResendInfo),
+        Just(LocalEvent::JoiningTimeoutProofRefused),
+        section_info().prop_map(LocalEvent::TimeoutDkg),
+        name().prop_map(LocalEvent::ResourceProofForElderReady),
+        node().prop_map(LocalEvent::NodeDetectedOffline),
+        node().prop_map(LocalEvent::NodeDetectedBackOnline),
+        name().prop_map(LocalEvent::NodeDisconnected),
+    ]
+}
+
+pub fn test_event() -> impl Strategy<Value = TestEvent> {
+    Union::new(vec![
+        sample::select(vec![ChurnNeeded::Split, ChurnNeeded::Merge])
+            .prop_map(TestEvent::SetChurnNeeded)
+            .boxed(),
+        option::of(section())
+            .prop_map(TestEvent::SetShortestPrefix)
+            .boxed(),
+        node()
+            .prop_map(TestEvent::SetWorkUnitEnoughToRelocate)
+            .boxed(),
+        (name(), proof_source())
+            .prop_map(|(name, source)| TestEvent::SetResourceProof(name, source))
+            .boxed(),
+    ])
+}
+
+// Any event a state may be given: NodeChange and ActionTriggered are only ever output.
+pub fn event() -> impl Strategy<Value = Event> {
+    Union::new(vec![
+        parsec_vote().prop_map(Event::ParsecConsensus).boxed(),
+        local_event().prop_map(Event::LocalEvent).boxed(),
+        message().prop_map(Event::Rpc).boxed(),
+        test_event().prop_map(Event::TestEvent).boxed(),
+    ])
+}
+
+// What a consistent section is built from: MemberState shares its Action when cloned, so
+// strategies produce these and build a new state for each value.
+#[derive(Debug, Clone)]
+pub struct SectionParams {
+    pub our_attributes: Attributes,
+    pub nodes: Vec<Node>,
+    pub waiting_candidates: Vec<Candidate>,
+    pub next_target_interval: Name,
+}
+
+impl SectionParams {
+    // Elders chosen as CheckElder would, and a waiting candidate for each relocation to us.
+    pub fn member_state(&self) -> MemberState {
+        let action = Action::new(
+            InnerAction::new_with_our_attributes(self.our_attributes)
+                .with_next_target_interval(self.next_target_interval)
+                .extend_current_nodes_with(&NodeState::default(), &self.nodes),
+        );
+        if let Some(change_elder) = action.check_elder() {
+            action.mark_elder_change(change_elder);
+        }
+        for candidate in &self.waiting_candidates {
+            let _ = action.add_node_waiting_candidate_info(*candidate);
+        }
+        let _ = action.take_our_events();
+
+        MemberState {
+            action,
+            ..Default::default()
+        }
+    }
+}
+
+// Enough Online nodes to fill the elders, and target intervals free for the candidates.
+pub fn section_params() -> impl Strategy<Value = SectionParams> {
+    (
+        attributes(),
+        btree_set(name(), ELDER_SIZE..=2 * ELDER_SIZE),
+        vec(age(), 2 * ELDER_SIZE),
+        vec(candidate(), 0..=MAX_WAITING_CANDIDATES),
+        name(),
+    )
+        .prop_filter(
+            "Target intervals must not be member names",
+            |(_, names, _, candidates, next_target_interval)| {
+                (0..=candidates.len() as i32)
+                    .all(|offset| !names.contains(&Name(next_target_interval.0 + offset)))
+            },
+        )
+        .prop_map(
+            |(our_attributes, names, ages, waiting_candidates, next_target_interval)| {
+                SectionParams {
+                    our_attributes,
+                    nodes: names
+                        .into_iter()
+                        .zip(ages)
+                        .map(|(name, age)| {
+                            Node(Attributes {
+                                age,
+                                name,
+                                ..Attributes::default()
+                            })
+                        })
+                        .collect(),
+                    waiting_candidates,
+                    next_target_interval,
+                }
+            },
+        )
+}
+
+// Events a section of these members may plausibly see: consensus on its own members and
+// candidates, its timeouts, and candidates from other sections.
+pub fn member_event(members: Vec<Node>) -> impl Strategy<Value = Event> {
+    let member = sample::select(members);
+    prop_oneof![
+        Just(ParsecVote::WorkUnitIncrement.to_event()),
+        Just(ParsecVote::CheckRelocate.to_event()),
+        Just(ParsecVote::CheckElder.to_event()),
+        Just(ParsecVote::CheckResourceProof.to_event()),
+        Just(LocalEvent::TimeoutWorkUnit.to_event()),
+        Just(LocalEvent::TimeoutCheckRelocate.to_event()),
+        Just(LocalEvent::TimeoutCheckElder.to_event()),
+        Just(LocalEvent::CheckResourceProofTimeout.to_event()),
+        member
+            .clone()
+            .prop_map(|node| TestEvent::SetWorkUnitEnoughToRelocate(node).to_event()),
+        member
+            .clone()
+            .prop_map(|node| LocalEvent::NodeDetectedOffline(node).to_event()),
+        member
+            .clone()
+            .prop_map(|node| ParsecVote::Offline(node).to_event()),
+        member.prop_map(|node| ParsecVote::BackOnline(node).to_event()),
+        (candidate(), section()).prop_map(|(candidate, source)| {
+            Rpc::ExpectCandidate(candidate)
+                .to_message(
+                    Authority::Section(source),
+                    Authority::Section(Section::default()),
+                )
+                .to_event()
+        }),
+        candidate().prop_map(|candidate| ParsecVote::ExpectCandidate(candidate).to_event()),
+    ]
+}
+
+// A consistent section, and a plausible stream of events for it.
+pub fn section_and_events(max_events: usize) -> impl Strategy<Value = (SectionParams, Vec<Event>)> {
+    section_params().prop_flat_map(move |params| {
+        let events = vec(member_event(params.nodes.clone()), 0..=max_events);
+        (Just(params), events)
+    })
+}
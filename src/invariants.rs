@@ -0,0 +1,181 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{
+    model_checker::Invariant,
+    state::MemberState,
+    utilities::{Event, State, ELDER_SIZE, MAX_WAITING_CANDIDATES},
+};
+use std::env;
+
+pub const ELDERS_WITHIN_ELDER_SIZE: Invariant = Invariant {
+    name: "elder count within ELDER_SIZE",
+    check: |state| {
+        let elders = state
+            .action
+            .inner()
+            .our_current_nodes
+            .values()
+            .filter(|node| node.is_elder)
+            .count();
+        if elders <= ELDER_SIZE {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} elders, at most {} expected",
+                elders, ELDER_SIZE
+            ))
+        }
+    },
+};
+
+pub const NO_RELOCATED_ELDER: Invariant = Invariant {
+    name: "no relocated elder",
+    check: |state| match state
+        .action
+        .inner()
+        .our_current_nodes
+        .values()
+        .find(|node| node.is_elder && matches!(node.state, State::Relocated(_)))
+    {
+        Some(node) => Err(format!("elder {:?} is relocated", node.node)),
+        None => Ok(()),
+    },
+};
+
+pub const WAITING_CANDIDATES_WITHIN_LIMIT: Invariant = Invariant {
+    name: "waiting candidates within MAX_WAITING_CANDIDATES",
+    check: |state| {
+        let waiting = state
+            .action
+            .inner()
+            .our_current_nodes
+            .values()
+            .filter(|node| matches!(node.state, State::WaitingCandidateInfo(_)))
+            .count();
+        if waiting <= MAX_WAITING_CANDIDATES {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} nodes wait for CandidateInfo, at most {} expected",
+                waiting, MAX_WAITING_CANDIDATES
+            ))
+        }
+    },
+};
+
+// The next waiting candidate is added under this name.
+pub const NEXT_TARGET_INTERVAL_FREE: Invariant = Invariant {
+    name: "next_target_interval not a member name",
+    check: |state| {
+        let action = state.action.inner();
+        if action
+            .our_current_nodes
+            .contains_key(&action.next_target_interval)
+        {
+            Err(format!(
+                "{:?} is already a member",
+                action.next_target_interval
+            ))
+        } else {
+            Ok(())
+        }
+    },
+};
+
+// Between the consensus on the new elders and the vote for their NewSectionInfo, the routine
+// waits for the new elders' key instead.
+pub const ELDER_CHANGE_ACTIVE_WITH_WAIT_VOTES: Invariant = Invariant {
+    name: "ProcessElderChange is active exactly while waiting for votes or the new key",
+    check: |state| {
+        let routine = &state
+            .start_merge_split_and_change_elders
+            .sub_routine_process_elder_change;
+        let generating_key = routine.dkg_started && !routine.dkg_complete;
+        let waiting = !routine.wait_votes.is_empty() || generating_key;
+        if routine.is_active == waiting {
+            Ok(())
+        } else if routine.is_active {
+            Err("active without votes or a key to wait for".to_string())
+        } else if generating_key {
+            Err("inactive but generating the new elders' key".to_string())
+        } else {
+            Err(format!("inactive but waiting for {:?}", routine.wait_votes))
+        }
+    },
+};
+
+pub const SPLIT_ACTIVE_WITH_WAIT_VOTES: Invariant = Invariant {
+    name: "ProcessSplit is active exactly while waiting for votes",
+    check: |state| {
+        let routine = &state
+            .start_merge_split_and_change_elders
+            .sub_routine_process_split;
+        if routine.is_active != routine.wait_votes.is_empty() {
+            Ok(())
+        } else if routine.is_active {
+            Err("active without votes to wait for".to_string())
+        } else {
+            Err(format!("inactive but waiting for {:?}", routine.wait_votes))
+        }
+    },
+};
+
+// ProcessMerge, ProcessSplit and ProcessElderChange each change our elders: one at a time.
+pub const ONE_SECTION_CHANGE_ACTIVE: Invariant = Invariant {
+    name: "at most one of ProcessMerge, ProcessSplit and ProcessElderChange active",
+    check: |state| {
+        let routines = &state.start_merge_split_and_change_elders;
+        let active = [
+            ("ProcessMerge", routines.sub_routine_process_merge_active),
+            ("ProcessSplit", routines.sub_routine_process_split.is_active),
+            (
+                "ProcessElderChange",
+                routines.sub_routine_process_elder_change.is_active,
+            ),
+        ]
+        .iter()
+        .filter(|(_, is_active)| *is_active)
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>();
+        if active.len() <= 1 {
+            Ok(())
+        } else {
+            Err(format!("{} all active", active.join(", ")))
+        }
+    },
+};
+
+pub const ALL: [Invariant; 7] = [
+    ELDERS_WITHIN_ELDER_SIZE,
+    NO_RELOCATED_ELDER,
+    WAITING_CANDIDATES_WITHIN_LIMIT,
+    NEXT_TARGET_INTERVAL_FREE,
+    ELDER_CHANGE_ACTIVE_WITH_WAIT_VOTES,
+    SPLIT_ACTIVE_WITH_WAIT_VOTES,
+    ONE_SECTION_CHANGE_ACTIVE,
+];
+
+// Set to check every invariant after each event the tests process.
+const CHECK_ENV_VAR_NAME: &str = "ROUTING_MODEL_CHECK_INVARIANTS";
+
+pub fn check_enabled() -> bool {
+    env::var(CHECK_ENV_VAR_NAME).is_ok()
+}
+
+pub fn assert_invariants(state: &MemberState, event: Event) {
+    for invariant in &ALL {
+        if let Err(explanation) = (invariant.check)(state) {
+            panic!(
+                "Invariant '{}' broken after {:?}: {}\n{}",
+                invariant.name, event, explanation, state
+            );
+        }
+    }
+}
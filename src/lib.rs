@@ -52,11 +52,23 @@
 #![cfg(test)]
 
 mod actions;
+mod consistency;
+mod coverage;
+mod diagrams;
 mod flows_dst;
 mod flows_elder;
 mod flows_node;
 mod flows_src;
 mod functional_tests;
+mod fuzz;
+mod generators;
+mod invariants;
+mod liveness;
+mod model_checker;
+mod replicas;
 mod scenario_tests;
+mod shrinker;
+mod simulation;
 mod state;
+mod temporal;
 mod utilities;
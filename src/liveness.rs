@@ -0,0 +1,174 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{
+    state::{JoiningState, MemberState},
+    utilities::{Event, Name, State},
+};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    mem,
+};
+
+// How long an entity may wait without advancing before it is reported stuck.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LivenessBound {
+    // Events delivered in the simulation
+    Steps(usize),
+    // Virtual time elapsed in the simulation
+    Time(u64),
+}
+
+// Something that must eventually make progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Entity {
+    // A node an elder holds as relocated or as a candidate still to join.
+    Member { elder: Name, node: Name },
+    // An elder's ProcessElderChange still waiting for votes.
+    ElderChange(Name),
+    // An elder's ProcessSplit still waiting for votes.
+    Split(Name),
+    // A joining node not yet approved or failed.
+    Joining(Name),
+}
+
+impl Entity {
+    // The node whose events lead to this entity's state.
+    fn owner(self) -> Name {
+        match self {
+            Entity::Member { elder, .. } => elder,
+            Entity::ElderChange(name) | Entity::Split(name) | Entity::Joining(name) => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stuck {
+    pub entity: Entity,
+    pub state: String,
+    // When the entity last advanced
+    pub since_step: usize,
+    pub since_time: u64,
+    // Events delivered to the entity's node, up to the one it last advanced on
+    pub events: Vec<Event>,
+}
+
+impl Display for Stuck {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        writeln!(
+            formatter,
+            "{:?} stuck since step {} (time {}) in: {}",
+            self.entity, self.since_step, self.since_time, self.state
+        )?;
+        writeln!(formatter, "After:")?;
+        for event in &self.events {
+            writeln!(formatter, "    {:?}", event)?;
+        }
+        Ok(())
+    }
+}
+
+// Tracks when each entity still owing progress last changed state.
+#[derive(Debug, Default)]
+pub struct LivenessChecker {
+    progress: BTreeMap<Entity, (String, usize, u64)>,
+}
+
+impl LivenessChecker {
+    pub fn observe(
+        &mut self,
+        step: usize,
+        now: u64,
+        elders: &BTreeMap<Name, MemberState>,
+        joining: &BTreeMap<Name, JoiningState>,
+    ) {
+        let previous = mem::take(&mut self.progress);
+        self.progress = waiting_entities(elders, joining)
+            .into_iter()
+            .map(|(entity, state)| match previous.get(&entity) {
+                Some((previous_state, since_step, since_time)) if *previous_state == state => {
+                    (entity, (state, *since_step, *since_time))
+                }
+                _ => (entity, (state, step, now)),
+            })
+            .collect();
+    }
+
+    pub fn stuck(
+        &self,
+        bound: LivenessBound,
+        step: usize,
+        now: u64,
+        delivered: &[(Name, Event)],
+    ) -> Vec<Stuck> {
+        self.progress
+            .iter()
+            .filter(|(_, (_, since_step, since_time))| match bound {
+                LivenessBound::Steps(steps) => step - since_step > steps,
+                LivenessBound::Time(time) => now - since_time > time,
+            })
+            .map(|(entity, (state, since_step, since_time))| Stuck {
+                entity: *entity,
+                state: state.clone(),
+                since_step: *since_step,
+                since_time: *since_time,
+                events: delivered[..*since_step]
+                    .iter()
+                    .filter(|(name, _)| *name == entity.owner())
+                    .map(|(_, event)| *event)
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+fn waiting_entities(
+    elders: &BTreeMap<Name, MemberState>,
+    joining: &BTreeMap<Name, JoiningState>,
+) -> BTreeMap<Entity, String> {
+    let mut entities = BTreeMap::new();
+
+    for (elder, state) in elders {
+        for (node, node_state) in &state.action.inner().our_current_nodes {
+            if let State::Relocated(_) | State::WaitingCandidateInfo(_) | State::WaitingProofing =
+                node_state.state
+            {
+                let entity = Entity::Member {
+                    elder: *elder,
+                    node: *node,
+                };
+                let _ = entities.insert(entity, format!("{:?}", node_state.state));
+            }
+        }
+
+        let routines = &state.start_merge_split_and_change_elders;
+        if routines.sub_routine_process_elder_change.is_active {
+            let _ = entities.insert(
+                Entity::ElderChange(*elder),
+                format!("{:?}", routines.sub_routine_process_elder_change),
+            );
+        }
+        if routines.sub_routine_process_split.is_active {
+            let _ = entities.insert(
+                Entity::Split(*elder),
+                format!("{:?}", routines.sub_routine_process_split),
+            );
+        }
+    }
+
+    for (name, state) in joining {
+        let routine = &state.join_routine;
+        if routine.routine_complete_output.is_none() && routine.routine_failure_output.is_none() {
+            let _ = entities.insert(Entity::Joining(*name), format!("{:?}", routine));
+        }
+    }
+
+    entities
+}
@@ -0,0 +1,178 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{
+    actions::Action,
+    state::MemberState,
+    utilities::{Event, TryResult},
+};
+use std::{
+    any::Any,
+    collections::{BTreeSet, VecDeque},
+    fmt::{self, Display, Formatter},
+    panic::{self, AssertUnwindSafe},
+};
+
+// A property every reachable MemberState must have: Err explains how it is broken.
+#[derive(Clone, Copy)]
+pub struct Invariant {
+    pub name: &'static str,
+    pub check: fn(&MemberState) -> Result<(), String>,
+}
+
+// The shortest sequence of events from the start state that breaks an invariant.
+#[derive(Debug, PartialEq)]
+pub struct Counterexample {
+    pub invariant: &'static str,
+    pub explanation: String,
+    pub trace: Vec<Event>,
+}
+
+impl Display for Counterexample {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        writeln!(
+            formatter,
+            "Invariant '{}' broken: {}",
+            self.invariant, self.explanation
+        )?;
+        writeln!(formatter, "After {} events:", self.trace.len())?;
+        for event in &self.trace {
+            writeln!(formatter, "    {:?}", event)?;
+        }
+        Ok(())
+    }
+}
+
+// Opt-in: every event must be handled in every state it can be delivered in. An unhandled
+// event is otherwise recorded as the state's failure, as the scenario tests do.
+pub const EVERY_EVENT_HANDLED: Invariant = Invariant {
+    name: "every event handled",
+    check: |state| match state.failure {
+        Some(event) => Err(format!("{:?} was not handled", event)),
+        None => Ok(()),
+    },
+};
+
+// The model must not panic on any order of events.
+const NO_PANIC: &str = "no panic";
+
+// Explores every order in which `events` may be delivered, each at most once, up to
+// `max_depth` events deep. An event that may happen more than once is listed as many times.
+// Breadth first, so the first counterexample found is a shortest one. A state already reached
+// with the same events left to deliver is not explored again.
+#[derive(Debug)]
+pub struct ModelChecker {
+    pub events: Vec<Event>,
+    pub max_depth: usize,
+}
+
+impl ModelChecker {
+    // Returns the number of distinct states reached when no invariant is broken.
+    pub fn check(
+        &self,
+        start: &MemberState,
+        invariants: &[Invariant],
+    ) -> Result<usize, Counterexample> {
+        let start = fork(start);
+        let _ = start.action.take_our_events();
+        check_invariants(&start, invariants, &[])?;
+
+        let delivered = vec![false; self.events.len()];
+        let mut visited = BTreeSet::new();
+        let _ = visited.insert((fingerprint(&start), delivered.clone()));
+        let mut queue = VecDeque::new();
+        queue.push_back((start, delivered, Vec::new()));
+
+        while let Some((state, delivered, trace)) = queue.pop_front() {
+            if trace.len() >= self.max_depth {
+                continue;
+            }
+
+            for (index, event) in self.events.iter().enumerate() {
+                // The same event listed twice would only lead to the same states.
+                if delivered[index]
+                    || self.events[..index]
+                        .iter()
+                        .enumerate()
+                        .any(|(earlier, other)| other == event && !delivered[earlier])
+                {
+                    continue;
+                }
+
+                let mut next = fork(&state);
+                let mut next_delivered = delivered.clone();
+                next_delivered[index] = true;
+                let mut next_trace = trace.clone();
+                next_trace.push(*event);
+
+                match panic::catch_unwind(AssertUnwindSafe(|| next.try_next(*event))) {
+                    Ok(TryResult::Handled) => (),
+                    Ok(TryResult::Unhandled) => next.failure_event(*event),
+                    Err(payload) => {
+                        return Err(Counterexample {
+                            invariant: NO_PANIC,
+                            explanation: panic_message(&*payload),
+                            trace: next_trace,
+                        });
+                    }
+                }
+                // The events we output are the transition, not part of the state reached.
+                let _ = next.action.take_our_events();
+                check_invariants(&next, invariants, &next_trace)?;
+
+                if visited.insert((fingerprint(&next), next_delivered.clone())) {
+                    queue.push_back((next, next_delivered, next_trace));
+                }
+            }
+        }
+
+        Ok(visited.len())
+    }
+}
+
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<String>()
+        .cloned()
+        .or_else(|| {
+            payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+        })
+        .unwrap_or_else(|| "panicked".to_string())
+}
+
+fn check_invariants(
+    state: &MemberState,
+    invariants: &[Invariant],
+    trace: &[Event],
+) -> Result<(), Counterexample> {
+    for invariant in invariants {
+        if let Err(explanation) = (invariant.check)(state) {
+            return Err(Counterexample {
+                invariant: invariant.name,
+                explanation,
+                trace: trace.to_vec(),
+            });
+        }
+    }
+    Ok(())
+}
+
+// Cloning a MemberState shares its Action: branches each need their own.
+fn fork(state: &MemberState) -> MemberState {
+    MemberState {
+        action: Action::new(state.action.inner()),
+        ..state.clone()
+    }
+}
+
+fn fingerprint(state: &MemberState) -> String {
+    format!("{:?}", state)
+}
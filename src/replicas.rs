@@ -0,0 +1,192 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{
+    state::MemberState,
+    utilities::{Event, Name, ParsecVote},
+};
+use rand::{seq::SliceRandom, Rng};
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display, Formatter},
+    mem,
+};
+
+// A field the replicas disagree on after the same consensus.
+#[derive(Debug, PartialEq)]
+pub struct Divergence {
+    pub field: String,
+    // How many consensus events every replica was delivered
+    pub consensus_count: usize,
+    pub first: Name,
+    pub first_value: String,
+    pub replica: Name,
+    pub replica_value: String,
+    // Events each of the two replicas was delivered, in order
+    pub first_events: Vec<Event>,
+    pub replica_events: Vec<Event>,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        writeln!(
+            formatter,
+            "Replicas diverge on {} after {} consensus events:",
+            self.field, self.consensus_count
+        )?;
+        for (name, value, events) in &[
+            (self.first, &self.first_value, &self.first_events),
+            (self.replica, &self.replica_value, &self.replica_events),
+        ] {
+            writeln!(formatter, "{:?} has {}, after:", name, value)?;
+            for event in events.iter() {
+                writeln!(formatter, "    {:?}", event)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Runs the elders of one section side by side. Each is delivered the same `consensus`, in
+// order, and its own local events and Rpcs, shuffled in between: consensus alone must decide
+// what they agree on.
+#[derive(Debug)]
+pub struct ReplicaChecker {
+    pub consensus: Vec<ParsecVote>,
+}
+
+impl ReplicaChecker {
+    // Compares the replicas after each consensus event and once all events are delivered.
+    pub fn check<R: Rng>(
+        &self,
+        rng: &mut R,
+        replicas: Vec<(MemberState, Vec<Event>)>,
+    ) -> Result<(), Box<Divergence>> {
+        let mut replicas = replicas
+            .into_iter()
+            .map(|(state, events)| Replica::new(rng, state, events, self.consensus.len()))
+            .collect::<Vec<_>>();
+
+        for (index, vote) in self.consensus.iter().enumerate() {
+            for replica in &mut replicas {
+                replica.deliver_others(index);
+                replica.deliver(vote.to_event());
+            }
+            compare(&replicas, index + 1)?;
+        }
+
+        for replica in &mut replicas {
+            replica.deliver_others(self.consensus.len());
+        }
+        compare(&replicas, self.consensus.len())
+    }
+}
+
+struct Replica {
+    state: MemberState,
+    // The events to deliver before each consensus event, the last ones after all of them.
+    others: Vec<Vec<Event>>,
+    delivered: Vec<Event>,
+}
+
+impl Replica {
+    fn new<R: Rng>(
+        rng: &mut R,
+        state: MemberState,
+        mut events: Vec<Event>,
+        consensus_count: usize,
+    ) -> Self {
+        events.shuffle(rng);
+        let mut others = vec![Vec::new(); consensus_count + 1];
+        for event in events {
+            others[rng.gen_range(0, consensus_count + 1)].push(event);
+        }
+
+        let _ = state.action.take_our_events();
+        Self {
+            state,
+            others,
+            delivered: Vec::new(),
+        }
+    }
+
+    fn deliver_others(&mut self, index: usize) {
+        for event in mem::take(&mut self.others[index]) {
+            self.deliver(event);
+        }
+    }
+
+    // Our votes and Rpcs only matter to the other nodes: the consensus stream stands for them.
+    fn deliver(&mut self, event: Event) {
+        let _ = self.state.try_next(event);
+        let _ = self.state.action.take_our_events();
+        self.delivered.push(event);
+    }
+}
+
+// What the elders must agree on, field by field: our section and each member.
+fn agreed_fields(state: &MemberState) -> Vec<(String, String)> {
+    let inner = state.action.inner();
+    let mut fields = vec![(
+        "our_section".to_string(),
+        format!("{:?}", inner.our_section),
+    )];
+    for (name, node_state) in &inner.our_current_nodes {
+        let field = |member_field: &str| format!("our_current_nodes[{:?}].{}", name, member_field);
+        fields.extend(vec![
+            (field("node"), format!("{:?}", node_state.node)),
+            (field("state"), format!("{:?}", node_state.state)),
+            (field("is_elder"), format!("{:?}", node_state.is_elder)),
+            (
+                field("work_units_done"),
+                format!("{:?}", node_state.work_units_done),
+            ),
+        ]);
+    }
+    fields
+}
+
+// The first field a replica has different from the first replica, in the order listed.
+fn compare(replicas: &[Replica], consensus_count: usize) -> Result<(), Box<Divergence>> {
+    let first = &replicas[0];
+    let first_fields = agreed_fields(&first.state);
+
+    for replica in &replicas[1..] {
+        let replica_fields = agreed_fields(&replica.state);
+        let value = |fields: &[(String, String)], field: &str| {
+            fields
+                .iter()
+                .find(|(name, _)| name == field)
+                .map(|(_, value)| value.clone())
+                .unwrap_or_else(|| "no such member".to_string())
+        };
+
+        let mut seen = BTreeSet::new();
+        for (field, _) in first_fields.iter().chain(&replica_fields) {
+            if !seen.insert(field) {
+                continue;
+            }
+            let first_value = value(&first_fields, field);
+            let replica_value = value(&replica_fields, field);
+            if first_value != replica_value {
+                return Err(Box::new(Divergence {
+                    field: field.clone(),
+                    consensus_count,
+                    first: first.state.action.our_name(),
+                    first_value,
+                    replica: replica.state.action.our_name(),
+                    replica_value,
+                    first_events: first.delivered.clone(),
+                    replica_events: replica.delivered.clone(),
+                }));
+            }
+        }
+    }
+    Ok(())
+}
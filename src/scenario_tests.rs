@@ -9,42 +9,62 @@
 
 use crate::{
     actions::{Action, InnerAction},
+    invariants,
     state::{JoiningState, MemberState},
+    temporal::{self, event, until},
     utilities::{
-        ActionTriggered, Age, Attributes, Candidate, CandidateInfo, ChurnNeeded, Event,
-        GenesisPfxInfo, LocalEvent, Name, Node, NodeChange, NodeState, ParsecVote, Proof,
-        ProofRequest, ProofSource, RelocatedInfo, Rpc, Section, SectionInfo, State, TestEvent,
-        TryResult,
+        ActionTriggered, Age, Attributes, Authority, Candidate, CandidateInfo, ChurnNeeded,
+        CountingScheme, Event, GenesisPfxInfo, JoiningFailure, LocalEvent, Message, MessageId,
+        Name, Node, NodeChange, NodeState, ParsecVote, Proof, ProofRequest, ProofSource, PublicKey,
+        RelocatedInfo, ResourceProofScheme, Rpc, SecretKey, Section, SectionElders, SectionInfo,
+        SectionLoad, SectionProof, Signature, State, TestEvent, TryResult,
     },
 };
 use lazy_static::lazy_static;
 use pretty_assertions::assert_eq;
+use unwrap::unwrap;
+
+// Held by the nodes owning the identities that sign in these tests.
+const SECRET_KEY_1_OLD: SecretKey = SecretKey(7_001);
+const SECRET_KEY_1: SecretKey = SecretKey(7_011);
+const SECRET_KEY_2_OLD: SecretKey = SecretKey(7_002);
+const SECRET_KEY_2: SecretKey = SecretKey(7_012);
+const SECRET_KEY_132_OLD: SecretKey = SecretKey(7_132);
+const SECRET_KEY_132: SecretKey = SecretKey(7_232);
+// The other nodes never sign, nor does a candidate before its CandidateInfo.
+const NO_KEY: PublicKey = PublicKey(0);
 
 const ATTRIBUTES_1_OLD: Attributes = Attributes {
     name: Name(1001),
     age: Age(9),
+    key: SECRET_KEY_1_OLD.public_key(),
 };
 const ATTRIBUTES_1: Attributes = Attributes {
     name: Name(1),
     age: Age(10),
+    key: SECRET_KEY_1.public_key(),
 };
 
 const ATTRIBUTES_2_OLD: Attributes = Attributes {
     name: Name(1002),
     age: Age(9),
+    key: SECRET_KEY_2_OLD.public_key(),
 };
 const ATTRIBUTES_2: Attributes = Attributes {
     name: Name(2),
     age: Age(10),
+    key: SECRET_KEY_2.public_key(),
 };
 
 const ATTRIBUTES_132_OLD: Attributes = Attributes {
     name: Name(132),
     age: Age(31),
+    key: SECRET_KEY_132_OLD.public_key(),
 };
 const ATTRIBUTES_132: Attributes = Attributes {
     name: Name(132),
     age: Age(32),
+    key: SECRET_KEY_132.public_key(),
 };
 
 const CANDIDATE_1_OLD: Candidate = Candidate(ATTRIBUTES_1_OLD);
@@ -54,15 +74,20 @@ const CANDIDATE_2: Candidate = Candidate(ATTRIBUTES_2);
 const CANDIDATE_130: Candidate = Candidate(Attributes {
     name: Name(130),
     age: Age(30),
+    key: NO_KEY,
 });
 const CANDIDATE_205: Candidate = Candidate(Attributes {
     name: Name(205),
     age: Age(5),
+    key: NO_KEY,
 });
 const OTHER_SECTION_1: Section = Section(1);
 const OTHER_SECTION_2: Section = Section(2);
 const DST_SECTION_200: Section = Section(200);
+const SRC_SECTION_300: Section = Section(300);
 const MERGED_SECTION_2: Section = Section(2);
+// get_best_relocating_node_and_target does not pick a target section yet.
+const RELOCATION_TARGET: Section = Section(0);
 
 const NODE_1_OLD: Node = Node(ATTRIBUTES_1_OLD);
 const NODE_1: Node = Node(ATTRIBUTES_1);
@@ -74,50 +99,124 @@ const SET_ONLINE_NODE_1: NodeChange =
 const NODE_ELDER_109: Node = Node(Attributes {
     name: Name(109),
     age: Age(9),
+    key: NO_KEY,
 });
 const NODE_ELDER_110: Node = Node(Attributes {
     name: Name(110),
     age: Age(10),
+    key: NO_KEY,
 });
 const NODE_ELDER_111: Node = Node(Attributes {
     name: Name(111),
     age: Age(11),
+    key: NO_KEY,
 });
 const NODE_ELDER_130: Node = Node(Attributes {
     name: Name(130),
     age: Age(30),
+    key: NO_KEY,
 });
 const NODE_ELDER_131: Node = Node(Attributes {
     name: Name(131),
     age: Age(31),
+    key: NO_KEY,
 });
 const NODE_ELDER_132: Node = Node(ATTRIBUTES_132);
 
 const NAME_109: Name = NODE_ELDER_109.0.name;
 const NAME_110: Name = NODE_ELDER_110.0.name;
 const NAME_111: Name = NODE_ELDER_111.0.name;
+const NAME_130: Name = NODE_ELDER_130.0.name;
+const NAME_131: Name = NODE_ELDER_131.0.name;
+const NAME_132: Name = NODE_ELDER_132.0.name;
 
 const YOUNG_ADULT_205: Node = Node(Attributes {
     name: Name(205),
     age: Age(5),
+    key: NO_KEY,
 });
-const SECTION_INFO_1: SectionInfo = SectionInfo(OUR_SECTION, 1);
-const SECTION_INFO_2: SectionInfo = SectionInfo(OUR_SECTION, 2);
-const DST_SECTION_INFO_200: SectionInfo = SectionInfo(DST_SECTION_200, 0);
-
-const OTHER_SECTION_INFO: SectionInfo = SectionInfo(OTHER_SECTION_1, 0);
-const REMOTE_OTHER_SECTION_INFO: SectionInfo = SectionInfo(OTHER_SECTION_2, 0);
-const MERGED_SECTION_INFO: SectionInfo = SectionInfo(MERGED_SECTION_2, 0);
-
-const SPLIT_SECTION_INFO_1: SectionInfo = SectionInfo(Section(1), 0);
-const SPLIT_SECTION_INFO_2: SectionInfo = SectionInfo(Section(2), 0);
-
-const CANDIDATE_INFO_VALID_1: CandidateInfo = CandidateInfo {
-    old_public_id: CANDIDATE_1_OLD,
-    new_public_id: CANDIDATE_1,
-    destination: OUR_NAME,
-    waiting_candidate_name: TARGET_INTERVAL_1,
-    valid: true,
+const NO_ELDERS: SectionElders = SectionElders::new(&[]);
+// Once CANDIDATE_1 replaced NODE_ELDER_109 (young elders).
+const SECTION_INFO_1: SectionInfo = SectionInfo(
+    OUR_SECTION,
+    1,
+    SectionElders::new(&[NODE_1, NODE_ELDER_110, NODE_ELDER_132]),
+);
+// Once YOUNG_ADULT_205 replaced NODE_ELDER_130 (old elders).
+const SECTION_INFO_1_OLD_ELDERS: SectionInfo = SectionInfo(
+    OUR_SECTION,
+    1,
+    SectionElders::new(&[YOUNG_ADULT_205, NODE_ELDER_131, NODE_ELDER_132]),
+);
+const SECTION_INFO_2: SectionInfo = SectionInfo(OUR_SECTION, 2, NO_ELDERS);
+const DST_SECTION_INFO_200: SectionInfo = SectionInfo(
+    DST_SECTION_200,
+    0,
+    SectionElders::new(&[NODE_ELDER_109, NODE_ELDER_110, NODE_ELDER_111]),
+);
+const SRC_SECTION_INFO_300: SectionInfo = SectionInfo(
+    SRC_SECTION_300,
+    0,
+    SectionElders::new(&SRC_SECTION_300_ELDERS),
+);
+const SRC_SECTION_300_ELDERS: [Node; 3] = [
+    Node(Attributes {
+        name: Name(301),
+        age: Age(31),
+        key: NO_KEY,
+    }),
+    Node(Attributes {
+        name: Name(302),
+        age: Age(32),
+        key: NO_KEY,
+    }),
+    Node(Attributes {
+        name: Name(303),
+        age: Age(33),
+        key: NO_KEY,
+    }),
+];
+
+const OTHER_SECTION_INFO: SectionInfo = SectionInfo(OTHER_SECTION_1, 0, NO_ELDERS);
+// Once the elders of OTHER_SECTION_INFO handed over to new ones.
+const OTHER_SECTION_INFO_NEW_ELDERS: SectionInfo = SectionInfo(
+    OTHER_SECTION_1,
+    1,
+    SectionElders::new(&[NODE_ELDER_109, NODE_ELDER_110, NODE_ELDER_111]),
+);
+const REMOTE_OTHER_SECTION_INFO: SectionInfo = SectionInfo(OTHER_SECTION_2, 0, NO_ELDERS);
+// Our old elders are the oldest of both sections.
+const MERGED_SECTION_INFO: SectionInfo = SectionInfo(
+    MERGED_SECTION_2,
+    0,
+    SectionElders::new(&[NODE_ELDER_130, NODE_ELDER_131, NODE_ELDER_132]),
+);
+
+// Our old elders' members split by name.
+const SPLIT_SECTION_INFO_1: SectionInfo = SectionInfo(
+    Section(1),
+    0,
+    SectionElders::new(&[NODE_ELDER_130, NODE_ELDER_131]),
+);
+const SPLIT_SECTION_INFO_2: SectionInfo = SectionInfo(
+    Section(2),
+    0,
+    SectionElders::new(&[NODE_ELDER_132, YOUNG_ADULT_205]),
+);
+
+const CANDIDATE_INFO_VALID_1: CandidateInfo = CandidateInfo::new(
+    CANDIDATE_1_OLD,
+    CANDIDATE_1,
+    OUR_NAME,
+    TARGET_INTERVAL_1,
+    SIGNED_RELOCATED_INFO_1.source_proof,
+    SECRET_KEY_1_OLD,
+    SECRET_KEY_1,
+);
+// The info all elders vote for once the candidate is connected.
+const CANDIDATE_INFO_VOTED_1: CandidateInfo = CandidateInfo {
+    destination: TARGET_INTERVAL_1,
+    ..CANDIDATE_INFO_VALID_1
 };
 const REMOVE_CANDIDATE_1: NodeChange = NodeChange::Remove(TARGET_INTERVAL_1);
 
@@ -126,14 +225,20 @@ const CANDIDATE_RELOCATED_INFO_1: RelocatedInfo = RelocatedInfo {
     expected_age: Age(CANDIDATE_1_OLD.0.age.0 + 1),
     target_interval_centre: TARGET_INTERVAL_1,
     section_info: OUR_INITIAL_SECTION_INFO,
+    source_proof: None,
 };
+// As the source section sent it to the candidate.
+const SIGNED_RELOCATED_INFO_1: RelocatedInfo =
+    CANDIDATE_RELOCATED_INFO_1.signed_by(SRC_SECTION_INFO_300, &SRC_SECTION_300_ELDERS);
 
 const CANDIDATE_RELOCATED_INFO_132: RelocatedInfo = RelocatedInfo {
     candidate: OUR_NODE_CANDIDATE_OLD,
     expected_age: OUR_NODE.0.age,
     target_interval_centre: TARGET_INTERVAL_1,
     section_info: DST_SECTION_INFO_200,
-};
+    source_proof: None,
+}
+.signed_by(SRC_SECTION_INFO_300, &SRC_SECTION_300_ELDERS);
 
 const CANDIDATE_INFO_VALID_RPC_1: Rpc = Rpc::CandidateInfo(CANDIDATE_INFO_VALID_1);
 const TARGET_INTERVAL_1: Name = Name(1234);
@@ -145,13 +250,22 @@ const OUR_NODE: Node = Node(ATTRIBUTES_132);
 const OUR_NAME: Name = OUR_NODE.0.name;
 const OUR_NODE_CANDIDATE: Candidate = Candidate(OUR_NODE.0);
 const OUR_NODE_CANDIDATE_OLD: Candidate = Candidate(OUR_NODE_OLD.0);
-const OUR_PROOF_REQUEST: ProofRequest = ProofRequest { value: OUR_NAME.0 };
-const OUR_INITIAL_SECTION_INFO: SectionInfo = SectionInfo(OUR_SECTION, 0);
+const OUR_INITIAL_SECTION_INFO: SectionInfo = SectionInfo(OUR_SECTION, 0, NO_ELDERS);
 const OUR_GENESIS_INFO: GenesisPfxInfo = GenesisPfxInfo(OUR_INITIAL_SECTION_INFO);
 
 lazy_static! {
+    static ref OUR_PROOF_REQUEST: ProofRequest =
+        CountingScheme.request(OUR_NAME, CANDIDATE_1.name(), 0, SectionLoad::default());
+    static ref CANDIDATE_1_PROOF_PART: Proof = OUR_PROOF_REQUEST.chunk(0);
+    static ref CANDIDATE_1_PROOF_END: Proof = OUR_PROOF_REQUEST.chunk(1);
+    static ref PROOF_REQUEST_110: ProofRequest =
+        CountingScheme.request(NAME_110, OUR_NAME, 0, SectionLoad::default());
+    static ref PROOF_REQUEST_111: ProofRequest =
+        CountingScheme.request(NAME_111, OUR_NAME, 0, SectionLoad::default());
     static ref INNER_ACTION_132: InnerAction = InnerAction::new_with_our_attributes(OUR_NODE.0)
-        .with_next_target_interval(TARGET_INTERVAL_1);
+        .with_next_target_interval(TARGET_INTERVAL_1)
+        .with_section_members(SRC_SECTION_INFO_300, &SRC_SECTION_300_ELDERS)
+        .with_secret_keys(SECRET_KEY_132_OLD, SECRET_KEY_132);
     static ref INNER_ACTION_YOUNG_ELDERS: InnerAction = INNER_ACTION_132
         .clone()
         .extend_current_nodes_with(
@@ -166,6 +280,9 @@ lazy_static! {
             &[NODE_ELDER_130, NODE_ELDER_131, NODE_ELDER_132]
         )
         .extend_current_nodes_with(&NodeState::default(), &[YOUNG_ADULT_205]);
+    static ref INNER_ACTION_OLD_ELDERS_WITH_NEIGHBOUR: InnerAction = INNER_ACTION_OLD_ELDERS
+        .clone()
+        .with_neighbour_infos(&[OTHER_SECTION_INFO]);
     static ref INNER_ACTION_YOUNG_ELDERS_WITH_WAITING_ELDER: InnerAction = INNER_ACTION_132
         .clone()
         .extend_current_nodes_with(
@@ -186,10 +303,14 @@ struct AssertState {
 }
 
 fn process_events(mut state: MemberState, events: &[Event]) -> MemberState {
+    let check_invariants = invariants::check_enabled();
     for event in events.iter().cloned() {
         if TryResult::Unhandled == state.try_next(event) {
             state.failure_event(event);
         }
+        if check_invariants {
+            invariants::assert_invariants(&state, event);
+        }
 
         if state.failure.is_some() {
             break;
@@ -210,7 +331,7 @@ fn run_test(
 
     let final_state = (
         AssertState {
-            action_our_events: action.our_events,
+            action_our_events: without_message_ids(action.our_events),
         },
         final_state.failure,
     );
@@ -219,6 +340,39 @@ fn run_test(
     assert_eq!(expected_state, final_state, "{}", test_name);
 }
 
+// Each event followed by the events we output handling it.
+fn event_trace(state: &MemberState, events: &[Event]) -> Vec<Event> {
+    let mut state = MemberState {
+        action: Action::new(state.action.inner()),
+        ..state.clone()
+    };
+
+    let mut trace = Vec::new();
+    for event in events {
+        let output_start = state.action.inner().our_events.len();
+        let _ = state.try_next(*event);
+        trace.push(*event);
+        let output = state.action.inner().our_events[output_start..].to_vec();
+        trace.extend(without_message_ids(output));
+    }
+    trace
+}
+
+// Each message we send has its own id: compare our messages by what they carry.
+fn without_message_ids(events: Vec<Event>) -> Vec<Event> {
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::Rpc(message) => Message {
+                id: MessageId::default(),
+                ..message
+            }
+            .to_event(),
+            event => event,
+        })
+        .collect()
+}
+
 fn arrange_initial_state(state: &MemberState, events: &[Event]) -> MemberState {
     let state = process_events(state.clone(), events);
     state.action.remove_processed_state();
@@ -239,15 +393,78 @@ fn initial_state_old_elders() -> MemberState {
     }
 }
 
+fn initial_state_old_elders_with_neighbour() -> MemberState {
+    MemberState {
+        action: Action::new(INNER_ACTION_OLD_ELDERS_WITH_NEIGHBOUR.clone()),
+        ..Default::default()
+    }
+}
+
 fn get_relocated_info(candidate: Candidate, section_info: SectionInfo) -> RelocatedInfo {
     RelocatedInfo {
         candidate,
         expected_age: candidate.0.age.increment_by_one(),
         target_interval_centre: TARGET_INTERVAL_1,
         section_info,
+        source_proof: None,
     }
 }
 
+// Rpc sent by the elders of `section` on its behalf.
+fn section_rpc(section: Section, destination: Authority, rpc: Rpc) -> Event {
+    rpc.to_message(Authority::Section(section), destination)
+        .to_event()
+}
+
+// The link a section sends its neighbours once `info` follows `previous`.
+fn neighbour_update(previous: SectionInfo, info: SectionInfo) -> Event {
+    section_rpc(
+        info.0,
+        Authority::Neighbours(info.0),
+        Rpc::NeighbourUpdate {
+            info,
+            proof: previous.sign_successor(info),
+        },
+    )
+}
+
+// Our contributions to the key generation for the new elders of `info`, and its timeout.
+fn our_dkg_start(info: SectionInfo) -> Vec<Event> {
+    info.2
+        .nodes()
+        .iter()
+        .filter(|node| node.name() != OUR_NAME)
+        .map(|node| {
+            Rpc::DkgContribution {
+                source: OUR_NAME,
+                destination: node.name(),
+                info,
+            }
+            .to_event()
+        })
+        .chain(std::iter::once(
+            ActionTriggered::Scheduled(LocalEvent::TimeoutDkg(info)).to_event(),
+        ))
+        .collect()
+}
+
+// The contributions of the other new elders of `info`, completing our key generation.
+fn dkg_contributions(info: SectionInfo) -> Vec<Event> {
+    info.2
+        .nodes()
+        .iter()
+        .filter(|node| node.name() != OUR_NAME)
+        .map(|node| {
+            Rpc::DkgContribution {
+                source: node.name(),
+                destination: OUR_NAME,
+                info,
+            }
+            .to_event()
+        })
+        .collect()
+}
+
 //////////////////
 /// Dst
 //////////////////
@@ -260,13 +477,64 @@ mod dst_tests {
         run_test(
             "",
             &initial_state_old_elders(),
-            &[Rpc::ExpectCandidate(CANDIDATE_1_OLD).to_event()],
+            &[section_rpc(
+                SRC_SECTION_300,
+                Authority::Section(OUR_SECTION),
+                Rpc::ExpectCandidate(CANDIDATE_1_OLD),
+            )],
             &AssertState {
                 action_our_events: vec![ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event()],
             },
         );
     }
 
+    #[test]
+    fn rpc_expect_candidate_from_a_node() {
+        run_test(
+            "Only a section may relocate a node to us: reject.",
+            &initial_state_old_elders(),
+            &[Rpc::ExpectCandidate(CANDIDATE_1_OLD)
+                .to_message(Authority::Node(NAME_110), Authority::Section(OUR_SECTION))
+                .to_event()],
+            &AssertState {
+                action_our_events: vec![ActionTriggered::UnexpectedAuthorityRejected.to_event()],
+            },
+        );
+    }
+
+    #[test]
+    fn rpc_expect_candidate_from_neighbours() {
+        run_test(
+            "Neighbours only address the sections receiving a message: reject as a source.",
+            &initial_state_old_elders(),
+            &[Rpc::ExpectCandidate(CANDIDATE_1_OLD)
+                .to_message(
+                    Authority::Neighbours(SRC_SECTION_300),
+                    Authority::Section(OUR_SECTION),
+                )
+                .to_event()],
+            &AssertState {
+                action_our_events: vec![ActionTriggered::UnexpectedAuthorityRejected.to_event()],
+            },
+        );
+    }
+
+    #[test]
+    fn rpc_expect_candidate_to_other_section() {
+        run_test(
+            "ExpectCandidate addressed to another section: reject.",
+            &initial_state_old_elders(),
+            &[section_rpc(
+                SRC_SECTION_300,
+                Authority::Section(OTHER_SECTION_1),
+                Rpc::ExpectCandidate(CANDIDATE_1_OLD),
+            )],
+            &AssertState {
+                action_our_events: vec![ActionTriggered::UnexpectedAuthorityRejected.to_event()],
+            },
+        );
+    }
+
     #[test]
     fn parsec_expect_candidate() {
         run_test(
@@ -282,11 +550,16 @@ mod dst_tests {
                         Node(Attributes {
                             name: TARGET_INTERVAL_1,
                             age: CANDIDATE_1.0.age,
+                            key: NO_KEY,
                         }),
                         State::WaitingCandidateInfo(CANDIDATE_RELOCATED_INFO_1),
                     )
                     .to_event(),
-                    Rpc::RelocateResponse(CANDIDATE_RELOCATED_INFO_1).to_event(),
+                    section_rpc(
+                        OUR_SECTION,
+                        Authority::SectionOf(CANDIDATE_1_OLD.name()),
+                        Rpc::RelocateResponse(CANDIDATE_RELOCATED_INFO_1),
+                    ),
                     ActionTriggered::Scheduled(LocalEvent::TimeoutAccept).to_event(),
                 ],
             },
@@ -305,9 +578,11 @@ mod dst_tests {
             &initial_state,
             &[ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event()],
             &AssertState {
-                action_our_events: vec![
-                    Rpc::RelocateResponse(CANDIDATE_RELOCATED_INFO_1).to_event()
-                ],
+                action_our_events: vec![section_rpc(
+                    OUR_SECTION,
+                    Authority::SectionOf(CANDIDATE_1_OLD.name()),
+                    Rpc::RelocateResponse(CANDIDATE_RELOCATED_INFO_1),
+                )],
             },
         );
     }
@@ -323,14 +598,14 @@ mod dst_tests {
         );
 
         run_test(
-            "Start resource proofing candidate: Send RPC.",
+            "Start resource proofing connected candidate: Send RPC.",
             &initial_state,
-            &[CANDIDATE_INFO_VALID_RPC_1.to_event()],
+            &[ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event()],
             &AssertState {
                 action_our_events: vec![Rpc::ResourceProof {
                     candidate: CANDIDATE_1,
                     source: OUR_NAME,
-                    proof: OUR_PROOF_REQUEST,
+                    proof: *OUR_PROOF_REQUEST,
                 }
                 .to_event()],
             },
@@ -344,19 +619,19 @@ mod dst_tests {
             &[
                 ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
                 ParsecVote::CheckResourceProof.to_event(),
-                CANDIDATE_INFO_VALID_RPC_1.to_event(),
+                ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
             ],
         );
 
         run_test(
-            "Start resource proofing candidate: Send same RPC again.",
+            "Start resource proofing connected candidate: Send same RPC again.",
             &initial_state,
-            &[CANDIDATE_INFO_VALID_RPC_1.to_event()],
+            &[ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event()],
             &AssertState {
                 action_our_events: vec![Rpc::ResourceProof {
                     candidate: CANDIDATE_1,
                     source: OUR_NAME,
-                    proof: OUR_PROOF_REQUEST,
+                    proof: *OUR_PROOF_REQUEST,
                 }
                 .to_event()],
             },
@@ -370,7 +645,7 @@ mod dst_tests {
             &[
                 ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
                 ParsecVote::CheckResourceProof.to_event(),
-                CANDIDATE_INFO_VALID_RPC_1.to_event(),
+                ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
             ],
         );
 
@@ -379,9 +654,11 @@ mod dst_tests {
             &initial_state,
             &[ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event()],
             &AssertState {
-                action_our_events: vec![
-                    Rpc::RelocateResponse(CANDIDATE_RELOCATED_INFO_1).to_event()
-                ],
+                action_our_events: vec![section_rpc(
+                    OUR_SECTION,
+                    Authority::SectionOf(CANDIDATE_1_OLD.name()),
+                    Rpc::RelocateResponse(CANDIDATE_RELOCATED_INFO_1),
+                )],
             },
         );
     }
@@ -400,7 +677,11 @@ mod dst_tests {
             &initial_state,
             &[ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event()],
             &AssertState {
-                action_our_events: vec![Rpc::ExpectCandidate(CANDIDATE_1_OLD).to_event()],
+                action_our_events: vec![section_rpc(
+                    OUR_SECTION,
+                    Authority::Section(OTHER_SECTION_1),
+                    Rpc::ExpectCandidate(CANDIDATE_1_OLD),
+                )],
             },
         );
     }
@@ -416,14 +697,106 @@ mod dst_tests {
         );
 
         run_test(
-            "Discard invalid CandidateInfo",
+            "Discard CandidateInfo with a forged signature",
             &initial_state,
             &[Rpc::CandidateInfo(CandidateInfo {
-                old_public_id: CANDIDATE_1_OLD,
-                new_public_id: CANDIDATE_1,
-                destination: OUR_NAME,
-                waiting_candidate_name: TARGET_INTERVAL_1,
-                valid: false,
+                signature_using_old: Signature(0, 0),
+                ..CANDIDATE_INFO_VALID_1
+            })
+            .to_event()],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_candidate_info_signed_by_new_key_only() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+            ],
+        );
+
+        // A node that does not hold the old identity's key cannot claim its relocation.
+        run_test(
+            "Discard CandidateInfo not signed with the old identity's key",
+            &initial_state,
+            &[Rpc::CandidateInfo(CandidateInfo::new(
+                CANDIDATE_1_OLD,
+                CANDIDATE_1,
+                OUR_NAME,
+                TARGET_INTERVAL_1,
+                SIGNED_RELOCATED_INFO_1.source_proof,
+                SECRET_KEY_1,
+                SECRET_KEY_1,
+            ))
+            .to_event()],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_candidate_info_signed_by_third_party() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+            ],
+        );
+        // CANDIDATE_2's node holds its own keys only, and tries to join in CANDIDATE_1's place.
+        let old_public_id_with_our_key = Candidate(Attributes {
+            key: SECRET_KEY_2_OLD.public_key(),
+            ..ATTRIBUTES_1_OLD
+        });
+
+        run_test(
+            "Discard CandidateInfo re-signed by a third party for another node's old identity, \
+             whether it keeps that identity's key or puts its own in.",
+            &initial_state,
+            &[
+                Rpc::CandidateInfo(CandidateInfo::new(
+                    CANDIDATE_1_OLD,
+                    CANDIDATE_2,
+                    OUR_NAME,
+                    TARGET_INTERVAL_1,
+                    SIGNED_RELOCATED_INFO_1.source_proof,
+                    SECRET_KEY_2_OLD,
+                    SECRET_KEY_2,
+                ))
+                .to_event(),
+                Rpc::CandidateInfo(CandidateInfo::new(
+                    old_public_id_with_our_key,
+                    CANDIDATE_2,
+                    OUR_NAME,
+                    TARGET_INTERVAL_1,
+                    SIGNED_RELOCATED_INFO_1.source_proof,
+                    SECRET_KEY_2_OLD,
+                    SECRET_KEY_2,
+                ))
+                .to_event(),
+            ],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_candidate_info_with_swapped_new_id() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+            ],
+        );
+
+        run_test(
+            "Discard CandidateInfo whose new identity is not the signed one",
+            &initial_state,
+            &[Rpc::CandidateInfo(CandidateInfo {
+                new_public_id: CANDIDATE_2,
+                ..CANDIDATE_INFO_VALID_1
             })
             .to_event()],
             &AssertState::default(),
@@ -437,7 +810,7 @@ mod dst_tests {
             &[
                 ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
                 ParsecVote::CheckResourceProof.to_event(),
-                CANDIDATE_INFO_VALID_RPC_1.to_event(),
+                ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
             ],
         );
 
@@ -464,12 +837,117 @@ mod dst_tests {
         run_test(
             "Discard CandidateInfo from candidate we are not or no longer expecting",
             &initial_state,
+            &[Rpc::CandidateInfo(CandidateInfo::new(
+                CANDIDATE_2,
+                CANDIDATE_2,
+                OUR_NAME,
+                TARGET_INTERVAL_1,
+                SIGNED_RELOCATED_INFO_1.source_proof,
+                SECRET_KEY_2,
+                SECRET_KEY_2,
+            ))
+            .to_event()],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_candidate_info_without_source_proof() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+            ],
+        );
+
+        run_test(
+            "Discard CandidateInfo with no proof the source section relocated the candidate",
+            &initial_state,
             &[Rpc::CandidateInfo(CandidateInfo {
-                old_public_id: CANDIDATE_2,
-                new_public_id: CANDIDATE_2,
-                destination: OUR_NAME,
-                waiting_candidate_name: TARGET_INTERVAL_1,
-                valid: true,
+                source_proof: None,
+                ..CANDIDATE_INFO_VALID_1
+            })
+            .to_event()],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_candidate_info_from_unknown_section() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+            ],
+        );
+
+        let source_proof = CANDIDATE_RELOCATED_INFO_1
+            .signed_by(OTHER_SECTION_INFO, &SRC_SECTION_300_ELDERS)
+            .source_proof;
+
+        run_test(
+            "Discard CandidateInfo with a source proof from a section we do not know",
+            &initial_state,
+            &[Rpc::CandidateInfo(CandidateInfo {
+                source_proof,
+                ..CANDIDATE_INFO_VALID_1
+            })
+            .to_event()],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_candidate_info_signed_by_minority() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+            ],
+        );
+
+        let source_proof = CANDIDATE_RELOCATED_INFO_1
+            .signed_by(SRC_SECTION_INFO_300, &SRC_SECTION_300_ELDERS[..1])
+            .source_proof;
+
+        run_test(
+            "Discard CandidateInfo with a source proof not signed by the source section elders",
+            &initial_state,
+            &[Rpc::CandidateInfo(CandidateInfo {
+                source_proof,
+                ..CANDIDATE_INFO_VALID_1
+            })
+            .to_event()],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_candidate_info_with_proof_of_other_relocation() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+            ],
+        );
+
+        let source_proof = RelocatedInfo {
+            target_interval_centre: TARGET_INTERVAL_2,
+            ..CANDIDATE_RELOCATED_INFO_1
+        }
+        .signed_by(SRC_SECTION_INFO_300, &SRC_SECTION_300_ELDERS)
+        .source_proof;
+
+        run_test(
+            "Discard CandidateInfo with a source proof for a relocation we did not agree to",
+            &initial_state,
+            &[Rpc::CandidateInfo(CandidateInfo {
+                source_proof,
+                ..CANDIDATE_INFO_VALID_1
             })
             .to_event()],
             &AssertState::default(),
@@ -483,7 +961,7 @@ mod dst_tests {
             &[
                 ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
                 ParsecVote::CheckResourceProof.to_event(),
-                CANDIDATE_INFO_VALID_RPC_1.to_event(),
+                ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
             ],
         );
 
@@ -493,13 +971,14 @@ mod dst_tests {
             &[Rpc::ResourceProofResponse {
                 candidate: CANDIDATE_1,
                 destination: OUR_NAME,
-                proof: Proof::ValidPart,
+                proof: *CANDIDATE_1_PROOF_PART,
             }
             .to_event()],
             &AssertState {
                 action_our_events: vec![Rpc::ResourceProofReceipt {
                     candidate: CANDIDATE_1,
                     source: OUR_NAME,
+                    index: 0,
                 }
                 .to_event()],
             },
@@ -513,7 +992,13 @@ mod dst_tests {
             &[
                 ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
                 ParsecVote::CheckResourceProof.to_event(),
-                CANDIDATE_INFO_VALID_RPC_1.to_event(),
+                ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
+                Rpc::ResourceProofResponse {
+                    candidate: CANDIDATE_1,
+                    destination: OUR_NAME,
+                    proof: *CANDIDATE_1_PROOF_PART,
+                }
+                .to_event(),
             ],
         );
 
@@ -523,15 +1008,16 @@ mod dst_tests {
             &[Rpc::ResourceProofResponse {
                 candidate: CANDIDATE_1,
                 destination: OUR_NAME,
-                proof: Proof::ValidEnd,
+                proof: *CANDIDATE_1_PROOF_END,
             }
             .to_event()],
             &AssertState {
                 action_our_events: vec![
-                    ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1).to_event(),
+                    ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, OUR_NAME).to_event(),
                     Rpc::ResourceProofReceipt {
                         candidate: CANDIDATE_1,
                         source: OUR_NAME,
+                        index: 1,
                     }
                     .to_event(),
                 ],
@@ -546,26 +1032,43 @@ mod dst_tests {
             &[
                 ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
                 ParsecVote::CheckResourceProof.to_event(),
-                CANDIDATE_INFO_VALID_RPC_1.to_event(),
+                ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
+                Rpc::ResourceProofResponse {
+                    candidate: CANDIDATE_1,
+                    destination: OUR_NAME,
+                    proof: *CANDIDATE_1_PROOF_PART,
+                }
+                .to_event(),
                 Rpc::ResourceProofResponse {
                     candidate: CANDIDATE_1,
                     destination: OUR_NAME,
-                    proof: Proof::ValidEnd,
+                    proof: *CANDIDATE_1_PROOF_END,
                 }
                 .to_event(),
             ],
         );
 
         run_test(
-            "Discard further ResourceProofResponse once voted online.",
+            "End resent once voted online: reject it but respond with receipt again, as it may have \
+             been lost.",
             &initial_state,
             &[Rpc::ResourceProofResponse {
                 candidate: CANDIDATE_1,
                 destination: OUR_NAME,
-                proof: Proof::ValidEnd,
+                proof: *CANDIDATE_1_PROOF_END,
             }
             .to_event()],
-            &AssertState::default(),
+            &AssertState {
+                action_our_events: vec![
+                    ActionTriggered::ReplayedMessageRejected.to_event(),
+                    Rpc::ResourceProofReceipt {
+                        candidate: CANDIDATE_1,
+                        source: OUR_NAME,
+                        index: 1,
+                    }
+                    .to_event(),
+                ],
+            },
         );
     }
 
@@ -576,17 +1079,20 @@ mod dst_tests {
             &[
                 ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
                 ParsecVote::CheckResourceProof.to_event(),
-                CANDIDATE_INFO_VALID_RPC_1.to_event(),
+                ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
             ],
         );
 
         run_test(
-            "Discard invalid proofs.",
+            "Discard proofs with a wrong value.",
             &initial_state,
             &[Rpc::ResourceProofResponse {
                 candidate: CANDIDATE_1,
                 destination: OUR_NAME,
-                proof: Proof::Invalid,
+                proof: Proof {
+                    value: CANDIDATE_1_PROOF_PART.value + 1,
+                    ..*CANDIDATE_1_PROOF_PART
+                },
             }
             .to_event()],
             &AssertState::default(),
@@ -594,23 +1100,23 @@ mod dst_tests {
     }
 
     #[test]
-    fn parsec_expect_candidate_then_candidate_info_then_end_proof_wrong_candidate() {
+    fn parsec_expect_candidate_then_candidate_info_then_truncated_proof() {
         let initial_state = arrange_initial_state(
             &initial_state_old_elders(),
             &[
                 ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
                 ParsecVote::CheckResourceProof.to_event(),
-                CANDIDATE_INFO_VALID_RPC_1.to_event(),
+                ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
             ],
         );
 
         run_test(
-            "Discard final proof from a candidate that is not the current one.",
+            "Discard the end of the proof when earlier parts are missing.",
             &initial_state,
             &[Rpc::ResourceProofResponse {
-                candidate: CANDIDATE_2,
+                candidate: CANDIDATE_1,
                 destination: OUR_NAME,
-                proof: Proof::ValidEnd,
+                proof: *CANDIDATE_1_PROOF_END,
             }
             .to_event()],
             &AssertState::default(),
@@ -618,42 +1124,350 @@ mod dst_tests {
     }
 
     #[test]
-    fn parsec_expect_candidate_then_purge_and_online_for_wrong_candidate() {
+    fn parsec_expect_candidate_then_candidate_info_then_replayed_part_proof() {
         let initial_state = arrange_initial_state(
-            &initial_state_young_elders(),
+            &initial_state_old_elders(),
             &[
                 ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
                 ParsecVote::CheckResourceProof.to_event(),
-                CANDIDATE_INFO_VALID_RPC_1.to_event(),
+                ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
             ],
         );
 
-        let description = "Ignore parsec votes not for the current candidate. \
-             The previous running resource proof for CANDIDATE_2 may have been cancelled, \
-             or we would only see either of the votes later on.";
         run_test(
-            description,
+            "Replayed part: reject it but respond with receipt again, as it may have been lost.",
             &initial_state,
             &[
-                ParsecVote::Online(CANDIDATE_2_OLD, CANDIDATE_2).to_event(),
-                ParsecVote::PurgeCandidate(CANDIDATE_2_OLD).to_event(),
+                Rpc::ResourceProofResponse {
+                    candidate: CANDIDATE_1,
+                    destination: OUR_NAME,
+                    proof: *CANDIDATE_1_PROOF_PART,
+                }
+                .to_event(),
+                Rpc::ResourceProofResponse {
+                    candidate: CANDIDATE_1,
+                    destination: OUR_NAME,
+                    proof: *CANDIDATE_1_PROOF_PART,
+                }
+                .to_event(),
             ],
-            &AssertState::default(),
-        );
-    }
-
-    #[test]
-    fn rpc_merge() {
-        run_test(
-            "",
-            &initial_state_old_elders(),
-            &[Rpc::Merge(OTHER_SECTION_INFO).to_event()],
+            &AssertState {
+                action_our_events: vec![
+                    Rpc::ResourceProofReceipt {
+                        candidate: CANDIDATE_1,
+                        source: OUR_NAME,
+                        index: 0,
+                    }
+                    .to_event(),
+                    ActionTriggered::ReplayedMessageRejected.to_event(),
+                    Rpc::ResourceProofReceipt {
+                        candidate: CANDIDATE_1,
+                        source: OUR_NAME,
+                        index: 0,
+                    }
+                    .to_event(),
+                ],
+            },
+        );
+    }
+
+    // CANDIDATE_1 purged, then relocated to us again: its second resource proof session.
+    fn initial_state_second_session() -> (MemberState, ProofRequest) {
+        let relocated_info = RelocatedInfo {
+            target_interval_centre: TARGET_INTERVAL_2,
+            ..CANDIDATE_RELOCATED_INFO_1
+        }
+        .signed_by(SRC_SECTION_INFO_300, &SRC_SECTION_300_ELDERS);
+        let info = CandidateInfo::new(
+            CANDIDATE_1_OLD,
+            CANDIDATE_1,
+            TARGET_INTERVAL_2,
+            TARGET_INTERVAL_2,
+            relocated_info.source_proof,
+            SECRET_KEY_1_OLD,
+            SECRET_KEY_1,
+        );
+
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+                ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
+                ParsecVote::PurgeCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+                ParsecVote::CandidateConnected(info).to_event(),
+            ],
+        );
+        let proof_request =
+            CountingScheme.request(OUR_NAME, CANDIDATE_1.name(), 1, SectionLoad::default());
+
+        (initial_state, proof_request)
+    }
+
+    #[test]
+    fn parsec_second_session_then_proof_from_earlier_session() {
+        let (initial_state, proof_request) = initial_state_second_session();
+
+        run_test(
+            "Reject a proof part replayed from an earlier session, accept the current one.",
+            &initial_state,
+            &[
+                Rpc::ResourceProofResponse {
+                    candidate: CANDIDATE_1,
+                    destination: OUR_NAME,
+                    proof: *CANDIDATE_1_PROOF_PART,
+                }
+                .to_event(),
+                Rpc::ResourceProofResponse {
+                    candidate: CANDIDATE_1,
+                    destination: OUR_NAME,
+                    proof: proof_request.chunk(0),
+                }
+                .to_event(),
+            ],
+            &AssertState {
+                action_our_events: vec![
+                    ActionTriggered::ReplayedMessageRejected.to_event(),
+                    Rpc::ResourceProofReceipt {
+                        candidate: CANDIDATE_1,
+                        source: OUR_NAME,
+                        index: 0,
+                    }
+                    .to_event(),
+                ],
+            },
+        );
+    }
+
+    #[test]
+    fn parsec_second_session_then_candidate_info_from_earlier_relocation() {
+        let (initial_state, _) = initial_state_second_session();
+
+        run_test(
+            "Reject a CandidateInfo replayed from an earlier relocation: keep the current session.",
+            &initial_state,
+            &[ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event()],
+            &AssertState {
+                action_our_events: vec![ActionTriggered::ReplayedMessageRejected.to_event()],
+            },
+        );
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_candidate_info_with_rewritten_waiting_name() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+            ],
+        );
+
+        run_test(
+            "A CandidateInfo signed for another relocation cannot be rewritten for this one: \
+             its signatures cover the waiting candidate name.",
+            &initial_state,
+            &[ParsecVote::CandidateConnected(CandidateInfo {
+                waiting_candidate_name: TARGET_INTERVAL_1,
+                destination: TARGET_INTERVAL_1,
+                ..CandidateInfo::new(
+                    CANDIDATE_1_OLD,
+                    CANDIDATE_1,
+                    TARGET_INTERVAL_2,
+                    TARGET_INTERVAL_2,
+                    SIGNED_RELOCATED_INFO_1.source_proof,
+                    SECRET_KEY_1_OLD,
+                    SECRET_KEY_1,
+                )
+            })
+            .to_event()],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_candidate_info_then_proof_for_other_elder() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+                ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
+            ],
+        );
+
+        run_test(
+            "Discard a proof computed for another elder's challenge.",
+            &initial_state,
+            &[Rpc::ResourceProofResponse {
+                candidate: CANDIDATE_1,
+                destination: OUR_NAME,
+                proof: CountingScheme
+                    .request(NAME_111, CANDIDATE_1.name(), 0, SectionLoad::default())
+                    .chunk(0),
+            }
+            .to_event()],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_candidate_info_then_end_proof_wrong_candidate() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+                ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
+            ],
+        );
+
+        run_test(
+            "Discard final proof from a candidate that is not the current one.",
+            &initial_state,
+            &[Rpc::ResourceProofResponse {
+                candidate: CANDIDATE_2,
+                destination: OUR_NAME,
+                proof: *CANDIDATE_1_PROOF_END,
+            }
+            .to_event()],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_purge_and_online_for_wrong_candidate() {
+        let initial_state = arrange_initial_state(
+            &initial_state_young_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+                ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
+            ],
+        );
+
+        let description = "Ignore parsec votes not for the current candidate. \
+             The previous running resource proof for CANDIDATE_2 may have been cancelled, \
+             or we would only see either of the votes later on.";
+        run_test(
+            description,
+            &initial_state,
+            &[
+                ParsecVote::Online(CANDIDATE_2_OLD, CANDIDATE_2, NAME_130).to_event(),
+                ParsecVote::PurgeCandidate(CANDIDATE_2_OLD).to_event(),
+            ],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn rpc_merge() {
+        run_test(
+            "",
+            &initial_state_old_elders_with_neighbour(),
+            &[section_rpc(
+                OTHER_SECTION_1,
+                Authority::Neighbours(OTHER_SECTION_1),
+                Rpc::Merge(OTHER_SECTION_INFO),
+            )],
             &AssertState {
                 action_our_events: vec![ParsecVote::NeighbourMerge(OTHER_SECTION_INFO).to_event()],
             },
         );
     }
 
+    #[test]
+    fn rpc_merge_untrusted_section_info() {
+        run_test(
+            "Merge for a neighbour version we do not trust: reject.",
+            &initial_state_old_elders_with_neighbour(),
+            &[section_rpc(
+                OTHER_SECTION_1,
+                Authority::Neighbours(OTHER_SECTION_1),
+                Rpc::Merge(OTHER_SECTION_INFO_NEW_ELDERS),
+            )],
+            &AssertState {
+                action_our_events: vec![ActionTriggered::UnexpectedAuthorityRejected.to_event()],
+            },
+        );
+    }
+
+    #[test]
+    fn rpc_merge_from_other_section() {
+        run_test(
+            "Merge for a section not sent by that section: reject.",
+            &initial_state_old_elders(),
+            &[section_rpc(
+                OTHER_SECTION_2,
+                Authority::Neighbours(OTHER_SECTION_1),
+                Rpc::Merge(OTHER_SECTION_INFO),
+            )],
+            &AssertState {
+                action_our_events: vec![ActionTriggered::UnexpectedAuthorityRejected.to_event()],
+            },
+        );
+    }
+
+    #[test]
+    fn rpc_neighbour_update() {
+        run_test(
+            "NeighbourUpdate signed by the neighbour elders we trust: trust the new version.",
+            &initial_state_old_elders_with_neighbour(),
+            &[neighbour_update(
+                OTHER_SECTION_INFO,
+                OTHER_SECTION_INFO_NEW_ELDERS,
+            )],
+            &AssertState {
+                action_our_events: vec![ActionTriggered::NeighbourUpdated(
+                    OTHER_SECTION_INFO_NEW_ELDERS,
+                )
+                .to_event()],
+            },
+        );
+    }
+
+    #[test]
+    fn rpc_neighbour_update_forged() {
+        let forged_proof = SectionProof::new(
+            OTHER_SECTION_INFO,
+            OTHER_SECTION_INFO_NEW_ELDERS.2.nodes(),
+            &OTHER_SECTION_INFO_NEW_ELDERS.signed_values(),
+        );
+
+        run_test(
+            "NeighbourUpdate signed by the new elders instead of the ones we trust: reject.",
+            &initial_state_old_elders_with_neighbour(),
+            &[section_rpc(
+                OTHER_SECTION_1,
+                Authority::Neighbours(OTHER_SECTION_1),
+                Rpc::NeighbourUpdate {
+                    info: OTHER_SECTION_INFO_NEW_ELDERS,
+                    proof: forged_proof,
+                },
+            )],
+            &AssertState {
+                action_our_events: vec![ActionTriggered::UnexpectedAuthorityRejected.to_event()],
+            },
+        );
+    }
+
+    #[test]
+    fn rpc_neighbour_update_from_unknown_version() {
+        run_test(
+            "NeighbourUpdate signed by a neighbour version we do not trust: reject.",
+            &initial_state_old_elders(),
+            &[neighbour_update(
+                OTHER_SECTION_INFO,
+                OTHER_SECTION_INFO_NEW_ELDERS,
+            )],
+            &AssertState {
+                action_our_events: vec![ActionTriggered::UnexpectedAuthorityRejected.to_event()],
+            },
+        );
+    }
+
     #[test]
     fn parsec_neighbour_merge() {
         run_test(
@@ -681,7 +1495,11 @@ mod dst_tests {
             &[ParsecVote::CheckElder.to_event()],
             &AssertState {
                 action_our_events: vec![
-                    Rpc::Merge(SectionInfo(OUR_SECTION, 0)).to_event(),
+                    section_rpc(
+                        OUR_SECTION,
+                        Authority::Neighbours(OUR_SECTION),
+                        Rpc::Merge(SectionInfo(OUR_SECTION, 0, NO_ELDERS)),
+                    ),
                     ParsecVote::NewSectionInfo(MERGED_SECTION_INFO).to_event(),
                 ],
             },
@@ -700,7 +1518,11 @@ mod dst_tests {
                 ParsecVote::CheckElder.to_event(),
             ],
             &AssertState {
-                action_our_events: vec![Rpc::Merge(SectionInfo(OUR_SECTION, 0)).to_event()],
+                action_our_events: vec![section_rpc(
+                    OUR_SECTION,
+                    Authority::Neighbours(OUR_SECTION),
+                    Rpc::Merge(SectionInfo(OUR_SECTION, 0, NO_ELDERS)),
+                )],
             },
         );
     }
@@ -831,13 +1653,19 @@ mod dst_tests {
             description,
             &initial_state,
             &[
-                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_130).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_131).to_event(),
                 ParsecVote::CheckElder.to_event(),
             ],
             &AssertState {
                 action_our_events: vec![
                     SET_ONLINE_NODE_1.to_event(),
-                    Rpc::NodeApproval(CANDIDATE_1, OUR_GENESIS_INFO).to_event(),
+                    Rpc::NodeApproval {
+                        candidate: CANDIDATE_1,
+                        source: OUR_NAME,
+                        info: OUR_GENESIS_INFO,
+                    }
+                    .to_event(),
                     ActionTriggered::Scheduled(LocalEvent::CheckResourceProofTimeout).to_event(),
                     ActionTriggered::Scheduled(LocalEvent::TimeoutCheckElder).to_event(),
                 ],
@@ -846,65 +1674,182 @@ mod dst_tests {
     }
 
     #[test]
-    fn parsec_expect_candidate_then_online_elder_change() {
-        let initial_state = arrange_initial_state(
-            &initial_state_young_elders(),
+    fn temporal_candidates_answered_and_approved_once_online() {
+        let trace = event_trace(
+            &initial_state_old_elders(),
             &[
                 ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::ExpectCandidate(CANDIDATE_2_OLD).to_event(),
                 ParsecVote::CheckResourceProof.to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_130).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_131).to_event(),
+                ParsecVote::CheckElder.to_event(),
             ],
         );
 
+        let approval = event("NodeApproval", |event| {
+            matches!(
+                event,
+                Event::Rpc(Message {
+                    rpc: Rpc::NodeApproval { .. },
+                    ..
+                })
+            )
+        });
+        let online = event("Online", |event| {
+            *event == ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_131).to_event()
+        });
+
+        unwrap!(temporal::expect_candidate_answered().check(&trace));
+        unwrap!(temporal::no_approval_before_online().check(&trace));
+        unwrap!(until(approval.not(), online).check(&trace));
+    }
+
+    #[test]
+    fn temporal_forwarded_candidate_not_answered_by_us() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[TestEvent::SetShortestPrefix(Some(OTHER_SECTION_1)).to_event()],
+        );
+        let expect_candidate = ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event();
+        let trace = event_trace(&initial_state, &[expect_candidate]);
+
+        let property = temporal::expect_candidate_answered();
+        let expected = Err(format!(
+            "'{}' does not hold: fails at event 0 of 2: {:?}",
+            property, expect_candidate
+        ));
+        assert!(expected == property.check(&trace));
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_online_after_section_changed() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                vec![
+                    ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                    ParsecVote::CheckResourceProof.to_event(),
+                    ParsecVote::Offline(NODE_ELDER_130).to_event(),
+                    ParsecVote::CheckElder.to_event(),
+                    ParsecVote::AddElderNode(YOUNG_ADULT_205).to_event(),
+                    ParsecVote::RemoveElderNode(NODE_ELDER_130).to_event(),
+                ],
+                dkg_contributions(SECTION_INFO_1_OLD_ELDERS),
+                vec![ParsecVote::NewSectionInfo(SECTION_INFO_1_OLD_ELDERS).to_event()],
+            ]
+            .concat(),
+        );
+        let chain = &initial_state.action.inner().section_proof_chain;
+        assert!(chain.verify());
+        assert!(
+            chain
+                .trimmed_to(OUR_INITIAL_SECTION_INFO)
+                .map(|chain| chain.first())
+                == Some(OUR_INITIAL_SECTION_INFO)
+        );
+
         let description =
-            "Accept a new node (Elder Change): send RPC and schedule next ResourceProof. \
-             CheckElder start updating the section when triggered.";
+            "Accept a new node relocated to an older version of our section: send it the \
+             section updates it needs to trust the NodeApproval.";
         run_test(
             description,
             &initial_state,
             &[
-                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1).to_event(),
-                ParsecVote::CheckElder.to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_131).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_132).to_event(),
             ],
             &AssertState {
                 action_our_events: vec![
                     SET_ONLINE_NODE_1.to_event(),
-                    Rpc::NodeApproval(CANDIDATE_1, OUR_GENESIS_INFO).to_event(),
+                    section_rpc(
+                        OUR_SECTION,
+                        Authority::Node(CANDIDATE_1.name()),
+                        Rpc::SectionUpdate {
+                            destination: CANDIDATE_1.name(),
+                            info: SECTION_INFO_1_OLD_ELDERS,
+                            proof: OUR_INITIAL_SECTION_INFO
+                                .sign_successor(SECTION_INFO_1_OLD_ELDERS),
+                        },
+                    ),
+                    Rpc::NodeApproval {
+                        candidate: CANDIDATE_1,
+                        source: OUR_NAME,
+                        info: GenesisPfxInfo(SECTION_INFO_1_OLD_ELDERS),
+                    }
+                    .to_event(),
                     ActionTriggered::Scheduled(LocalEvent::CheckResourceProofTimeout).to_event(),
-                    ParsecVote::AddElderNode(NODE_1).to_event(),
-                    ParsecVote::RemoveElderNode(NODE_ELDER_109).to_event(),
-                    ParsecVote::NewSectionInfo(SECTION_INFO_1).to_event(),
                 ],
             },
         );
     }
 
     #[test]
-    fn parsec_expect_candidate_then_online_elder_change_get_wrong_votes() {
+    fn parsec_expect_candidate_then_online_elder_change() {
         let initial_state = arrange_initial_state(
             &initial_state_young_elders(),
             &[
                 ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
                 ParsecVote::CheckResourceProof.to_event(),
-                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1).to_event(),
-                ParsecVote::CheckElder.to_event(),
             ],
         );
 
-        let description = "Accept a new node (Elder Change) - Check Elder Triggered. \
-                           Error if consensus unexpected votes.";
+        let description =
+            "Accept a new node (Elder Change): send RPC and schedule next ResourceProof. \
+             CheckElder start updating the section when triggered.";
         run_test(
             description,
             &initial_state,
             &[
-                ParsecVote::RemoveElderNode(NODE_1).to_event(),
-                ParsecVote::AddElderNode(NODE_ELDER_109).to_event(),
-                ParsecVote::NewSectionInfo(SECTION_INFO_2).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_109).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_110).to_event(),
+                ParsecVote::CheckElder.to_event(),
             ],
             &AssertState {
                 action_our_events: vec![
-                    ActionTriggered::UnexpectedEventErrorTriggered.to_event(),
-                    ActionTriggered::UnexpectedEventErrorTriggered.to_event(),
-                    ActionTriggered::UnexpectedEventErrorTriggered.to_event(),
+                    SET_ONLINE_NODE_1.to_event(),
+                    Rpc::NodeApproval {
+                        candidate: CANDIDATE_1,
+                        source: OUR_NAME,
+                        info: OUR_GENESIS_INFO,
+                    }
+                    .to_event(),
+                    ActionTriggered::Scheduled(LocalEvent::CheckResourceProofTimeout).to_event(),
+                    ParsecVote::AddElderNode(NODE_1).to_event(),
+                    ParsecVote::RemoveElderNode(NODE_ELDER_109).to_event(),
+                ],
+            },
+        );
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_online_elder_change_get_wrong_votes() {
+        let initial_state = arrange_initial_state(
+            &initial_state_young_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_109).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_110).to_event(),
+                ParsecVote::CheckElder.to_event(),
+            ],
+        );
+
+        let description = "Accept a new node (Elder Change) - Check Elder Triggered. \
+                           Error if consensus unexpected votes.";
+        run_test(
+            description,
+            &initial_state,
+            &[
+                ParsecVote::RemoveElderNode(NODE_1).to_event(),
+                ParsecVote::AddElderNode(NODE_ELDER_109).to_event(),
+                ParsecVote::NewSectionInfo(SECTION_INFO_2).to_event(),
+            ],
+            &AssertState {
+                action_our_events: vec![
+                    ActionTriggered::UnexpectedEventErrorTriggered.to_event(),
+                    ActionTriggered::UnexpectedEventErrorTriggered.to_event(),
+                    ActionTriggered::UnexpectedEventErrorTriggered.to_event(),
                 ],
             },
         );
@@ -917,7 +1862,8 @@ mod dst_tests {
             &[
                 ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
                 ParsecVote::CheckResourceProof.to_event(),
-                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_109).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_110).to_event(),
                 ParsecVote::CheckElder.to_event(),
             ],
         );
@@ -939,7 +1885,8 @@ mod dst_tests {
             &[
                 ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
                 ParsecVote::CheckResourceProof.to_event(),
-                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_109).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_110).to_event(),
                 ParsecVote::CheckElder.to_event(),
                 ParsecVote::RemoveElderNode(NODE_ELDER_109).to_event(),
             ],
@@ -947,21 +1894,30 @@ mod dst_tests {
 
         let description =
             "Accept a new node (Elder Change) - Check Elder Triggered and votes completed. \
-             Once completed, update our section and elders";
+             Generate the new elders key, vote the new section once done, and once \
+             consensused update our section and elders";
         run_test(
             description,
             &initial_state,
             &[
-                ParsecVote::AddElderNode(NODE_1).to_event(),
-                ParsecVote::NewSectionInfo(SECTION_INFO_1).to_event(),
-            ],
+                vec![ParsecVote::AddElderNode(NODE_1).to_event()],
+                dkg_contributions(SECTION_INFO_1),
+                vec![ParsecVote::NewSectionInfo(SECTION_INFO_1).to_event()],
+            ]
+            .concat(),
             &AssertState {
-                action_our_events: vec![
-                    NodeChange::Elder(NODE_1, true).to_event(),
-                    NodeChange::Elder(NODE_ELDER_109, false).to_event(),
-                    ActionTriggered::OurSectionChanged(SECTION_INFO_1).to_event(),
-                    ActionTriggered::Scheduled(LocalEvent::TimeoutCheckElder).to_event(),
-                ],
+                action_our_events: [
+                    our_dkg_start(SECTION_INFO_1),
+                    vec![
+                        ParsecVote::NewSectionInfo(SECTION_INFO_1).to_event(),
+                        NodeChange::Elder(NODE_1, true).to_event(),
+                        NodeChange::Elder(NODE_ELDER_109, false).to_event(),
+                        ActionTriggered::OurSectionChanged(SECTION_INFO_1).to_event(),
+                        neighbour_update(OUR_INITIAL_SECTION_INFO, SECTION_INFO_1),
+                        ActionTriggered::Scheduled(LocalEvent::TimeoutCheckElder).to_event(),
+                    ],
+                ]
+                .concat(),
             },
         );
     }
@@ -973,7 +1929,8 @@ mod dst_tests {
             &[
                 ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
                 ParsecVote::CheckResourceProof.to_event(),
-                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_109).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_110).to_event(),
                 ParsecVote::CheckElder.to_event(),
             ],
         );
@@ -994,22 +1951,28 @@ mod dst_tests {
                         Node(Attributes {
                             name: TARGET_INTERVAL_2,
                             age: CANDIDATE_2.0.age,
+                            key: NO_KEY,
                         }),
                         State::WaitingCandidateInfo(RelocatedInfo {
                             candidate: CANDIDATE_2_OLD,
                             expected_age: CANDIDATE_2.0.age,
                             target_interval_centre: TARGET_INTERVAL_2,
                             section_info: OUR_INITIAL_SECTION_INFO,
+                            source_proof: None,
                         }),
                     )
                     .to_event(),
-                    Rpc::RelocateResponse(RelocatedInfo {
-                        candidate: CANDIDATE_2_OLD,
-                        expected_age: CANDIDATE_2.0.age,
-                        target_interval_centre: TARGET_INTERVAL_2,
-                        section_info: OUR_INITIAL_SECTION_INFO,
-                    })
-                    .to_event(),
+                    section_rpc(
+                        OUR_SECTION,
+                        Authority::SectionOf(CANDIDATE_2_OLD.name()),
+                        Rpc::RelocateResponse(RelocatedInfo {
+                            candidate: CANDIDATE_2_OLD,
+                            expected_age: CANDIDATE_2.0.age,
+                            target_interval_centre: TARGET_INTERVAL_2,
+                            section_info: OUR_INITIAL_SECTION_INFO,
+                            source_proof: None,
+                        }),
+                    ),
                     ActionTriggered::Scheduled(LocalEvent::TimeoutAccept).to_event(),
                 ],
             },
@@ -1039,6 +2002,113 @@ mod dst_tests {
         );
     }
 
+    #[test]
+    fn parsec_expect_candidate_then_purge_then_time_out() {
+        let initial_state = arrange_initial_state(
+            &initial_state_young_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+                LocalEvent::TimeoutAccept.to_event(),
+                ParsecVote::PurgeCandidate(CANDIDATE_1_OLD).to_event(),
+            ],
+        );
+
+        run_test(
+            "Timeout for a candidate already purged: Discard it",
+            &initial_state,
+            &[LocalEvent::TimeoutAccept.to_event()],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_online_below_quorum() {
+        let initial_state = arrange_initial_state(
+            &initial_state_young_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+            ],
+        );
+
+        run_test(
+            "Only one elder judged the proof valid: the candidate is not online yet.",
+            &initial_state,
+            &[ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_109).to_event()],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_online_twice_from_same_elder() {
+        let initial_state = arrange_initial_state(
+            &initial_state_young_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+            ],
+        );
+
+        run_test(
+            "The same elder voting twice does not make a quorum.",
+            &initial_state,
+            &[
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_109).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_109).to_event(),
+            ],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_online_from_adult() {
+        let initial_state = arrange_initial_state(
+            &initial_state_young_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+            ],
+        );
+
+        run_test(
+            "Only elders judge the proof: the adult vote does not count.",
+            &initial_state,
+            &[
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_109).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, YOUNG_ADULT_205.0.name).to_event(),
+            ],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn parsec_expect_candidate_then_purge_before_online_quorum() {
+        let initial_state = arrange_initial_state(
+            &initial_state_young_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+            ],
+        );
+
+        run_test(
+            "PurgeCandidate before a quorum of Online: Remove and ignore later Online votes.",
+            &initial_state,
+            &[
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_109).to_event(),
+                ParsecVote::PurgeCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_110).to_event(),
+            ],
+            &AssertState {
+                action_our_events: vec![
+                    REMOVE_CANDIDATE_1.to_event(),
+                    ActionTriggered::Scheduled(LocalEvent::CheckResourceProofTimeout).to_event(),
+                ],
+            },
+        );
+    }
+
     #[test]
     fn parsec_expect_candidate_then_candidate_info_then_purge() {
         let initial_state = arrange_initial_state(
@@ -1046,7 +2116,7 @@ mod dst_tests {
             &[
                 ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
                 ParsecVote::CheckResourceProof.to_event(),
-                CANDIDATE_INFO_VALID_RPC_1.to_event(),
+                ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
             ],
         );
 
@@ -1070,7 +2140,7 @@ mod dst_tests {
             &[
                 ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
                 ParsecVote::CheckResourceProof.to_event(),
-                CANDIDATE_INFO_VALID_RPC_1.to_event(),
+                ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
             ],
         );
 
@@ -1079,7 +2149,11 @@ mod dst_tests {
             &initial_state,
             &[ParsecVote::ExpectCandidate(CANDIDATE_2_OLD).to_event()],
             &AssertState {
-                action_our_events: vec![Rpc::RefuseCandidate(CANDIDATE_2_OLD).to_event()],
+                action_our_events: vec![section_rpc(
+                    OUR_SECTION,
+                    Authority::SectionOf(CANDIDATE_2_OLD.name()),
+                    Rpc::RefuseCandidate(CANDIDATE_2_OLD),
+                )],
             },
         );
     }
@@ -1092,7 +2166,7 @@ mod dst_tests {
             description,
             &initial_state_old_elders(),
             &[
-                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1).to_event(),
+                ParsecVote::Online(CANDIDATE_1_OLD, CANDIDATE_1, NAME_130).to_event(),
                 ParsecVote::PurgeCandidate(CANDIDATE_1_OLD).to_event(),
             ],
             &AssertState::default(),
@@ -1107,11 +2181,11 @@ mod dst_tests {
             description,
             &initial_state_old_elders(),
             &[
-                CANDIDATE_INFO_VALID_RPC_1.to_event(),
+                ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
                 Rpc::ResourceProofResponse {
                     candidate: CANDIDATE_1,
                     destination: OUR_NAME,
-                    proof: Proof::ValidEnd,
+                    proof: *CANDIDATE_1_PROOF_END,
                 }
                 .to_event(),
             ],
@@ -1165,524 +2239,1089 @@ mod dst_tests {
                 action_our_events: vec![
                     ParsecVote::AddElderNode(YOUNG_ADULT_205).to_event(),
                     ParsecVote::RemoveElderNode(NODE_ELDER_130).to_event(),
-                    ParsecVote::NewSectionInfo(SECTION_INFO_1).to_event(),
                 ],
             },
         );
     }
 
     #[test]
-    fn parsec_offline_then_parsec_online() {
+    fn parsec_elder_change_dkg_waits_for_all_contributions() {
         let initial_state = arrange_initial_state(
             &initial_state_old_elders(),
-            &[ParsecVote::Offline(NODE_ELDER_130).to_event()],
+            &[
+                ParsecVote::Offline(NODE_ELDER_130).to_event(),
+                ParsecVote::CheckElder.to_event(),
+            ],
         );
         run_test(
-            "Relocate nodes coming back online.",
+            "Once the elder changes are consensused, generate the new elders key: only vote the \
+             new section once every new elder contributed.",
             &initial_state,
-            &[ParsecVote::BackOnline(NODE_ELDER_130).to_event()],
+            &[
+                ParsecVote::AddElderNode(YOUNG_ADULT_205).to_event(),
+                ParsecVote::RemoveElderNode(NODE_ELDER_130).to_event(),
+                Rpc::DkgContribution {
+                    source: NAME_131,
+                    destination: OUR_NAME,
+                    info: SECTION_INFO_1_OLD_ELDERS,
+                }
+                .to_event(),
+            ],
             &AssertState {
-                action_our_events: vec![NodeChange::State(
-                    NODE_ELDER_130,
-                    State::RelocatingBackOnline,
-                )
-                .to_event()],
+                action_our_events: our_dkg_start(SECTION_INFO_1_OLD_ELDERS),
             },
         );
     }
-}
-
-//////////////////
-/// Src
-//////////////////
-
-mod src_tests {
-    use super::*;
-    use itertools::Itertools;
 
     #[test]
-    fn local_event_time_out_work_unit() {
-        run_test(
-            "Work unit timer and vote together to keep span consistent.",
+    fn parsec_elder_change_dkg_contributions_before_changes_consensused() {
+        let initial_state = arrange_initial_state(
             &initial_state_old_elders(),
-            &[LocalEvent::TimeoutWorkUnit.to_event()],
+            &[
+                ParsecVote::Offline(NODE_ELDER_130).to_event(),
+                ParsecVote::CheckElder.to_event(),
+            ],
+        );
+        run_test(
+            "Keep contributions from new elders that were faster than us: the new section is \
+             voted as soon as we contributed.",
+            &initial_state,
+            &[
+                dkg_contributions(SECTION_INFO_1_OLD_ELDERS),
+                vec![
+                    ParsecVote::AddElderNode(YOUNG_ADULT_205).to_event(),
+                    ParsecVote::RemoveElderNode(NODE_ELDER_130).to_event(),
+                ],
+            ]
+            .concat(),
             &AssertState {
                 action_our_events: vec![
-                    ParsecVote::WorkUnitIncrement.to_event(),
-                    ActionTriggered::Scheduled(LocalEvent::TimeoutWorkUnit).to_event(),
+                    Rpc::DkgContribution {
+                        source: OUR_NAME,
+                        destination: NAME_131,
+                        info: SECTION_INFO_1_OLD_ELDERS,
+                    }
+                    .to_event(),
+                    Rpc::DkgContribution {
+                        source: OUR_NAME,
+                        destination: YOUNG_ADULT_205.name(),
+                        info: SECTION_INFO_1_OLD_ELDERS,
+                    }
+                    .to_event(),
+                    ParsecVote::NewSectionInfo(SECTION_INFO_1_OLD_ELDERS).to_event(),
                 ],
             },
         );
     }
 
     #[test]
-    fn start_relocation() {
-        run_test(
-            "When work unit is sufficient, CheckRelocate initiate relocating non elder node.",
+    fn parsec_elder_change_dkg_contributions_before_elder_change_started() {
+        let initial_state = arrange_initial_state(
             &initial_state_old_elders(),
+            &[ParsecVote::Offline(NODE_ELDER_130).to_event()],
+        );
+        run_test(
+            "Keep contributions from new elders that started the elder change before us: the new \
+             section is voted as soon as we contributed.",
+            &initial_state,
             &[
-                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
-                ParsecVote::WorkUnitIncrement.to_event(),
-                ParsecVote::CheckRelocate.to_event(),
-            ],
+                dkg_contributions(SECTION_INFO_1_OLD_ELDERS),
+                vec![
+                    ParsecVote::CheckElder.to_event(),
+                    ParsecVote::AddElderNode(YOUNG_ADULT_205).to_event(),
+                    ParsecVote::RemoveElderNode(NODE_ELDER_130).to_event(),
+                ],
+            ]
+            .concat(),
             &AssertState {
                 action_our_events: vec![
-                    ActionTriggered::WorkUnitIncremented.to_event(),
-                    NodeChange::State(YOUNG_ADULT_205, State::RelocatingAgeIncrease).to_event(),
-                    Rpc::ExpectCandidate(CANDIDATE_205).to_event(),
+                    ParsecVote::AddElderNode(YOUNG_ADULT_205).to_event(),
+                    ParsecVote::RemoveElderNode(NODE_ELDER_130).to_event(),
+                    Rpc::DkgContribution {
+                        source: OUR_NAME,
+                        destination: NAME_131,
+                        info: SECTION_INFO_1_OLD_ELDERS,
+                    }
+                    .to_event(),
+                    Rpc::DkgContribution {
+                        source: OUR_NAME,
+                        destination: YOUNG_ADULT_205.name(),
+                        info: SECTION_INFO_1_OLD_ELDERS,
+                    }
+                    .to_event(),
+                    ParsecVote::NewSectionInfo(SECTION_INFO_1_OLD_ELDERS).to_event(),
                 ],
             },
         );
     }
 
     #[test]
-    fn parsec_check_work_unit_increment_has_no_effect_if_relocating_node() {
+    fn parsec_elder_change_dkg_participant_offline() {
         let initial_state = arrange_initial_state(
             &initial_state_old_elders(),
             &[
-                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
-                ParsecVote::WorkUnitIncrement.to_event(),
-                TestEvent::SetWorkUnitEnoughToRelocate(NODE_ELDER_130).to_event(),
+                ParsecVote::Offline(NODE_ELDER_130).to_event(),
+                ParsecVote::CheckElder.to_event(),
+                ParsecVote::AddElderNode(YOUNG_ADULT_205).to_event(),
+                ParsecVote::RemoveElderNode(NODE_ELDER_130).to_event(),
+                Rpc::DkgContribution {
+                    source: NAME_131,
+                    destination: OUR_NAME,
+                    info: SECTION_INFO_1_OLD_ELDERS,
+                }
+                .to_event(),
             ],
         );
-
         run_test(
-            "Additional WorkUnitIncrement does not trigger a new relocate if one started",
+            "A new elder going offline before contributing fails the key generation: vote to \
+             abandon the elder change, and once consensused ignore its late contribution and \
+             check elders again later.",
             &initial_state,
-            &[ParsecVote::WorkUnitIncrement.to_event()],
+            &[
+                LocalEvent::NodeDetectedOffline(YOUNG_ADULT_205).to_event(),
+                ParsecVote::Offline(YOUNG_ADULT_205).to_event(),
+                LocalEvent::TimeoutDkg(SECTION_INFO_1_OLD_ELDERS).to_event(),
+                ParsecVote::DkgFailed(SECTION_INFO_1_OLD_ELDERS).to_event(),
+                Rpc::DkgContribution {
+                    source: YOUNG_ADULT_205.name(),
+                    destination: OUR_NAME,
+                    info: SECTION_INFO_1_OLD_ELDERS,
+                }
+                .to_event(),
+                ParsecVote::CheckElder.to_event(),
+            ],
             &AssertState {
-                action_our_events: vec![ActionTriggered::WorkUnitIncremented.to_event()],
+                action_our_events: vec![
+                    ParsecVote::Offline(YOUNG_ADULT_205).to_event(),
+                    NodeChange::State(YOUNG_ADULT_205, State::Offline).to_event(),
+                    ParsecVote::DkgFailed(SECTION_INFO_1_OLD_ELDERS).to_event(),
+                    ActionTriggered::DkgFailed(SECTION_INFO_1_OLD_ELDERS).to_event(),
+                    ActionTriggered::Scheduled(LocalEvent::TimeoutCheckElder).to_event(),
+                    ActionTriggered::Scheduled(LocalEvent::TimeoutCheckElder).to_event(),
+                ],
             },
         );
     }
 
     #[test]
-    fn parsec_check_get_node_to_relocate_uses_online_nodes_only() {
-        let mut all_non_online_states = vec![
-            State::RelocatingAgeIncrease,
-            State::RelocatingHop,
-            State::RelocatingBackOnline,
-            State::Relocated(get_relocated_info(CANDIDATE_1_OLD, SECTION_INFO_1)),
-            State::WaitingCandidateInfo(get_relocated_info(CANDIDATE_2_OLD, SECTION_INFO_2)),
-            State::WaitingProofing,
-            State::Offline,
-        ];
-
-        let age = CANDIDATE_1_OLD.0.age;
-        let states_with_enough_work_units = all_non_online_states
-            .drain(..)
-            .enumerate()
-            .map(|(name_increment, state)| {
-                let node = Node(Attributes {
-                    name: Name(1000 + name_increment as i32),
-                    age,
-                });
-                NodeState {
-                    node,
-                    is_elder: false,
-                    work_units_done: age.0,
-                    state,
-                }
-            })
-            .collect_vec();
-        let inner_action = INNER_ACTION_YOUNG_ELDERS
-            .clone()
-            .extend_current_nodes(&states_with_enough_work_units);
-
-        let initial_state = MemberState {
-            action: Action::new(inner_action),
-            ..Default::default()
+    fn parsec_elder_change_as_outgoing_elder() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                ParsecVote::Offline(NODE_ELDER_130).to_event(),
+                ParsecVote::CheckElder.to_event(),
+            ],
+        );
+        let outgoing_elder_state = MemberState {
+            action: Action::new(InnerAction {
+                our_attributes: NODE_ELDER_130.0,
+                ..initial_state.action.inner()
+            }),
+            ..initial_state
         };
 
         run_test(
-            "Check get_node_to_relocate doesn't choose anything but 'Online' nodes",
-            &initial_state,
-            &[ParsecVote::WorkUnitIncrement.to_event()],
+            "An elder leaving the elders takes no part in the key generation: never time it out \
+             nor vote it failed, but still apply the consensused new section.",
+            &outgoing_elder_state,
+            &[
+                ParsecVote::AddElderNode(YOUNG_ADULT_205).to_event(),
+                ParsecVote::RemoveElderNode(NODE_ELDER_130).to_event(),
+                LocalEvent::TimeoutDkg(SECTION_INFO_1_OLD_ELDERS).to_event(),
+                ParsecVote::NewSectionInfo(SECTION_INFO_1_OLD_ELDERS).to_event(),
+            ],
             &AssertState {
-                action_our_events: vec![ActionTriggered::WorkUnitIncremented.to_event()],
+                action_our_events: vec![
+                    NodeChange::Elder(YOUNG_ADULT_205, true).to_event(),
+                    NodeChange::Elder(NODE_ELDER_130, false).to_event(),
+                    ActionTriggered::OurSectionChanged(SECTION_INFO_1_OLD_ELDERS).to_event(),
+                    neighbour_update(OUR_INITIAL_SECTION_INFO, SECTION_INFO_1_OLD_ELDERS),
+                    ActionTriggered::Scheduled(LocalEvent::TimeoutCheckElder).to_event(),
+                ],
             },
         );
     }
 
     #[test]
-    fn parsec_check_relocate_trigger_again_no_retry() {
+    fn parsec_offline_then_parsec_online() {
         let initial_state = arrange_initial_state(
             &initial_state_old_elders(),
-            &[
-                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
-                ParsecVote::WorkUnitIncrement.to_event(),
-                ParsecVote::CheckRelocate.to_event(),
-            ],
+            &[ParsecVote::Offline(NODE_ELDER_130).to_event()],
         );
-
         run_test(
-            "Additional CheckRelocate do not trigger a resend",
+            "Relocate nodes coming back online.",
             &initial_state,
-            &[
-                ParsecVote::CheckRelocate.to_event(),
-                ParsecVote::CheckRelocate.to_event(),
-            ],
-            &AssertState::default(),
+            &[ParsecVote::BackOnline(NODE_ELDER_130).to_event()],
+            &AssertState {
+                action_our_events: vec![NodeChange::State(
+                    NODE_ELDER_130,
+                    State::RelocatingBackOnline,
+                )
+                .to_event()],
+            },
         );
     }
 
     #[test]
-    fn parsec_relocation_trigger_again_until_retry() {
+    fn rpc_candidate_info_not_connected() {
         let initial_state = arrange_initial_state(
             &initial_state_old_elders(),
             &[
-                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
-                ParsecVote::WorkUnitIncrement.to_event(),
-                ParsecVote::CheckRelocate.to_event(),
-                ParsecVote::CheckRelocate.to_event(),
-                ParsecVote::CheckRelocate.to_event(),
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
             ],
         );
 
         run_test(
-            "Enough additional CheckRelocate trigger a resend",
+            "Get CandidateInfo from a candidate not connected: Connect to it.",
             &initial_state,
-            &[ParsecVote::CheckRelocate.to_event()],
+            &[CANDIDATE_INFO_VALID_RPC_1.to_event()],
             &AssertState {
-                action_our_events: vec![Rpc::ExpectCandidate(CANDIDATE_205).to_event()],
+                action_our_events: vec![Rpc::ConnectionInfoRequest {
+                    source: OUR_NAME,
+                    destination: CANDIDATE_1.name(),
+                    connection_info: OUR_NAME.0,
+                }
+                .to_event()],
             },
         );
     }
 
     #[test]
-    fn parsec_check_relocate_trigger_again_with_relocating_hop_and_back_online() {
-        let initial_state = MemberState {
-            action: Action::new(
-                INNER_ACTION_OLD_ELDERS
-                    .clone()
-                    .extend_current_nodes_with(
-                        &NodeState {
-                            state: State::RelocatingHop,
-                            ..NodeState::default()
-                        },
-                        &[NODE_1_OLD],
-                    )
-                    .extend_current_nodes_with(
-                        &NodeState {
-                            state: State::RelocatingBackOnline,
-                            ..NodeState::default()
-                        },
-                        &[NODE_2, NODE_2_OLD, NODE_1],
-                    ),
-            ),
-            ..MemberState::default()
-        };
+    fn rpc_candidate_info_from_other_node() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+            ],
+        );
 
-        let description = "RelocatingHop or RelocatingBackOnline does not stop relocating our \
-        adults. Also relocated nodes are relocated AgeIncrease, then Hop, then BackOnline, break \
-        tie by age then name";
         run_test(
-            description,
+            "CandidateInfo not sent by the candidate it names: reject.",
             &initial_state,
-            &[
-                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
-                ParsecVote::WorkUnitIncrement.to_event(),
-                ParsecVote::CheckRelocate.to_event(),
-                ParsecVote::CheckRelocate.to_event(),
-                ParsecVote::CheckRelocate.to_event(),
-                ParsecVote::CheckRelocate.to_event(),
-            ],
+            &[CANDIDATE_INFO_VALID_RPC_1
+                .to_message(Authority::Node(NAME_110), Authority::Node(OUR_NAME))
+                .to_event()],
             &AssertState {
-                action_our_events: vec![
-                    ActionTriggered::WorkUnitIncremented.to_event(),
-                    NodeChange::State(YOUNG_ADULT_205, State::RelocatingAgeIncrease).to_event(),
-                    Rpc::ExpectCandidate(CANDIDATE_205).to_event(),
-                    Rpc::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
-                    Rpc::ExpectCandidate(CANDIDATE_2).to_event(),
-                    Rpc::ExpectCandidate(CANDIDATE_205).to_event(),
-                ],
+                action_our_events: vec![ActionTriggered::UnexpectedAuthorityRejected.to_event()],
             },
         );
     }
 
     #[test]
-    fn parsec_relocate_trigger_elder_change() {
-        run_test(
-            "Work unit trigger relocation (Elder Change): Update Elders and relocate only after.",
+    fn rpc_candidate_info_then_connection_info_response() {
+        let initial_state = arrange_initial_state(
             &initial_state_old_elders(),
             &[
-                TestEvent::SetWorkUnitEnoughToRelocate(NODE_ELDER_130).to_event(),
-                ParsecVote::WorkUnitIncrement.to_event(),
-                ParsecVote::CheckRelocate.to_event(),
-                ParsecVote::CheckElder.to_event(),
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+                CANDIDATE_INFO_VALID_RPC_1.to_event(),
             ],
+        );
+
+        run_test(
+            "Candidate connected after its CandidateInfo: Vote for it connected.",
+            &initial_state,
+            &[Rpc::ConnectionInfoResponse {
+                source: CANDIDATE_1.name(),
+                destination: OUR_NAME,
+                connection_info: CANDIDATE_1.name().0,
+            }
+            .to_event()],
             &AssertState {
                 action_our_events: vec![
-                    ActionTriggered::WorkUnitIncremented.to_event(),
-                    NodeChange::State(NODE_ELDER_130, State::RelocatingAgeIncrease).to_event(),
-                    ParsecVote::AddElderNode(YOUNG_ADULT_205).to_event(),
-                    ParsecVote::RemoveElderNode(NODE_ELDER_130).to_event(),
-                    ParsecVote::NewSectionInfo(SECTION_INFO_1).to_event(),
+                    ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event()
                 ],
             },
         );
     }
 
     #[test]
-    fn parsec_relocate_trigger_elder_change_complete() {
+    fn rpc_connection_info_request_then_candidate_info_twice() {
         let initial_state = arrange_initial_state(
             &initial_state_old_elders(),
             &[
-                TestEvent::SetWorkUnitEnoughToRelocate(NODE_ELDER_130).to_event(),
-                ParsecVote::WorkUnitIncrement.to_event(),
-                ParsecVote::CheckElder.to_event(),
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
             ],
         );
 
         run_test(
-            "Work unit trigger relocation (Elder Change): Update Elders and relocate only after.",
+            "Candidate connects then sends CandidateInfo: Vote for it connected only once.",
             &initial_state,
             &[
-                ParsecVote::RemoveElderNode(NODE_ELDER_130).to_event(),
-                ParsecVote::AddElderNode(YOUNG_ADULT_205).to_event(),
-                ParsecVote::NewSectionInfo(SECTION_INFO_1).to_event(),
-                ParsecVote::CheckRelocate.to_event(),
+                Rpc::ConnectionInfoRequest {
+                    source: CANDIDATE_1.name(),
+                    destination: OUR_NAME,
+                    connection_info: CANDIDATE_1.name().0,
+                }
+                .to_event(),
+                CANDIDATE_INFO_VALID_RPC_1.to_event(),
+                CANDIDATE_INFO_VALID_RPC_1.to_event(),
             ],
             &AssertState {
                 action_our_events: vec![
-                    NodeChange::Elder(YOUNG_ADULT_205, true).to_event(),
-                    NodeChange::Elder(NODE_ELDER_130, false).to_event(),
-                    ActionTriggered::OurSectionChanged(SECTION_INFO_1).to_event(),
-                    ActionTriggered::Scheduled(LocalEvent::TimeoutCheckElder).to_event(),
-                    Rpc::ExpectCandidate(CANDIDATE_130).to_event(),
+                    Rpc::ConnectionInfoResponse {
+                        source: OUR_NAME,
+                        destination: CANDIDATE_1.name(),
+                        connection_info: OUR_NAME.0,
+                    }
+                    .to_event(),
+                    ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event(),
                 ],
             },
         );
     }
 
     #[test]
-    fn parsec_relocation_trigger_refuse_candidate_rpc() {
-        let initial_state = arrange_initial_state(
+    fn rpc_unexpected_connection_info_response() {
+        run_test(
+            "Discard ConnectionInfoResponse from a node that is not a candidate",
             &initial_state_old_elders(),
-            &[
-                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
-                ParsecVote::WorkUnitIncrement.to_event(),
-                ParsecVote::CheckRelocate.to_event(),
-            ],
+            &[Rpc::ConnectionInfoResponse {
+                source: CANDIDATE_1.name(),
+                destination: OUR_NAME,
+                connection_info: CANDIDATE_1.name().0,
+            }
+            .to_event()],
+            &AssertState::default(),
         );
+    }
 
+    #[test]
+    fn rpc_connection_info_request_from_non_candidate() {
         run_test(
-            "Vote for RPC to be processed",
-            &initial_state,
-            &[Rpc::RefuseCandidate(CANDIDATE_205).to_event()],
+            "Answer ConnectionInfoRequest from any node",
+            &initial_state_old_elders(),
+            &[Rpc::ConnectionInfoRequest {
+                source: NAME_111,
+                destination: OUR_NAME,
+                connection_info: NAME_111.0,
+            }
+            .to_event()],
             &AssertState {
-                action_our_events: vec![ParsecVote::RefuseCandidate(CANDIDATE_205).to_event()],
+                action_our_events: vec![Rpc::ConnectionInfoResponse {
+                    source: OUR_NAME,
+                    destination: NAME_111,
+                    connection_info: OUR_NAME.0,
+                }
+                .to_event()],
             },
         );
     }
 
     #[test]
-    fn parsec_relocation_trigger_relocate_response_rpc() {
+    fn rpc_candidate_info_after_candidate_disconnected() {
         let initial_state = arrange_initial_state(
             &initial_state_old_elders(),
             &[
-                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
-                ParsecVote::WorkUnitIncrement.to_event(),
-                ParsecVote::CheckRelocate.to_event(),
+                ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                ParsecVote::CheckResourceProof.to_event(),
+                Rpc::ConnectionInfoRequest {
+                    source: CANDIDATE_1.name(),
+                    destination: OUR_NAME,
+                    connection_info: CANDIDATE_1.name().0,
+                }
+                .to_event(),
+                CANDIDATE_INFO_VALID_RPC_1.to_event(),
             ],
         );
 
         run_test(
-            "Vote for RPC to be processed",
+            "Candidate disconnected: Reconnect on CandidateInfo without voting again.",
             &initial_state,
             &[
-                Rpc::RelocateResponse(get_relocated_info(CANDIDATE_205, DST_SECTION_INFO_200))
-                    .to_event(),
+                LocalEvent::NodeDisconnected(CANDIDATE_1.name()).to_event(),
+                CANDIDATE_INFO_VALID_RPC_1.to_event(),
             ],
             &AssertState {
-                action_our_events: vec![ParsecVote::RelocateResponse(get_relocated_info(
-                    CANDIDATE_205,
-                    DST_SECTION_INFO_200,
-                ))
+                action_our_events: vec![Rpc::ConnectionInfoRequest {
+                    source: OUR_NAME,
+                    destination: CANDIDATE_1.name(),
+                    connection_info: OUR_NAME.0,
+                }
                 .to_event()],
             },
         );
     }
 
     #[test]
-    fn parsec_relocation_trigger_accept() {
+    fn rpc_candidate_info_after_elder_change() {
         let initial_state = arrange_initial_state(
             &initial_state_old_elders(),
             &[
-                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
-                ParsecVote::WorkUnitIncrement.to_event(),
-                ParsecVote::CheckRelocate.to_event(),
-            ],
+                vec![
+                    ParsecVote::ExpectCandidate(CANDIDATE_1_OLD).to_event(),
+                    ParsecVote::CheckResourceProof.to_event(),
+                    Rpc::ConnectionInfoRequest {
+                        source: CANDIDATE_1.name(),
+                        destination: OUR_NAME,
+                        connection_info: CANDIDATE_1.name().0,
+                    }
+                    .to_event(),
+                    CANDIDATE_INFO_VALID_RPC_1.to_event(),
+                    ParsecVote::Offline(NODE_ELDER_130).to_event(),
+                    ParsecVote::CheckElder.to_event(),
+                    ParsecVote::AddElderNode(YOUNG_ADULT_205).to_event(),
+                    ParsecVote::RemoveElderNode(NODE_ELDER_130).to_event(),
+                ],
+                dkg_contributions(SECTION_INFO_1_OLD_ELDERS),
+                vec![ParsecVote::NewSectionInfo(SECTION_INFO_1_OLD_ELDERS).to_event()],
+            ]
+            .concat(),
         );
 
-        let description = "When RelocateResponse, update node state and vote for RelocatedInfo. \
-                           When RelocatedInfo consensused send RPC and remove node,";
         run_test(
-            description,
+            "Elder change reset the relocated node connection: Vote again with new elders.",
             &initial_state,
-            &[
-                ParsecVote::RelocateResponse(get_relocated_info(
-                    CANDIDATE_205,
-                    DST_SECTION_INFO_200,
-                ))
-                .to_event(),
-                ParsecVote::RelocatedInfo(get_relocated_info(CANDIDATE_205, DST_SECTION_INFO_200))
-                    .to_event(),
-            ],
+            &[CANDIDATE_INFO_VALID_RPC_1.to_event()],
             &AssertState {
                 action_our_events: vec![
-                    NodeChange::State(
-                        YOUNG_ADULT_205,
-                        State::Relocated(get_relocated_info(CANDIDATE_205, DST_SECTION_INFO_200)),
-                    )
-                    .to_event(),
-                    ParsecVote::RelocatedInfo(get_relocated_info(
-                        CANDIDATE_205,
-                        DST_SECTION_INFO_200,
-                    ))
-                    .to_event(),
-                    Rpc::RelocatedInfo(get_relocated_info(CANDIDATE_205, DST_SECTION_INFO_200))
-                        .to_event(),
-                    NodeChange::Remove(YOUNG_ADULT_205.name()).to_event(),
+                    ParsecVote::CandidateConnected(CANDIDATE_INFO_VOTED_1).to_event()
                 ],
             },
         );
     }
+}
 
-    #[test]
-    fn parsec_relocation_trigger_refuse() {
-        let initial_state = arrange_initial_state(
-            &initial_state_old_elders(),
-            &[
-                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
+//////////////////
+/// Src
+//////////////////
+
+mod src_tests {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn local_event_time_out_work_unit() {
+        run_test(
+            "Work unit timer and vote together to keep span consistent.",
+            &initial_state_old_elders(),
+            &[LocalEvent::TimeoutWorkUnit.to_event()],
+            &AssertState {
+                action_our_events: vec![
+                    ParsecVote::WorkUnitIncrement.to_event(),
+                    ActionTriggered::Scheduled(LocalEvent::TimeoutWorkUnit).to_event(),
+                ],
+            },
+        );
+    }
+
+    #[test]
+    fn start_relocation() {
+        run_test(
+            "When work unit is sufficient, CheckRelocate initiate relocating non elder node.",
+            &initial_state_old_elders(),
+            &[
+                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
                 ParsecVote::WorkUnitIncrement.to_event(),
                 ParsecVote::CheckRelocate.to_event(),
             ],
+            &AssertState {
+                action_our_events: vec![
+                    ActionTriggered::WorkUnitIncremented.to_event(),
+                    NodeChange::State(YOUNG_ADULT_205, State::RelocatingAgeIncrease).to_event(),
+                    section_rpc(
+                        OUR_SECTION,
+                        Authority::Section(RELOCATION_TARGET),
+                        Rpc::ExpectCandidate(CANDIDATE_205),
+                    ),
+                ],
+            },
+        );
+    }
+
+    #[test]
+    fn parsec_check_work_unit_increment_has_no_effect_if_relocating_node() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
+                ParsecVote::WorkUnitIncrement.to_event(),
+                TestEvent::SetWorkUnitEnoughToRelocate(NODE_ELDER_130).to_event(),
+            ],
         );
 
         run_test(
-            "On refuse, only get ready to resend ExpectCandidate on next check.",
+            "Additional WorkUnitIncrement does not trigger a new relocate if one started",
             &initial_state,
-            &[ParsecVote::RefuseCandidate(CANDIDATE_205).to_event()],
-            &AssertState::default(),
+            &[ParsecVote::WorkUnitIncrement.to_event()],
+            &AssertState {
+                action_our_events: vec![ActionTriggered::WorkUnitIncremented.to_event()],
+            },
         );
     }
 
     #[test]
-    fn parsec_relocation_trigger_refuse_trigger_again() {
+    fn parsec_check_get_node_to_relocate_uses_online_nodes_only() {
+        let mut all_non_online_states = vec![
+            State::RelocatingAgeIncrease,
+            State::RelocatingHop,
+            State::RelocatingBackOnline,
+            State::Relocated(get_relocated_info(CANDIDATE_1_OLD, SECTION_INFO_1)),
+            State::WaitingCandidateInfo(get_relocated_info(CANDIDATE_2_OLD, SECTION_INFO_2)),
+            State::WaitingProofing,
+            State::Offline,
+        ];
+
+        let age = CANDIDATE_1_OLD.0.age;
+        let states_with_enough_work_units = all_non_online_states
+            .drain(..)
+            .enumerate()
+            .map(|(name_increment, state)| {
+                let node = Node(Attributes {
+                    name: Name(1000 + name_increment as i32),
+                    age,
+                    key: NO_KEY,
+                });
+                NodeState {
+                    node,
+                    is_elder: false,
+                    work_units_done: age.0,
+                    state,
+                }
+            })
+            .collect_vec();
+        let inner_action = INNER_ACTION_YOUNG_ELDERS
+            .clone()
+            .extend_current_nodes(&states_with_enough_work_units);
+
+        let initial_state = MemberState {
+            action: Action::new(inner_action),
+            ..Default::default()
+        };
+
+        run_test(
+            "Check get_node_to_relocate doesn't choose anything but 'Online' nodes",
+            &initial_state,
+            &[ParsecVote::WorkUnitIncrement.to_event()],
+            &AssertState {
+                action_our_events: vec![ActionTriggered::WorkUnitIncremented.to_event()],
+            },
+        );
+    }
+
+    #[test]
+    fn parsec_check_relocate_trigger_again_no_retry() {
         let initial_state = arrange_initial_state(
             &initial_state_old_elders(),
             &[
                 TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
                 ParsecVote::WorkUnitIncrement.to_event(),
                 ParsecVote::CheckRelocate.to_event(),
-                ParsecVote::RefuseCandidate(CANDIDATE_205).to_event(),
             ],
         );
 
         run_test(
-            "On next check after refuse, re-send ExpectCandidate.",
+            "Additional CheckRelocate do not trigger a resend",
             &initial_state,
-            &[ParsecVote::CheckRelocate.to_event()],
-            &AssertState {
-                action_our_events: vec![Rpc::ExpectCandidate(CANDIDATE_205).to_event()],
-            },
+            &[
+                ParsecVote::CheckRelocate.to_event(),
+                ParsecVote::CheckRelocate.to_event(),
+            ],
+            &AssertState::default(),
         );
     }
 
     #[test]
-    fn parsec_relocation_trigger_elder_change_refuse_trigger_again() {
+    fn parsec_relocation_trigger_again_until_retry() {
         let initial_state = arrange_initial_state(
             &initial_state_old_elders(),
             &[
-                TestEvent::SetWorkUnitEnoughToRelocate(NODE_ELDER_130).to_event(),
+                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
                 ParsecVote::WorkUnitIncrement.to_event(),
-                ParsecVote::CheckElder.to_event(),
-                ParsecVote::RemoveElderNode(NODE_ELDER_130).to_event(),
-                ParsecVote::AddElderNode(YOUNG_ADULT_205).to_event(),
-                ParsecVote::NewSectionInfo(SECTION_INFO_1).to_event(),
                 ParsecVote::CheckRelocate.to_event(),
-                ParsecVote::RefuseCandidate(CANDIDATE_130).to_event(),
+                ParsecVote::CheckRelocate.to_event(),
+                ParsecVote::CheckRelocate.to_event(),
             ],
         );
 
         run_test(
-            "On next check after refuse, re-send ExpectCandidate for a node that was elder.",
+            "Enough additional CheckRelocate trigger a resend",
             &initial_state,
             &[ParsecVote::CheckRelocate.to_event()],
             &AssertState {
-                action_our_events: vec![Rpc::ExpectCandidate(CANDIDATE_130).to_event()],
+                action_our_events: vec![section_rpc(
+                    OUR_SECTION,
+                    Authority::Section(RELOCATION_TARGET),
+                    Rpc::ExpectCandidate(CANDIDATE_205),
+                )],
             },
         );
     }
 
     #[test]
-    fn unexpected_refuse_or_accept_candidate() {
+    fn parsec_check_relocate_trigger_again_with_relocating_hop_and_back_online() {
+        let initial_state = MemberState {
+            action: Action::new(
+                INNER_ACTION_OLD_ELDERS
+                    .clone()
+                    .extend_current_nodes_with(
+                        &NodeState {
+                            state: State::RelocatingHop,
+                            ..NodeState::default()
+                        },
+                        &[NODE_1_OLD],
+                    )
+                    .extend_current_nodes_with(
+                        &NodeState {
+                            state: State::RelocatingBackOnline,
+                            ..NodeState::default()
+                        },
+                        &[NODE_2, NODE_2_OLD, NODE_1],
+                    ),
+            ),
+            ..MemberState::default()
+        };
+
+        let description = "RelocatingHop or RelocatingBackOnline does not stop relocating our \
+        adults. Also relocated nodes are relocated AgeIncrease, then Hop, then BackOnline, break \
+        tie by age then name";
         run_test(
-            "Vote for unexpected responses to ExpectCandidate as we may be lagging.",
-            &initial_state_old_elders(),
+            description,
+            &initial_state,
             &[
-                Rpc::RefuseCandidate(CANDIDATE_205).to_event(),
-                Rpc::RelocateResponse(get_relocated_info(CANDIDATE_205, DST_SECTION_INFO_200))
-                    .to_event(),
+                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
+                ParsecVote::WorkUnitIncrement.to_event(),
+                ParsecVote::CheckRelocate.to_event(),
+                ParsecVote::CheckRelocate.to_event(),
+                ParsecVote::CheckRelocate.to_event(),
+                ParsecVote::CheckRelocate.to_event(),
             ],
             &AssertState {
                 action_our_events: vec![
-                    ParsecVote::RefuseCandidate(CANDIDATE_205).to_event(),
-                    ParsecVote::RelocateResponse(get_relocated_info(
-                        CANDIDATE_205,
-                        DST_SECTION_INFO_200,
-                    ))
-                    .to_event(),
+                    ActionTriggered::WorkUnitIncremented.to_event(),
+                    NodeChange::State(YOUNG_ADULT_205, State::RelocatingAgeIncrease).to_event(),
+                    section_rpc(
+                        OUR_SECTION,
+                        Authority::Section(RELOCATION_TARGET),
+                        Rpc::ExpectCandidate(CANDIDATE_205),
+                    ),
+                    section_rpc(
+                        OUR_SECTION,
+                        Authority::Section(RELOCATION_TARGET),
+                        Rpc::ExpectCandidate(CANDIDATE_1_OLD),
+                    ),
+                    section_rpc(
+                        OUR_SECTION,
+                        Authority::Section(RELOCATION_TARGET),
+                        Rpc::ExpectCandidate(CANDIDATE_2),
+                    ),
+                    section_rpc(
+                        OUR_SECTION,
+                        Authority::Section(RELOCATION_TARGET),
+                        Rpc::ExpectCandidate(CANDIDATE_205),
+                    ),
                 ],
             },
         );
     }
-}
-
-mod node_tests {
-    use super::*;
-    use pretty_assertions::assert_eq;
-
-    #[derive(Debug, PartialEq, Default, Clone)]
-    struct AssertJoiningState {
-        action_our_events: Vec<Event>,
-        routine_complete_output: Option<GenesisPfxInfo>,
-    }
-
-    fn run_joining_test(
-        test_name: &str,
-        start_state: &JoiningState,
-        events: &[Event],
-        expected_state: &AssertJoiningState,
-    ) {
-        let final_state = process_joining_events(start_state.clone(), &events);
-        let action = final_state.action.inner();
 
-        let final_state = (
-            AssertJoiningState {
-                action_our_events: action.our_events,
-                routine_complete_output: final_state.join_routine.routine_complete_output,
+    #[test]
+    fn parsec_relocate_trigger_elder_change() {
+        run_test(
+            "Work unit trigger relocation (Elder Change): Update Elders and relocate only after.",
+            &initial_state_old_elders(),
+            &[
+                TestEvent::SetWorkUnitEnoughToRelocate(NODE_ELDER_130).to_event(),
+                ParsecVote::WorkUnitIncrement.to_event(),
+                ParsecVote::CheckRelocate.to_event(),
+                ParsecVote::CheckElder.to_event(),
+            ],
+            &AssertState {
+                action_our_events: vec![
+                    ActionTriggered::WorkUnitIncremented.to_event(),
+                    NodeChange::State(NODE_ELDER_130, State::RelocatingAgeIncrease).to_event(),
+                    ParsecVote::AddElderNode(YOUNG_ADULT_205).to_event(),
+                    ParsecVote::RemoveElderNode(NODE_ELDER_130).to_event(),
+                ],
             },
-            final_state.failure,
         );
-        let expected_state = (expected_state.clone(), None);
-
-        assert_eq!(expected_state, final_state, "{}", test_name);
     }
 
-    fn process_joining_events(mut state: JoiningState, events: &[Event]) -> JoiningState {
-        for event in events.iter().cloned() {
-            if TryResult::Unhandled == state.try_next(event) {
-                state.failure_event(event);
-            }
-
-            if state.failure.is_some() {
-                break;
-            }
-        }
+    #[test]
+    fn parsec_relocate_trigger_elder_change_complete() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                TestEvent::SetWorkUnitEnoughToRelocate(NODE_ELDER_130).to_event(),
+                ParsecVote::WorkUnitIncrement.to_event(),
+                ParsecVote::CheckElder.to_event(),
+            ],
+        );
 
-        state
+        run_test(
+            "Work unit trigger relocation (Elder Change): Update Elders and relocate only after.",
+            &initial_state,
+            &[
+                vec![
+                    ParsecVote::RemoveElderNode(NODE_ELDER_130).to_event(),
+                    ParsecVote::AddElderNode(YOUNG_ADULT_205).to_event(),
+                ],
+                dkg_contributions(SECTION_INFO_1_OLD_ELDERS),
+                vec![
+                    ParsecVote::NewSectionInfo(SECTION_INFO_1_OLD_ELDERS).to_event(),
+                    ParsecVote::CheckRelocate.to_event(),
+                ],
+            ]
+            .concat(),
+            &AssertState {
+                action_our_events: [
+                    our_dkg_start(SECTION_INFO_1_OLD_ELDERS),
+                    vec![
+                        ParsecVote::NewSectionInfo(SECTION_INFO_1_OLD_ELDERS).to_event(),
+                        NodeChange::Elder(YOUNG_ADULT_205, true).to_event(),
+                        NodeChange::Elder(NODE_ELDER_130, false).to_event(),
+                        ActionTriggered::OurSectionChanged(SECTION_INFO_1_OLD_ELDERS).to_event(),
+                        neighbour_update(OUR_INITIAL_SECTION_INFO, SECTION_INFO_1_OLD_ELDERS),
+                        ActionTriggered::Scheduled(LocalEvent::TimeoutCheckElder).to_event(),
+                        section_rpc(
+                            OUR_SECTION,
+                            Authority::Section(RELOCATION_TARGET),
+                            Rpc::ExpectCandidate(CANDIDATE_130),
+                        ),
+                    ],
+                ]
+                .concat(),
+            },
+        );
+    }
+
+    #[test]
+    fn parsec_relocation_trigger_refuse_candidate_rpc() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
+                ParsecVote::WorkUnitIncrement.to_event(),
+                ParsecVote::CheckRelocate.to_event(),
+            ],
+        );
+
+        run_test(
+            "Vote for RPC to be processed",
+            &initial_state,
+            &[section_rpc(
+                RELOCATION_TARGET,
+                Authority::SectionOf(CANDIDATE_205.name()),
+                Rpc::RefuseCandidate(CANDIDATE_205),
+            )],
+            &AssertState {
+                action_our_events: vec![ParsecVote::RefuseCandidate(CANDIDATE_205).to_event()],
+            },
+        );
+    }
+
+    #[test]
+    fn parsec_relocation_trigger_relocate_response_rpc() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
+                ParsecVote::WorkUnitIncrement.to_event(),
+                ParsecVote::CheckRelocate.to_event(),
+            ],
+        );
+
+        run_test(
+            "Vote for RPC to be processed",
+            &initial_state,
+            &[section_rpc(
+                RELOCATION_TARGET,
+                Authority::SectionOf(CANDIDATE_205.name()),
+                Rpc::RelocateResponse(get_relocated_info(CANDIDATE_205, DST_SECTION_INFO_200)),
+            )],
+            &AssertState {
+                action_our_events: vec![ParsecVote::RelocateResponse(get_relocated_info(
+                    CANDIDATE_205,
+                    DST_SECTION_INFO_200,
+                ))
+                .to_event()],
+            },
+        );
+    }
+
+    #[test]
+    fn parsec_relocation_trigger_accept() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
+                ParsecVote::WorkUnitIncrement.to_event(),
+                ParsecVote::CheckRelocate.to_event(),
+            ],
+        );
+
+        let description = "When RelocateResponse, update node state and vote for RelocatedInfo. \
+                           When RelocatedInfo consensused send RPC and remove node,";
+        run_test(
+            description,
+            &initial_state,
+            &[
+                ParsecVote::RelocateResponse(get_relocated_info(
+                    CANDIDATE_205,
+                    DST_SECTION_INFO_200,
+                ))
+                .to_event(),
+                ParsecVote::RelocatedInfo(get_relocated_info(CANDIDATE_205, DST_SECTION_INFO_200))
+                    .to_event(),
+            ],
+            &AssertState {
+                action_our_events: vec![
+                    NodeChange::State(
+                        YOUNG_ADULT_205,
+                        State::Relocated(get_relocated_info(CANDIDATE_205, DST_SECTION_INFO_200)),
+                    )
+                    .to_event(),
+                    ParsecVote::RelocatedInfo(get_relocated_info(
+                        CANDIDATE_205,
+                        DST_SECTION_INFO_200,
+                    ))
+                    .to_event(),
+                    section_rpc(
+                        OUR_SECTION,
+                        Authority::Node(CANDIDATE_205.name()),
+                        Rpc::RelocatedInfo(
+                            get_relocated_info(CANDIDATE_205, DST_SECTION_INFO_200).signed_by(
+                                OUR_INITIAL_SECTION_INFO,
+                                &[NODE_ELDER_130, NODE_ELDER_131, NODE_ELDER_132],
+                            ),
+                        ),
+                    ),
+                    NodeChange::Remove(YOUNG_ADULT_205.name()).to_event(),
+                ],
+            },
+        );
+    }
+
+    #[test]
+    fn parsec_relocation_trigger_refuse() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
+                ParsecVote::WorkUnitIncrement.to_event(),
+                ParsecVote::CheckRelocate.to_event(),
+            ],
+        );
+
+        run_test(
+            "On refuse, only get ready to resend ExpectCandidate on next check.",
+            &initial_state,
+            &[ParsecVote::RefuseCandidate(CANDIDATE_205).to_event()],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn parsec_relocation_trigger_refuse_trigger_again() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
+                ParsecVote::WorkUnitIncrement.to_event(),
+                ParsecVote::CheckRelocate.to_event(),
+                ParsecVote::RefuseCandidate(CANDIDATE_205).to_event(),
+            ],
+        );
+
+        run_test(
+            "On next check after refuse, re-send ExpectCandidate.",
+            &initial_state,
+            &[ParsecVote::CheckRelocate.to_event()],
+            &AssertState {
+                action_our_events: vec![section_rpc(
+                    OUR_SECTION,
+                    Authority::Section(RELOCATION_TARGET),
+                    Rpc::ExpectCandidate(CANDIDATE_205),
+                )],
+            },
+        );
+    }
+
+    #[test]
+    fn parsec_refuse_candidate_not_yet_sent() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
+                ParsecVote::WorkUnitIncrement.to_event(),
+            ],
+        );
+
+        run_test(
+            "A refuse for an ExpectCandidate we did not send yet is stale: discard it.",
+            &initial_state,
+            &[ParsecVote::RefuseCandidate(CANDIDATE_205).to_event()],
+            &AssertState::default(),
+        );
+    }
+
+    #[test]
+    fn parsec_relocation_trigger_elder_change_refuse_trigger_again() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                vec![
+                    TestEvent::SetWorkUnitEnoughToRelocate(NODE_ELDER_130).to_event(),
+                    ParsecVote::WorkUnitIncrement.to_event(),
+                    ParsecVote::CheckElder.to_event(),
+                    ParsecVote::RemoveElderNode(NODE_ELDER_130).to_event(),
+                    ParsecVote::AddElderNode(YOUNG_ADULT_205).to_event(),
+                ],
+                dkg_contributions(SECTION_INFO_1_OLD_ELDERS),
+                vec![
+                    ParsecVote::NewSectionInfo(SECTION_INFO_1_OLD_ELDERS).to_event(),
+                    ParsecVote::CheckRelocate.to_event(),
+                    ParsecVote::RefuseCandidate(CANDIDATE_130).to_event(),
+                ],
+            ]
+            .concat(),
+        );
+
+        run_test(
+            "On next check after refuse, re-send ExpectCandidate for a node that was elder.",
+            &initial_state,
+            &[ParsecVote::CheckRelocate.to_event()],
+            &AssertState {
+                action_our_events: vec![section_rpc(
+                    OUR_SECTION,
+                    Authority::Section(RELOCATION_TARGET),
+                    Rpc::ExpectCandidate(CANDIDATE_130),
+                )],
+            },
+        );
+    }
+
+    #[test]
+    fn unexpected_refuse_or_accept_candidate() {
+        run_test(
+            "Vote for unexpected responses to ExpectCandidate as we may be lagging.",
+            &initial_state_old_elders(),
+            &[
+                section_rpc(
+                    DST_SECTION_200,
+                    Authority::SectionOf(CANDIDATE_205.name()),
+                    Rpc::RefuseCandidate(CANDIDATE_205),
+                ),
+                section_rpc(
+                    DST_SECTION_200,
+                    Authority::SectionOf(CANDIDATE_205.name()),
+                    Rpc::RelocateResponse(get_relocated_info(CANDIDATE_205, DST_SECTION_INFO_200)),
+                ),
+            ],
+            &AssertState {
+                action_our_events: vec![
+                    ParsecVote::RefuseCandidate(CANDIDATE_205).to_event(),
+                    ParsecVote::RelocateResponse(get_relocated_info(
+                        CANDIDATE_205,
+                        DST_SECTION_INFO_200,
+                    ))
+                    .to_event(),
+                ],
+            },
+        );
+    }
+
+    #[test]
+    fn parsec_relocation_trigger_responses_from_other_section() {
+        let initial_state = arrange_initial_state(
+            &initial_state_old_elders(),
+            &[
+                TestEvent::SetWorkUnitEnoughToRelocate(YOUNG_ADULT_205).to_event(),
+                ParsecVote::WorkUnitIncrement.to_event(),
+                ParsecVote::CheckRelocate.to_event(),
+            ],
+        );
+
+        run_test(
+            "Responses not from the section we sent ExpectCandidate to: reject.",
+            &initial_state,
+            &[
+                section_rpc(
+                    DST_SECTION_200,
+                    Authority::SectionOf(CANDIDATE_205.name()),
+                    Rpc::RefuseCandidate(CANDIDATE_205),
+                ),
+                section_rpc(
+                    DST_SECTION_200,
+                    Authority::SectionOf(CANDIDATE_205.name()),
+                    Rpc::RelocateResponse(get_relocated_info(CANDIDATE_205, DST_SECTION_INFO_200)),
+                ),
+            ],
+            &AssertState {
+                action_our_events: vec![
+                    ActionTriggered::UnexpectedAuthorityRejected.to_event(),
+                    ActionTriggered::UnexpectedAuthorityRejected.to_event(),
+                ],
+            },
+        );
+    }
+
+    #[test]
+    fn unexpected_refuse_candidate_from_a_node() {
+        run_test(
+            "Even when lagging, only a section may answer ExpectCandidate: reject.",
+            &initial_state_old_elders(),
+            &[Rpc::RefuseCandidate(CANDIDATE_205)
+                .to_message(
+                    Authority::Node(NAME_110),
+                    Authority::SectionOf(CANDIDATE_205.name()),
+                )
+                .to_event()],
+            &AssertState {
+                action_our_events: vec![ActionTriggered::UnexpectedAuthorityRejected.to_event()],
+            },
+        );
+    }
+}
+
+mod node_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[derive(Debug, PartialEq, Default, Clone)]
+    struct AssertJoiningState {
+        action_our_events: Vec<Event>,
+        routine_complete_output: Option<GenesisPfxInfo>,
+        routine_failure_output: Option<JoiningFailure>,
+    }
+
+    fn run_joining_test(
+        test_name: &str,
+        start_state: &JoiningState,
+        events: &[Event],
+        expected_state: &AssertJoiningState,
+    ) {
+        let final_state = process_joining_events(start_state.clone(), &events);
+        let action = final_state.action.inner();
+
+        let final_state = (
+            AssertJoiningState {
+                action_our_events: without_message_ids(action.our_events),
+                routine_complete_output: final_state.join_routine.routine_complete_output,
+                routine_failure_output: final_state.join_routine.routine_failure_output,
+            },
+            final_state.failure,
+        );
+        let expected_state = (expected_state.clone(), None);
+
+        assert_eq!(expected_state, final_state, "{}", test_name);
+    }
+
+    fn process_joining_events(mut state: JoiningState, events: &[Event]) -> JoiningState {
+        for event in events.iter().cloned() {
+            if TryResult::Unhandled == state.try_next(event) {
+                state.failure_event(event);
+            }
+
+            if state.failure.is_some() {
+                break;
+            }
+        }
+
+        state
     }
 
     fn arrange_initial_joining_state(state: &JoiningState, events: &[Event]) -> JoiningState {
@@ -1691,26 +3330,498 @@ mod node_tests {
         state
     }
 
-    fn initial_joining_state_with_dst_200() -> JoiningState {
-        JoiningState {
-            action: Action::new(INNER_ACTION_WITH_DST_SECTION_200.clone()),
-            ..Default::default()
-        }
-    }
+    fn initial_joining_state_with_dst_200() -> JoiningState {
+        JoiningState {
+            action: Action::new(INNER_ACTION_WITH_DST_SECTION_200.clone()),
+            ..Default::default()
+        }
+    }
+
+    //////////////////
+    /// Joining Relocate Node
+    //////////////////
+
+    #[test]
+    fn joining_start() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+
+        run_joining_test(
+            "",
+            &initial_state,
+            &[],
+            &AssertJoiningState {
+                action_our_events: vec![
+                    Rpc::ConnectionInfoRequest {
+                        source: OUR_NAME,
+                        destination: NAME_109,
+                        connection_info: OUR_NAME.0,
+                    }
+                    .to_event(),
+                    Rpc::ConnectionInfoRequest {
+                        source: OUR_NAME,
+                        destination: NAME_110,
+                        connection_info: OUR_NAME.0,
+                    }
+                    .to_event(),
+                    Rpc::ConnectionInfoRequest {
+                        source: OUR_NAME,
+                        destination: NAME_111,
+                        connection_info: OUR_NAME.0,
+                    }
+                    .to_event(),
+                    ActionTriggered::Scheduled(LocalEvent::JoiningTimeoutResendInfo).to_event(),
+                    ActionTriggered::Scheduled(LocalEvent::JoiningTimeoutProofRefused).to_event(),
+                ],
+                routine_complete_output: None,
+                routine_failure_output: None,
+            },
+        );
+    }
+
+    #[test]
+    fn joining_resend_timeout() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+
+        let initial_state = arrange_initial_joining_state(&initial_state, &[]);
+
+        run_joining_test(
+            "When not yet connected, resend CandidateInfo.",
+            &initial_state,
+            &[LocalEvent::JoiningTimeoutResendInfo.to_event()],
+            &AssertJoiningState {
+                action_our_events: vec![
+                    Rpc::ConnectionInfoRequest {
+                        source: OUR_NAME,
+                        destination: NAME_109,
+                        connection_info: OUR_NAME.0,
+                    }
+                    .to_event(),
+                    Rpc::ConnectionInfoRequest {
+                        source: OUR_NAME,
+                        destination: NAME_110,
+                        connection_info: OUR_NAME.0,
+                    }
+                    .to_event(),
+                    Rpc::ConnectionInfoRequest {
+                        source: OUR_NAME,
+                        destination: NAME_111,
+                        connection_info: OUR_NAME.0,
+                    }
+                    .to_event(),
+                    ActionTriggered::Scheduled(LocalEvent::JoiningTimeoutResendInfo).to_event(),
+                ],
+                routine_complete_output: None,
+                routine_failure_output: None,
+            },
+        );
+    }
+
+    #[test]
+    fn joining_connection_info_for_other_node() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+
+        let initial_state = arrange_initial_joining_state(&initial_state, &[]);
+
+        run_joining_test(
+            "ConnectionInfoResponse addressed to another node: reject.",
+            &initial_state,
+            &[Rpc::ConnectionInfoResponse {
+                source: NAME_110,
+                destination: NAME_109,
+                connection_info: NAME_110.0,
+            }
+            .to_event()],
+            &AssertJoiningState {
+                action_our_events: vec![ActionTriggered::UnexpectedAuthorityRejected.to_event()],
+                routine_complete_output: None,
+                routine_failure_output: None,
+            },
+        );
+    }
+
+    #[test]
+    fn joining_receive_two_connection_info() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+
+        let initial_state = arrange_initial_joining_state(&initial_state, &[]);
+
+        run_joining_test(
+            "",
+            &initial_state,
+            &[
+                Rpc::ConnectionInfoResponse {
+                    source: NAME_110,
+                    destination: OUR_NAME,
+                    connection_info: NAME_110.0,
+                }
+                .to_event(),
+                Rpc::ConnectionInfoResponse {
+                    source: NAME_111,
+                    destination: OUR_NAME,
+                    connection_info: NAME_111.0,
+                }
+                .to_event(),
+            ],
+            &AssertJoiningState {
+                action_our_events: vec![
+                    Rpc::CandidateInfo(CandidateInfo::new(
+                        OUR_NODE_CANDIDATE_OLD,
+                        OUR_NODE_CANDIDATE,
+                        NAME_110,
+                        TARGET_INTERVAL_1,
+                        CANDIDATE_RELOCATED_INFO_132.source_proof,
+                        SECRET_KEY_132_OLD,
+                        SECRET_KEY_132,
+                    ))
+                    .to_event(),
+                    Rpc::CandidateInfo(CandidateInfo::new(
+                        OUR_NODE_CANDIDATE_OLD,
+                        OUR_NODE_CANDIDATE,
+                        NAME_111,
+                        TARGET_INTERVAL_1,
+                        CANDIDATE_RELOCATED_INFO_132.source_proof,
+                        SECRET_KEY_132_OLD,
+                        SECRET_KEY_132,
+                    ))
+                    .to_event(),
+                ],
+                routine_complete_output: None,
+                routine_failure_output: None,
+            },
+        );
+    }
+
+    #[test]
+    fn joining_receive_two_resource_proof() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+        let initial_state = arrange_initial_joining_state(&initial_state, &[]);
+
+        run_joining_test(
+            "Start computing resource proof when receiving ResourceProof RPC and setup timers.",
+            &initial_state,
+            &[
+                Rpc::ResourceProof {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_111,
+                    proof: *PROOF_REQUEST_111,
+                }
+                .to_event(),
+                Rpc::ResourceProof {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_110,
+                    proof: *PROOF_REQUEST_110,
+                }
+                .to_event(),
+            ],
+            &AssertJoiningState {
+                action_our_events: vec![
+                    ActionTriggered::ComputeResourceProofForElder(NAME_111).to_event(),
+                    ActionTriggered::ComputeResourceProofForElder(NAME_110).to_event(),
+                ],
+                routine_complete_output: None,
+                routine_failure_output: None,
+            },
+        );
+    }
+
+    #[test]
+    fn joining_computed_two_proofs() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+        let initial_state = arrange_initial_joining_state(&initial_state, &[]);
+
+        run_joining_test(
+            "When proof computed, start sending response to correct Elder.",
+            &initial_state,
+            &[
+                TestEvent::SetResourceProof(NAME_111, ProofSource::new(*PROOF_REQUEST_111))
+                    .to_event(),
+                LocalEvent::ResourceProofForElderReady(NAME_111).to_event(),
+                TestEvent::SetResourceProof(NAME_110, ProofSource::new(*PROOF_REQUEST_110))
+                    .to_event(),
+                LocalEvent::ResourceProofForElderReady(NAME_110).to_event(),
+            ],
+            &AssertJoiningState {
+                action_our_events: vec![
+                    Rpc::ResourceProofResponse {
+                        candidate: OUR_NODE_CANDIDATE,
+                        destination: NAME_111,
+                        proof: PROOF_REQUEST_111.chunk(0),
+                    }
+                    .to_event(),
+                    Rpc::ResourceProofResponse {
+                        candidate: OUR_NODE_CANDIDATE,
+                        destination: NAME_110,
+                        proof: PROOF_REQUEST_110.chunk(0),
+                    }
+                    .to_event(),
+                ],
+                routine_complete_output: None,
+                routine_failure_output: None,
+            },
+        );
+    }
+
+    #[test]
+    fn joining_got_part_proof_receipt() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+
+        let initial_state = arrange_initial_joining_state(
+            &initial_state,
+            &[
+                Rpc::ResourceProof {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_111,
+                    proof: *PROOF_REQUEST_111,
+                }
+                .to_event(),
+                TestEvent::SetResourceProof(NAME_111, ProofSource::new(*PROOF_REQUEST_111))
+                    .to_event(),
+                LocalEvent::ResourceProofForElderReady(NAME_111).to_event(),
+            ],
+        );
+
+        run_joining_test(
+            "On receiving receipt, send the next part (end) of the proof to that Elder.",
+            &initial_state,
+            &[Rpc::ResourceProofReceipt {
+                candidate: OUR_NODE_CANDIDATE,
+                source: NAME_111,
+                index: 0,
+            }
+            .to_event()],
+            &AssertJoiningState {
+                action_our_events: vec![Rpc::ResourceProofResponse {
+                    candidate: OUR_NODE_CANDIDATE,
+                    destination: NAME_111,
+                    proof: PROOF_REQUEST_111.chunk(1),
+                }
+                .to_event()],
+                routine_complete_output: None,
+                routine_failure_output: None,
+            },
+        );
+    }
+
+    #[test]
+    fn joining_got_end_proof_receipt() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+
+        let initial_state = arrange_initial_joining_state(
+            &initial_state,
+            &[
+                Rpc::ResourceProof {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_111,
+                    proof: *PROOF_REQUEST_111,
+                }
+                .to_event(),
+                TestEvent::SetResourceProof(NAME_111, ProofSource::new(*PROOF_REQUEST_111))
+                    .to_event(),
+                LocalEvent::ResourceProofForElderReady(NAME_111).to_event(),
+                Rpc::ResourceProofReceipt {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_111,
+                    index: 0,
+                }
+                .to_event(),
+            ],
+        );
+
+        run_joining_test(
+            "On receiving receipt for end, do not send anymore.",
+            &initial_state,
+            &[Rpc::ResourceProofReceipt {
+                candidate: OUR_NODE_CANDIDATE,
+                source: NAME_111,
+                index: 1,
+            }
+            .to_event()],
+            &AssertJoiningState {
+                action_our_events: vec![],
+                routine_complete_output: None,
+                routine_failure_output: None,
+            },
+        );
+    }
+
+    #[test]
+    fn joining_resend_timeout_one_proof_completed_one_in_progress() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+
+        let initial_state = arrange_initial_joining_state(
+            &initial_state,
+            &[
+                Rpc::ConnectionInfoResponse {
+                    source: NAME_110,
+                    destination: OUR_NAME,
+                    connection_info: NAME_110.0,
+                }
+                .to_event(),
+                Rpc::ConnectionInfoResponse {
+                    source: NAME_111,
+                    destination: OUR_NAME,
+                    connection_info: NAME_111.0,
+                }
+                .to_event(),
+                TestEvent::SetResourceProof(NAME_111, ProofSource::new(*PROOF_REQUEST_111))
+                    .to_event(),
+                LocalEvent::ResourceProofForElderReady(NAME_111).to_event(),
+                TestEvent::SetResourceProof(NAME_110, ProofSource::new(*PROOF_REQUEST_110))
+                    .to_event(),
+                LocalEvent::ResourceProofForElderReady(NAME_110).to_event(),
+                Rpc::ResourceProofReceipt {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_111,
+                    index: 0,
+                }
+                .to_event(),
+                Rpc::ResourceProofReceipt {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_111,
+                    index: 1,
+                }
+                .to_event(),
+            ],
+        );
+
+        run_joining_test(
+            "When connected, resend connection info, and the part without receipt.",
+            &initial_state,
+            &[LocalEvent::JoiningTimeoutResendInfo.to_event()],
+            &AssertJoiningState {
+                action_our_events: vec![
+                    Rpc::ConnectionInfoRequest {
+                        source: OUR_NAME,
+                        destination: NAME_109,
+                        connection_info: OUR_NAME.0,
+                    }
+                    .to_event(),
+                    Rpc::ResourceProofResponse {
+                        candidate: OUR_NODE_CANDIDATE,
+                        destination: NAME_110,
+                        proof: PROOF_REQUEST_110.chunk(0),
+                    }
+                    .to_event(),
+                    ActionTriggered::Scheduled(LocalEvent::JoiningTimeoutResendInfo).to_event(),
+                ],
+                routine_complete_output: None,
+                routine_failure_output: None,
+            },
+        );
+    }
+
+    #[test]
+    fn joining_lost_proof_receipt() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
 
-    //////////////////
-    /// Joining Relocate Node
-    //////////////////
+        let initial_state = arrange_initial_joining_state(
+            &initial_state,
+            &[
+                Rpc::ConnectionInfoResponse {
+                    source: NAME_111,
+                    destination: OUR_NAME,
+                    connection_info: NAME_111.0,
+                }
+                .to_event(),
+                Rpc::ResourceProof {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_111,
+                    proof: *PROOF_REQUEST_111,
+                }
+                .to_event(),
+                TestEvent::SetResourceProof(NAME_111, ProofSource::new(*PROOF_REQUEST_111))
+                    .to_event(),
+                LocalEvent::ResourceProofForElderReady(NAME_111).to_event(),
+            ],
+        );
+
+        run_joining_test(
+            "Receipt lost: resend the same part, not the next one.",
+            &initial_state,
+            &[LocalEvent::JoiningTimeoutResendInfo.to_event()],
+            &AssertJoiningState {
+                action_our_events: vec![
+                    Rpc::ConnectionInfoRequest {
+                        source: OUR_NAME,
+                        destination: NAME_109,
+                        connection_info: OUR_NAME.0,
+                    }
+                    .to_event(),
+                    Rpc::ConnectionInfoRequest {
+                        source: OUR_NAME,
+                        destination: NAME_110,
+                        connection_info: OUR_NAME.0,
+                    }
+                    .to_event(),
+                    Rpc::ResourceProofResponse {
+                        candidate: OUR_NODE_CANDIDATE,
+                        destination: NAME_111,
+                        proof: PROOF_REQUEST_111.chunk(0),
+                    }
+                    .to_event(),
+                    ActionTriggered::Scheduled(LocalEvent::JoiningTimeoutResendInfo).to_event(),
+                ],
+                routine_complete_output: None,
+                routine_failure_output: None,
+            },
+        );
+    }
 
     #[test]
-    fn joining_start() {
+    fn joining_duplicated_proof_receipt_after_resend() {
         let mut initial_state = initial_joining_state_with_dst_200();
         initial_state.start(CANDIDATE_RELOCATED_INFO_132);
 
+        let initial_state = arrange_initial_joining_state(
+            &initial_state,
+            &[
+                Rpc::ConnectionInfoResponse {
+                    source: NAME_111,
+                    destination: OUR_NAME,
+                    connection_info: NAME_111.0,
+                }
+                .to_event(),
+                Rpc::ResourceProof {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_111,
+                    proof: *PROOF_REQUEST_111,
+                }
+                .to_event(),
+                TestEvent::SetResourceProof(NAME_111, ProofSource::new(*PROOF_REQUEST_111))
+                    .to_event(),
+                LocalEvent::ResourceProofForElderReady(NAME_111).to_event(),
+                LocalEvent::JoiningTimeoutResendInfo.to_event(),
+                Rpc::ResourceProofReceipt {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_111,
+                    index: 0,
+                }
+                .to_event(),
+            ],
+        );
+
         run_joining_test(
-            "",
+            "Resent part receipted twice: the part sent since still waits for its receipt.",
             &initial_state,
-            &[],
+            &[
+                Rpc::ResourceProofReceipt {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_111,
+                    index: 0,
+                }
+                .to_event(),
+                LocalEvent::JoiningTimeoutResendInfo.to_event(),
+            ],
             &AssertJoiningState {
                 action_our_events: vec![
                     Rpc::ConnectionInfoRequest {
@@ -1725,342 +3836,658 @@ mod node_tests {
                         connection_info: OUR_NAME.0,
                     }
                     .to_event(),
+                    Rpc::ResourceProofResponse {
+                        candidate: OUR_NODE_CANDIDATE,
+                        destination: NAME_111,
+                        proof: PROOF_REQUEST_111.chunk(1),
+                    }
+                    .to_event(),
+                    ActionTriggered::Scheduled(LocalEvent::JoiningTimeoutResendInfo).to_event(),
+                ],
+                routine_complete_output: None,
+                routine_failure_output: None,
+            },
+        );
+    }
+
+    #[test]
+    fn joining_proof_receipt_without_part_sent() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+
+        let initial_state = arrange_initial_joining_state(
+            &initial_state,
+            &[
+                TestEvent::SetResourceProof(NAME_111, ProofSource::new(*PROOF_REQUEST_111))
+                    .to_event(),
+            ],
+        );
+
+        run_joining_test(
+            "Receipt without any part outstanding: do not send any part.",
+            &initial_state,
+            &[Rpc::ResourceProofReceipt {
+                candidate: OUR_NODE_CANDIDATE,
+                source: NAME_111,
+                index: 0,
+            }
+            .to_event()],
+            &AssertJoiningState {
+                action_our_events: vec![],
+                routine_complete_output: None,
+                routine_failure_output: None,
+            },
+        );
+    }
+
+    #[test]
+    fn joining_lost_resource_proof_request() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+
+        let initial_state = arrange_initial_joining_state(
+            &initial_state,
+            &[
+                Rpc::ConnectionInfoResponse {
+                    source: NAME_110,
+                    destination: OUR_NAME,
+                    connection_info: NAME_110.0,
+                }
+                .to_event(),
+                Rpc::ConnectionInfoResponse {
+                    source: NAME_111,
+                    destination: OUR_NAME,
+                    connection_info: NAME_111.0,
+                }
+                .to_event(),
+                Rpc::ResourceProof {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_111,
+                    proof: *PROOF_REQUEST_111,
+                }
+                .to_event(),
+            ],
+        );
+
+        run_joining_test(
+            "No ResourceProof from an elder: resend CandidateInfo to that elder only.",
+            &initial_state,
+            &[LocalEvent::JoiningTimeoutResendInfo.to_event()],
+            &AssertJoiningState {
+                action_our_events: vec![
+                    Rpc::ConnectionInfoRequest {
+                        source: OUR_NAME,
+                        destination: NAME_109,
+                        connection_info: OUR_NAME.0,
+                    }
+                    .to_event(),
+                    Rpc::CandidateInfo(CandidateInfo::new(
+                        OUR_NODE_CANDIDATE_OLD,
+                        OUR_NODE_CANDIDATE,
+                        NAME_110,
+                        TARGET_INTERVAL_1,
+                        CANDIDATE_RELOCATED_INFO_132.source_proof,
+                        SECRET_KEY_132_OLD,
+                        SECRET_KEY_132,
+                    ))
+                    .to_event(),
+                    ActionTriggered::Scheduled(LocalEvent::JoiningTimeoutResendInfo).to_event(),
+                ],
+                routine_complete_output: None,
+                routine_failure_output: None,
+            },
+        );
+    }
+
+    #[test]
+    fn joining_connection_info_request() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+        let initial_state = arrange_initial_joining_state(&initial_state, &[]);
+
+        run_joining_test(
+            "Answer ConnectionInfoRequest while joining",
+            &initial_state,
+            &[Rpc::ConnectionInfoRequest {
+                source: NAME_109,
+                destination: OUR_NAME,
+                connection_info: NAME_109.0,
+            }
+            .to_event()],
+            &AssertJoiningState {
+                action_our_events: vec![Rpc::ConnectionInfoResponse {
+                    source: OUR_NAME,
+                    destination: NAME_109,
+                    connection_info: OUR_NAME.0,
+                }
+                .to_event()],
+                routine_complete_output: None,
+                routine_failure_output: None,
+            },
+        );
+    }
+
+    #[test]
+    fn joining_elder_disconnected() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+
+        let initial_state = arrange_initial_joining_state(
+            &initial_state,
+            &[
+                Rpc::ConnectionInfoResponse {
+                    source: NAME_109,
+                    destination: OUR_NAME,
+                    connection_info: NAME_109.0,
+                }
+                .to_event(),
+                Rpc::ConnectionInfoResponse {
+                    source: NAME_110,
+                    destination: OUR_NAME,
+                    connection_info: NAME_110.0,
+                }
+                .to_event(),
+            ],
+        );
+
+        run_joining_test(
+            "Elder disconnected: reconnect to it on resend timeout.",
+            &initial_state,
+            &[
+                LocalEvent::NodeDisconnected(NAME_109).to_event(),
+                LocalEvent::JoiningTimeoutResendInfo.to_event(),
+            ],
+            &AssertJoiningState {
+                action_our_events: vec![
+                    Rpc::ConnectionInfoRequest {
+                        source: OUR_NAME,
+                        destination: NAME_109,
+                        connection_info: OUR_NAME.0,
+                    }
+                    .to_event(),
                     Rpc::ConnectionInfoRequest {
                         source: OUR_NAME,
                         destination: NAME_111,
                         connection_info: OUR_NAME.0,
                     }
                     .to_event(),
+                    Rpc::CandidateInfo(CandidateInfo::new(
+                        OUR_NODE_CANDIDATE_OLD,
+                        OUR_NODE_CANDIDATE,
+                        NAME_110,
+                        TARGET_INTERVAL_1,
+                        CANDIDATE_RELOCATED_INFO_132.source_proof,
+                        SECRET_KEY_132_OLD,
+                        SECRET_KEY_132,
+                    ))
+                    .to_event(),
                     ActionTriggered::Scheduled(LocalEvent::JoiningTimeoutResendInfo).to_event(),
-                    ActionTriggered::Scheduled(LocalEvent::JoiningTimeoutProofRefused).to_event(),
                 ],
                 routine_complete_output: None,
+                routine_failure_output: None,
+            },
+        );
+    }
+
+    #[test]
+    fn joining_approved() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+
+        let initial_state = arrange_initial_joining_state(&initial_state, &[]);
+
+        run_joining_test(
+            "On NodeApproval from a quorum of elders: complete the routine work.",
+            &initial_state,
+            &[
+                Rpc::NodeApproval {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_109,
+                    info: GenesisPfxInfo(DST_SECTION_INFO_200),
+                }
+                .to_event(),
+                Rpc::NodeApproval {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_110,
+                    info: GenesisPfxInfo(DST_SECTION_INFO_200),
+                }
+                .to_event(),
+            ],
+            &AssertJoiningState {
+                routine_complete_output: Some(GenesisPfxInfo(DST_SECTION_INFO_200)),
+                ..AssertJoiningState::default()
             },
         );
     }
 
     #[test]
-    fn joining_resend_timeout() {
+    fn joining_proof_refused_timeout() {
         let mut initial_state = initial_joining_state_with_dst_200();
         initial_state.start(CANDIDATE_RELOCATED_INFO_132);
 
         let initial_state = arrange_initial_joining_state(&initial_state, &[]);
 
         run_joining_test(
-            "When not yet connected, resend CandidateInfo.",
+            "On JoiningTimeoutProofRefused: fail the routine so we can rebootstrap.",
             &initial_state,
-            &[LocalEvent::JoiningTimeoutResendInfo.to_event()],
+            &[LocalEvent::JoiningTimeoutProofRefused.to_event()],
             &AssertJoiningState {
-                action_our_events: vec![
-                    Rpc::ConnectionInfoRequest {
-                        source: OUR_NAME,
-                        destination: NAME_109,
-                        connection_info: OUR_NAME.0,
-                    }
-                    .to_event(),
-                    Rpc::ConnectionInfoRequest {
-                        source: OUR_NAME,
-                        destination: NAME_110,
-                        connection_info: OUR_NAME.0,
-                    }
-                    .to_event(),
-                    Rpc::ConnectionInfoRequest {
-                        source: OUR_NAME,
-                        destination: NAME_111,
-                        connection_info: OUR_NAME.0,
-                    }
-                    .to_event(),
-                    ActionTriggered::Scheduled(LocalEvent::JoiningTimeoutResendInfo).to_event(),
-                ],
-                routine_complete_output: None,
+                routine_failure_output: Some(JoiningFailure::ProofRefusedTimeout),
+                ..AssertJoiningState::default()
             },
         );
     }
 
     #[test]
-    fn joining_receive_two_connection_info() {
+    fn joining_proof_refused_timeout_then_approved() {
         let mut initial_state = initial_joining_state_with_dst_200();
         initial_state.start(CANDIDATE_RELOCATED_INFO_132);
 
-        let initial_state = arrange_initial_joining_state(&initial_state, &[]);
+        let initial_state = arrange_initial_joining_state(
+            &initial_state,
+            &[LocalEvent::JoiningTimeoutProofRefused.to_event()],
+        );
 
         run_joining_test(
-            "",
+            "Once the routine failed, discard any further events.",
             &initial_state,
             &[
-                Rpc::ConnectionInfoResponse {
-                    source: NAME_110,
-                    destination: OUR_NAME,
-                    connection_info: NAME_110.0,
+                Rpc::NodeApproval {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_109,
+                    info: GenesisPfxInfo(DST_SECTION_INFO_200),
                 }
                 .to_event(),
-                Rpc::ConnectionInfoResponse {
-                    source: NAME_111,
-                    destination: OUR_NAME,
-                    connection_info: NAME_111.0,
+                Rpc::NodeApproval {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_110,
+                    info: GenesisPfxInfo(DST_SECTION_INFO_200),
                 }
                 .to_event(),
+                LocalEvent::JoiningTimeoutResendInfo.to_event(),
             ],
             &AssertJoiningState {
-                action_our_events: vec![
-                    Rpc::CandidateInfo(CandidateInfo {
-                        old_public_id: OUR_NODE_CANDIDATE_OLD,
-                        new_public_id: OUR_NODE_CANDIDATE,
-                        destination: NAME_110,
-                        waiting_candidate_name: TARGET_INTERVAL_1,
-                        valid: true,
-                    })
-                    .to_event(),
-                    Rpc::CandidateInfo(CandidateInfo {
-                        old_public_id: OUR_NODE_CANDIDATE_OLD,
-                        new_public_id: OUR_NODE_CANDIDATE,
-                        destination: NAME_111,
-                        waiting_candidate_name: TARGET_INTERVAL_1,
-                        valid: true,
-                    })
-                    .to_event(),
-                ],
-                routine_complete_output: None,
+                routine_failure_output: Some(JoiningFailure::ProofRefusedTimeout),
+                ..AssertJoiningState::default()
             },
         );
     }
 
     #[test]
-    fn joining_receive_two_resource_proof() {
+    fn joining_approved_then_proof_refused_timeout() {
         let mut initial_state = initial_joining_state_with_dst_200();
         initial_state.start(CANDIDATE_RELOCATED_INFO_132);
-        let initial_state = arrange_initial_joining_state(&initial_state, &[]);
 
-        run_joining_test(
-            "Start computing resource proof when receiving ResourceProof RPC and setup timers.",
+        let initial_state = arrange_initial_joining_state(
             &initial_state,
             &[
-                Rpc::ResourceProof {
+                Rpc::NodeApproval {
                     candidate: OUR_NODE_CANDIDATE,
-                    source: NAME_111,
-                    proof: ProofRequest { value: NAME_111.0 },
+                    source: NAME_109,
+                    info: GenesisPfxInfo(DST_SECTION_INFO_200),
                 }
                 .to_event(),
-                Rpc::ResourceProof {
+                Rpc::NodeApproval {
                     candidate: OUR_NODE_CANDIDATE,
                     source: NAME_110,
-                    proof: ProofRequest { value: NAME_111.0 },
+                    info: GenesisPfxInfo(DST_SECTION_INFO_200),
                 }
                 .to_event(),
             ],
+        );
+
+        run_joining_test(
+            "Once approved, the refused timeout has no effect.",
+            &initial_state,
+            &[LocalEvent::JoiningTimeoutProofRefused.to_event()],
             &AssertJoiningState {
-                action_our_events: vec![
-                    ActionTriggered::ComputeResourceProofForElder(NAME_111).to_event(),
-                    ActionTriggered::ComputeResourceProofForElder(NAME_110).to_event(),
-                ],
-                routine_complete_output: None,
+                routine_complete_output: Some(GenesisPfxInfo(DST_SECTION_INFO_200)),
+                ..AssertJoiningState::default()
             },
         );
     }
 
     #[test]
-    fn joining_computed_two_proofs() {
+    fn joining_approved_by_one_elder() {
         let mut initial_state = initial_joining_state_with_dst_200();
         initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+
         let initial_state = arrange_initial_joining_state(&initial_state, &[]);
 
         run_joining_test(
-            "When proof computed, start sending response to correct Elder.",
+            "NodeApproval from less than a quorum of elders: keep waiting.",
             &initial_state,
             &[
-                TestEvent::SetResourceProof(NAME_111, ProofSource(2)).to_event(),
-                LocalEvent::ResourceProofForElderReady(NAME_111).to_event(),
-                TestEvent::SetResourceProof(NAME_110, ProofSource(2)).to_event(),
-                LocalEvent::ResourceProofForElderReady(NAME_110).to_event(),
+                Rpc::NodeApproval {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_109,
+                    info: GenesisPfxInfo(DST_SECTION_INFO_200),
+                }
+                .to_event(),
+                Rpc::NodeApproval {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_109,
+                    info: GenesisPfxInfo(DST_SECTION_INFO_200),
+                }
+                .to_event(),
             ],
-            &AssertJoiningState {
-                action_our_events: vec![
-                    Rpc::ResourceProofResponse {
-                        candidate: OUR_NODE_CANDIDATE,
-                        destination: NAME_111,
-                        proof: Proof::ValidPart,
-                    }
-                    .to_event(),
-                    Rpc::ResourceProofResponse {
-                        candidate: OUR_NODE_CANDIDATE,
-                        destination: NAME_110,
-                        proof: Proof::ValidPart,
-                    }
-                    .to_event(),
-                ],
-                routine_complete_output: None,
-            },
+            &AssertJoiningState::default(),
         );
     }
 
     #[test]
-    fn joining_got_part_proof_receipt() {
+    fn joining_approved_by_non_elder() {
         let mut initial_state = initial_joining_state_with_dst_200();
         initial_state.start(CANDIDATE_RELOCATED_INFO_132);
 
-        let initial_state = arrange_initial_joining_state(
+        let initial_state = arrange_initial_joining_state(&initial_state, &[]);
+
+        run_joining_test(
+            "NodeApproval from a node not an elder of the section we trust: discard.",
             &initial_state,
             &[
-                Rpc::ResourceProof {
+                Rpc::NodeApproval {
                     candidate: OUR_NODE_CANDIDATE,
-                    source: NAME_111,
-                    proof: ProofRequest { value: NAME_111.0 },
+                    source: NAME_109,
+                    info: GenesisPfxInfo(DST_SECTION_INFO_200),
+                }
+                .to_event(),
+                Rpc::NodeApproval {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NODE_ELDER_130.0.name,
+                    info: GenesisPfxInfo(DST_SECTION_INFO_200),
                 }
                 .to_event(),
-                TestEvent::SetResourceProof(NAME_111, ProofSource(2)).to_event(),
-                LocalEvent::ResourceProofForElderReady(NAME_111).to_event(),
             ],
+            &AssertJoiningState::default(),
         );
+    }
+
+    #[test]
+    fn joining_approved_with_untrusted_genesis() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+
+        let initial_state = arrange_initial_joining_state(&initial_state, &[]);
 
         run_joining_test(
-            "On receiving receipt, send the next part (end) of the proof to that Elder.",
+            "NodeApproval for a section we do not trust: discard.",
             &initial_state,
-            &[Rpc::ResourceProofReceipt {
-                candidate: OUR_NODE_CANDIDATE,
-                source: NAME_111,
-            }
-            .to_event()],
-            &AssertJoiningState {
-                action_our_events: vec![Rpc::ResourceProofResponse {
+            &[
+                Rpc::NodeApproval {
                     candidate: OUR_NODE_CANDIDATE,
-                    destination: NAME_111,
-                    proof: Proof::ValidEnd,
+                    source: NAME_109,
+                    info: GenesisPfxInfo(OTHER_SECTION_INFO),
                 }
-                .to_event()],
-                routine_complete_output: None,
-            },
+                .to_event(),
+                Rpc::NodeApproval {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_110,
+                    info: GenesisPfxInfo(OTHER_SECTION_INFO),
+                }
+                .to_event(),
+            ],
+            &AssertJoiningState::default(),
         );
     }
 
     #[test]
-    fn joining_got_end_proof_receipt() {
+    fn joining_approved_with_descendant_genesis() {
         let mut initial_state = initial_joining_state_with_dst_200();
         initial_state.start(CANDIDATE_RELOCATED_INFO_132);
 
-        let initial_state = arrange_initial_joining_state(
+        let initial_state = arrange_initial_joining_state(&initial_state, &[]);
+        let descendant_info = GenesisPfxInfo(SectionInfo(
+            DST_SECTION_200,
+            1,
+            SectionElders::new(&[NODE_ELDER_110, NODE_ELDER_111, NODE_ELDER_130]),
+        ));
+        let section_update = Rpc::SectionUpdate {
+            destination: OUR_NAME,
+            info: descendant_info.0,
+            proof: DST_SECTION_INFO_200.sign_successor(descendant_info.0),
+        };
+
+        run_joining_test(
+            "NodeApproval for a later version of the section we trust: complete.",
             &initial_state,
             &[
-                Rpc::ResourceProof {
+                section_rpc(DST_SECTION_200, Authority::Node(OUR_NAME), section_update),
+                Rpc::NodeApproval {
                     candidate: OUR_NODE_CANDIDATE,
-                    source: NAME_111,
-                    proof: ProofRequest { value: NAME_111.0 },
+                    source: NAME_110,
+                    info: descendant_info,
                 }
                 .to_event(),
-                TestEvent::SetResourceProof(NAME_111, ProofSource(2)).to_event(),
-                LocalEvent::ResourceProofForElderReady(NAME_111).to_event(),
-                Rpc::ResourceProofReceipt {
+                Rpc::NodeApproval {
                     candidate: OUR_NODE_CANDIDATE,
                     source: NAME_111,
+                    info: descendant_info,
                 }
                 .to_event(),
             ],
+            &AssertJoiningState {
+                routine_complete_output: Some(descendant_info),
+                ..AssertJoiningState::default()
+            },
+        );
+    }
+
+    #[test]
+    fn joining_approved_after_catching_up_two_versions() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+
+        let initial_state = arrange_initial_joining_state(&initial_state, &[]);
+        let section_info_1 = SectionInfo(
+            DST_SECTION_200,
+            1,
+            SectionElders::new(&[NODE_ELDER_110, NODE_ELDER_111, NODE_ELDER_130]),
+        );
+        let section_info_2 = SectionInfo(
+            DST_SECTION_200,
+            2,
+            SectionElders::new(&[NODE_ELDER_111, NODE_ELDER_130, NODE_ELDER_131]),
         );
 
         run_joining_test(
-            "On receiving receipt for end, do not send anymore.",
+            "Catch up with the section through its updates, then accept NodeApproval from a \
+             quorum of its new elders.",
             &initial_state,
-            &[Rpc::ResourceProofReceipt {
-                candidate: OUR_NODE_CANDIDATE,
-                source: NAME_111,
-            }
-            .to_event()],
+            &[
+                section_rpc(
+                    DST_SECTION_200,
+                    Authority::Node(OUR_NAME),
+                    Rpc::SectionUpdate {
+                        destination: OUR_NAME,
+                        info: section_info_1,
+                        proof: DST_SECTION_INFO_200.sign_successor(section_info_1),
+                    },
+                ),
+                section_rpc(
+                    DST_SECTION_200,
+                    Authority::Node(OUR_NAME),
+                    Rpc::SectionUpdate {
+                        destination: OUR_NAME,
+                        info: section_info_2,
+                        proof: section_info_1.sign_successor(section_info_2),
+                    },
+                ),
+                Rpc::NodeApproval {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_130,
+                    info: GenesisPfxInfo(section_info_2),
+                }
+                .to_event(),
+                Rpc::NodeApproval {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_131,
+                    info: GenesisPfxInfo(section_info_2),
+                }
+                .to_event(),
+            ],
             &AssertJoiningState {
-                action_our_events: vec![],
-                routine_complete_output: None,
+                routine_complete_output: Some(GenesisPfxInfo(section_info_2)),
+                ..AssertJoiningState::default()
             },
         );
     }
 
     #[test]
-    fn joining_resend_timeout_one_proof_completed_one_in_progress() {
+    fn joining_section_update_skipping_a_version() {
         let mut initial_state = initial_joining_state_with_dst_200();
         initial_state.start(CANDIDATE_RELOCATED_INFO_132);
 
-        let initial_state = arrange_initial_joining_state(
+        let initial_state = arrange_initial_joining_state(&initial_state, &[]);
+        let section_info_1 = SectionInfo(
+            DST_SECTION_200,
+            1,
+            SectionElders::new(&[NODE_ELDER_110, NODE_ELDER_111, NODE_ELDER_130]),
+        );
+        let section_info_2 = SectionInfo(
+            DST_SECTION_200,
+            2,
+            SectionElders::new(&[NODE_ELDER_111, NODE_ELDER_130, NODE_ELDER_131]),
+        );
+
+        run_joining_test(
+            "A section update not signed by the last version we trust cannot be linked: discard \
+             it and the NodeApproval relying on it.",
             &initial_state,
             &[
-                Rpc::ConnectionInfoResponse {
-                    source: NAME_110,
-                    destination: OUR_NAME,
-                    connection_info: NAME_110.0,
+                section_rpc(
+                    DST_SECTION_200,
+                    Authority::Node(OUR_NAME),
+                    Rpc::SectionUpdate {
+                        destination: OUR_NAME,
+                        info: section_info_2,
+                        proof: section_info_1.sign_successor(section_info_2),
+                    },
+                ),
+                Rpc::NodeApproval {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_130,
+                    info: GenesisPfxInfo(section_info_2),
                 }
                 .to_event(),
-                Rpc::ConnectionInfoResponse {
-                    source: NAME_111,
-                    destination: OUR_NAME,
-                    connection_info: NAME_111.0,
+                Rpc::NodeApproval {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_131,
+                    info: GenesisPfxInfo(section_info_2),
                 }
                 .to_event(),
-                TestEvent::SetResourceProof(NAME_111, ProofSource(2)).to_event(),
-                LocalEvent::ResourceProofForElderReady(NAME_111).to_event(),
-                TestEvent::SetResourceProof(NAME_110, ProofSource(2)).to_event(),
-                LocalEvent::ResourceProofForElderReady(NAME_110).to_event(),
-                Rpc::ResourceProofReceipt {
+            ],
+            &AssertJoiningState::default(),
+        );
+    }
+
+    #[test]
+    fn joining_approval_after_forged_section_update() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+
+        let initial_state = arrange_initial_joining_state(&initial_state, &[]);
+        let descendant_info = GenesisPfxInfo(SectionInfo(
+            DST_SECTION_200,
+            1,
+            SectionElders::new(&[NODE_ELDER_110, NODE_ELDER_111, NODE_ELDER_130]),
+        ));
+        // Signed by elders of a section we do not trust.
+        let section_update = Rpc::SectionUpdate {
+            destination: OUR_NAME,
+            info: descendant_info.0,
+            proof: SRC_SECTION_INFO_300.sign_successor(descendant_info.0),
+        };
+
+        run_joining_test(
+            "NodeApproval for a later version not authorised by the section we trust: discard.",
+            &initial_state,
+            &[
+                section_rpc(SRC_SECTION_300, Authority::Node(OUR_NAME), section_update),
+                Rpc::NodeApproval {
                     candidate: OUR_NODE_CANDIDATE,
-                    source: NAME_111,
+                    source: NAME_110,
+                    info: descendant_info,
                 }
                 .to_event(),
-                Rpc::ResourceProofReceipt {
+                Rpc::NodeApproval {
                     candidate: OUR_NODE_CANDIDATE,
                     source: NAME_111,
+                    info: descendant_info,
                 }
                 .to_event(),
             ],
+            &AssertJoiningState::default(),
         );
+    }
+
+    #[test]
+    fn joining_approval_from_other_node() {
+        let mut initial_state = initial_joining_state_with_dst_200();
+        initial_state.start(CANDIDATE_RELOCATED_INFO_132);
+
+        let initial_state = arrange_initial_joining_state(&initial_state, &[]);
 
         run_joining_test(
-            "When connected, resend connection info or candidate info as needed.",
+            "NodeApproval not sent by the elder it names: reject.",
             &initial_state,
-            &[LocalEvent::JoiningTimeoutResendInfo.to_event()],
+            &[Rpc::NodeApproval {
+                candidate: OUR_NODE_CANDIDATE,
+                source: NAME_110,
+                info: GenesisPfxInfo(DST_SECTION_INFO_200),
+            }
+            .to_message(Authority::Node(NAME_111), Authority::Node(OUR_NAME))
+            .to_event()],
             &AssertJoiningState {
-                action_our_events: vec![
-                    Rpc::ConnectionInfoRequest {
-                        source: OUR_NAME,
-                        destination: NAME_109,
-                        connection_info: OUR_NAME.0,
-                    }
-                    .to_event(),
-                    Rpc::CandidateInfo(CandidateInfo {
-                        old_public_id: OUR_NODE_CANDIDATE_OLD,
-                        new_public_id: OUR_NODE_CANDIDATE,
-                        destination: NAME_110,
-                        waiting_candidate_name: TARGET_INTERVAL_1,
-                        valid: true,
-                    })
-                    .to_event(),
-                    Rpc::CandidateInfo(CandidateInfo {
-                        old_public_id: OUR_NODE_CANDIDATE_OLD,
-                        new_public_id: OUR_NODE_CANDIDATE,
-                        destination: NAME_111,
-                        waiting_candidate_name: TARGET_INTERVAL_1,
-                        valid: true,
-                    })
-                    .to_event(),
-                    ActionTriggered::Scheduled(LocalEvent::JoiningTimeoutResendInfo).to_event(),
-                ],
-                routine_complete_output: None,
+                action_our_events: vec![ActionTriggered::UnexpectedAuthorityRejected.to_event()],
+                ..AssertJoiningState::default()
             },
         );
     }
 
     #[test]
-    fn joining_approved() {
+    fn joining_conflicting_approvals() {
         let mut initial_state = initial_joining_state_with_dst_200();
         initial_state.start(CANDIDATE_RELOCATED_INFO_132);
 
         let initial_state = arrange_initial_joining_state(&initial_state, &[]);
+        let descendant_info = GenesisPfxInfo(SectionInfo(
+            DST_SECTION_200,
+            1,
+            SectionElders::new(&[NODE_ELDER_110, NODE_ELDER_111, NODE_ELDER_130]),
+        ));
+        let section_update = Rpc::SectionUpdate {
+            destination: OUR_NAME,
+            info: descendant_info.0,
+            proof: DST_SECTION_INFO_200.sign_successor(descendant_info.0),
+        };
 
         run_joining_test(
-            "On NodeApproval: complete the routine work.",
+            "Conflicting NodeApproval: an elder cannot change its approval, and differing \
+             approvals do not count toward the same quorum.",
             &initial_state,
             &[
-                Rpc::NodeApproval(OUR_NODE_CANDIDATE, GenesisPfxInfo(DST_SECTION_INFO_200))
-                    .to_event(),
+                section_rpc(DST_SECTION_200, Authority::Node(OUR_NAME), section_update),
+                Rpc::NodeApproval {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_109,
+                    info: GenesisPfxInfo(DST_SECTION_INFO_200),
+                }
+                .to_event(),
+                Rpc::NodeApproval {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_109,
+                    info: descendant_info,
+                }
+                .to_event(),
+                Rpc::NodeApproval {
+                    candidate: OUR_NODE_CANDIDATE,
+                    source: NAME_110,
+                    info: descendant_info,
+                }
+                .to_event(),
             ],
-            &AssertJoiningState {
-                routine_complete_output: Some(GenesisPfxInfo(DST_SECTION_INFO_200)),
-                ..AssertJoiningState::default()
-            },
+            &AssertJoiningState::default(),
         );
     }
 }
@@ -0,0 +1,554 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::utilities::{
+    ActionTriggered, Age, Attributes, Authority, Candidate, CandidateInfo, ChurnNeeded, Event,
+    GenesisPfxInfo, LocalEvent, Message, Name, Node, NodeChange, ParsecVote, Proof, ProofRequest,
+    ProofScheme, ProofSource, PublicKey, RelocatedInfo, Rpc, Section, SectionElders, SectionInfo,
+    SectionProof, Signature, State, TestEvent,
+};
+
+// Delta debugging (ddmin): removes chunks of `events`, then single events, for as long as
+// `fails` still holds on what is left. The result fails, and removing any one of its events
+// makes it pass. Prints the result so it can be pasted as a scenario test.
+pub fn shrink(events: &[Event], fails: impl Fn(&[Event]) -> bool) -> Vec<Event> {
+    assert!(fails(events), "Only failing events can be shrunk");
+
+    let mut events = events.to_vec();
+    let mut granularity = 2;
+    while events.len() >= 2 {
+        let chunk_size = events.len().div_ceil(granularity);
+        let chunks = (0..events.len())
+            .step_by(chunk_size)
+            .map(|start| start..(start + chunk_size).min(events.len()))
+            .collect::<Vec<_>>();
+
+        if let Some(chunk) = chunks.iter().find(|chunk| fails(&events[(*chunk).clone()])) {
+            events = events[chunk.clone()].to_vec();
+            granularity = 2;
+            continue;
+        }
+
+        let complement = chunks
+            .iter()
+            .map(|chunk| {
+                events
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| !chunk.contains(index))
+                    .map(|(_, event)| *event)
+                    .collect::<Vec<_>>()
+            })
+            .find(|complement| fails(complement));
+        if let Some(complement) = complement {
+            events = complement;
+            granularity = (granularity - 1).max(2);
+            continue;
+        }
+
+        if granularity >= events.len() {
+            break;
+        }
+        granularity = (granularity * 2).min(events.len());
+    }
+
+    println!(
+        "Minimal failing events:\n{}",
+        regression_test_events(&events)
+    );
+    events
+}
+
+// The events as a slice expression, in the constructor syntax of scenario_tests.
+pub fn regression_test_events(events: &[Event]) -> String {
+    let mut text = "&[\n".to_string();
+    for event in events {
+        text.push_str(&format!("    {},\n", event.syntax()));
+    }
+    text.push(']');
+    text
+}
+
+// Rust expression that builds the value.
+trait Syntax {
+    fn syntax(&self) -> String;
+}
+
+fn tuple(name: &str, values: &[&dyn Syntax]) -> String {
+    let values = values
+        .iter()
+        .map(|value| value.syntax())
+        .collect::<Vec<_>>();
+    format!("{}({})", name, values.join(", "))
+}
+
+fn fields(name: &str, values: &[(&str, &dyn Syntax)]) -> String {
+    let values = values
+        .iter()
+        .map(|(field, value)| format!("{}: {}", field, value.syntax()))
+        .collect::<Vec<_>>();
+    format!("{} {{ {} }}", name, values.join(", "))
+}
+
+impl Syntax for i32 {
+    fn syntax(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Syntax for bool {
+    fn syntax(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl<T: Syntax> Syntax for Option<T> {
+    fn syntax(&self) -> String {
+        match self {
+            Some(value) => tuple("Some", &[value]),
+            None => "None".to_string(),
+        }
+    }
+}
+
+impl Syntax for Name {
+    fn syntax(&self) -> String {
+        tuple("Name", &[&self.0])
+    }
+}
+
+impl Syntax for Age {
+    fn syntax(&self) -> String {
+        tuple("Age", &[&self.0])
+    }
+}
+
+impl Syntax for Attributes {
+    fn syntax(&self) -> String {
+        fields(
+            "Attributes",
+            &[("age", &self.age), ("name", &self.name), ("key", &self.key)],
+        )
+    }
+}
+
+impl Syntax for Candidate {
+    fn syntax(&self) -> String {
+        tuple("Candidate", &[&self.0])
+    }
+}
+
+impl Syntax for Node {
+    fn syntax(&self) -> String {
+        tuple("Node", &[&self.0])
+    }
+}
+
+impl Syntax for Section {
+    fn syntax(&self) -> String {
+        tuple("Section", &[&self.0])
+    }
+}
+
+impl Syntax for PublicKey {
+    fn syntax(&self) -> String {
+        tuple("PublicKey", &[&self.0])
+    }
+}
+
+impl Syntax for Signature {
+    fn syntax(&self) -> String {
+        tuple("Signature", &[&self.0, &self.1])
+    }
+}
+
+impl Syntax for SectionElders {
+    fn syntax(&self) -> String {
+        let nodes = self
+            .nodes()
+            .iter()
+            .map(|node| node.syntax())
+            .collect::<Vec<_>>();
+        format!("SectionElders::new(&[{}])", nodes.join(", "))
+    }
+}
+
+impl Syntax for SectionInfo {
+    fn syntax(&self) -> String {
+        tuple("SectionInfo", &[&self.0, &self.1, &self.2])
+    }
+}
+
+impl Syntax for GenesisPfxInfo {
+    fn syntax(&self) -> String {
+        tuple("GenesisPfxInfo", &[&self.0])
+    }
+}
+
+impl Syntax for SectionProof {
+    fn syntax(&self) -> String {
+        fields(
+            "SectionProof",
+            &[
+                ("section_info", &self.section_info),
+                ("signature", &self.signature),
+            ],
+        )
+    }
+}
+
+impl Syntax for RelocatedInfo {
+    fn syntax(&self) -> String {
+        fields(
+            "RelocatedInfo",
+            &[
+                ("candidate", &self.candidate),
+                ("expected_age", &self.expected_age),
+                ("target_interval_centre", &self.target_interval_centre),
+                ("section_info", &self.section_info),
+                ("source_proof", &self.source_proof),
+            ],
+        )
+    }
+}
+
+impl Syntax for CandidateInfo {
+    fn syntax(&self) -> String {
+        fields(
+            "CandidateInfo",
+            &[
+                ("old_public_id", &self.old_public_id),
+                ("new_public_id", &self.new_public_id),
+                ("destination", &self.destination),
+                ("waiting_candidate_name", &self.waiting_candidate_name),
+                ("signature_using_old", &self.signature_using_old),
+                ("signature_using_new", &self.signature_using_new),
+                ("source_proof", &self.source_proof),
+            ],
+        )
+    }
+}
+
+impl Syntax for ProofScheme {
+    fn syntax(&self) -> String {
+        format!("ProofScheme::{:?}", self)
+    }
+}
+
+impl Syntax for ProofRequest {
+    fn syntax(&self) -> String {
+        fields(
+            "ProofRequest",
+            &[
+                ("scheme", &self.scheme),
+                ("nonce", &self.nonce),
+                ("seed", &self.seed),
+                ("difficulty", &self.difficulty),
+                ("data_size", &self.data_size),
+            ],
+        )
+    }
+}
+
+impl Syntax for Proof {
+    fn syntax(&self) -> String {
+        fields(
+            "Proof",
+            &[
+                ("nonce", &self.nonce),
+                ("index", &self.index),
+                ("value", &self.value),
+            ],
+        )
+    }
+}
+
+impl Syntax for ProofSource {
+    fn syntax(&self) -> String {
+        fields(
+            "ProofSource",
+            &[("request", &self.request), ("computed", &self.computed)],
+        )
+    }
+}
+
+impl Syntax for State {
+    fn syntax(&self) -> String {
+        match self {
+            State::Relocated(info) => tuple("State::Relocated", &[info]),
+            State::WaitingCandidateInfo(info) => tuple("State::WaitingCandidateInfo", &[info]),
+            _ => format!("State::{:?}", self),
+        }
+    }
+}
+
+impl Syntax for ChurnNeeded {
+    fn syntax(&self) -> String {
+        format!("ChurnNeeded::{:?}", self)
+    }
+}
+
+impl Syntax for NodeChange {
+    fn syntax(&self) -> String {
+        match self {
+            NodeChange::AddWithState(node, state) => {
+                tuple("NodeChange::AddWithState", &[node, state])
+            }
+            NodeChange::ReplaceWith(name, node, state) => {
+                tuple("NodeChange::ReplaceWith", &[name, node, state])
+            }
+            NodeChange::State(node, state) => tuple("NodeChange::State", &[node, state]),
+            NodeChange::Remove(name) => tuple("NodeChange::Remove", &[name]),
+            NodeChange::Elder(node, is_elder) => tuple("NodeChange::Elder", &[node, is_elder]),
+        }
+    }
+}
+
+impl Syntax for Authority {
+    fn syntax(&self) -> String {
+        match self {
+            Authority::Node(name) => tuple("Authority::Node", &[name]),
+            Authority::Section(section) => tuple("Authority::Section", &[section]),
+            Authority::SectionOf(name) => tuple("Authority::SectionOf", &[name]),
+            Authority::Neighbours(section) => tuple("Authority::Neighbours", &[section]),
+        }
+    }
+}
+
+impl Syntax for Rpc {
+    fn syntax(&self) -> String {
+        match self {
+            Rpc::RefuseCandidate(candidate) => tuple("Rpc::RefuseCandidate", &[candidate]),
+            Rpc::RelocateResponse(info) => tuple("Rpc::RelocateResponse", &[info]),
+            Rpc::RelocatedInfo(info) => tuple("Rpc::RelocatedInfo", &[info]),
+            Rpc::ExpectCandidate(candidate) => tuple("Rpc::ExpectCandidate", &[candidate]),
+            Rpc::ResourceProof {
+                candidate,
+                source,
+                proof,
+            } => fields(
+                "Rpc::ResourceProof",
+                &[
+                    ("candidate", candidate),
+                    ("source", source),
+                    ("proof", proof),
+                ],
+            ),
+            Rpc::ResourceProofReceipt {
+                candidate,
+                source,
+                index,
+            } => fields(
+                "Rpc::ResourceProofReceipt",
+                &[
+                    ("candidate", candidate),
+                    ("source", source),
+                    ("index", index),
+                ],
+            ),
+            Rpc::NodeApproval {
+                candidate,
+                source,
+                info,
+            } => fields(
+                "Rpc::NodeApproval",
+                &[("candidate", candidate), ("source", source), ("info", info)],
+            ),
+            Rpc::SectionUpdate {
+                destination,
+                info,
+                proof,
+            } => fields(
+                "Rpc::SectionUpdate",
+                &[
+                    ("destination", destination),
+                    ("info", info),
+                    ("proof", proof),
+                ],
+            ),
+            Rpc::NeighbourUpdate { info, proof } => {
+                fields("Rpc::NeighbourUpdate", &[("info", info), ("proof", proof)])
+            }
+            Rpc::ResourceProofResponse {
+                candidate,
+                destination,
+                proof,
+            } => fields(
+                "Rpc::ResourceProofResponse",
+                &[
+                    ("candidate", candidate),
+                    ("destination", destination),
+                    ("proof", proof),
+                ],
+            ),
+            Rpc::CandidateInfo(info) => tuple("Rpc::CandidateInfo", &[info]),
+            Rpc::ConnectionInfoRequest {
+                source,
+                destination,
+                connection_info,
+            } => fields(
+                "Rpc::ConnectionInfoRequest",
+                &[
+                    ("source", source),
+                    ("destination", destination),
+                    ("connection_info", connection_info),
+                ],
+            ),
+            Rpc::ConnectionInfoResponse {
+                source,
+                destination,
+                connection_info,
+            } => fields(
+                "Rpc::ConnectionInfoResponse",
+                &[
+                    ("source", source),
+                    ("destination", destination),
+                    ("connection_info", connection_info),
+                ],
+            ),
+            Rpc::Merge(info) => tuple("Rpc::Merge", &[info]),
+            Rpc::DkgContribution {
+                source,
+                destination,
+                info,
+            } => fields(
+                "Rpc::DkgContribution",
+                &[
+                    ("source", source),
+                    ("destination", destination),
+                    ("info", info),
+                ],
+            ),
+        }
+    }
+}
+
+impl Syntax for ParsecVote {
+    fn syntax(&self) -> String {
+        match self {
+            ParsecVote::ExpectCandidate(candidate) => {
+                tuple("ParsecVote::ExpectCandidate", &[candidate])
+            }
+            ParsecVote::CandidateConnected(info) => {
+                tuple("ParsecVote::CandidateConnected", &[info])
+            }
+            ParsecVote::Online(old, new, voter) => tuple("ParsecVote::Online", &[old, new, voter]),
+            ParsecVote::PurgeCandidate(candidate) => {
+                tuple("ParsecVote::PurgeCandidate", &[candidate])
+            }
+            ParsecVote::AddElderNode(node) => tuple("ParsecVote::AddElderNode", &[node]),
+            ParsecVote::RemoveElderNode(node) => tuple("ParsecVote::RemoveElderNode", &[node]),
+            ParsecVote::NewSectionInfo(info) => tuple("ParsecVote::NewSectionInfo", &[info]),
+            ParsecVote::DkgFailed(info) => tuple("ParsecVote::DkgFailed", &[info]),
+            ParsecVote::RefuseCandidate(candidate) => {
+                tuple("ParsecVote::RefuseCandidate", &[candidate])
+            }
+            ParsecVote::RelocateResponse(info) => tuple("ParsecVote::RelocateResponse", &[info]),
+            ParsecVote::RelocatedInfo(info) => tuple("ParsecVote::RelocatedInfo", &[info]),
+            ParsecVote::Offline(node) => tuple("ParsecVote::Offline", &[node]),
+            ParsecVote::BackOnline(node) => tuple("ParsecVote::BackOnline", &[node]),
+            ParsecVote::NeighbourMerge(info) => tuple("ParsecVote::NeighbourMerge", &[info]),
+            ParsecVote::CheckResourceProof
+            | ParsecVote::WorkUnitIncrement
+            | ParsecVote::CheckRelocate
+            | ParsecVote::CheckElder => format!("ParsecVote::{:?}", self),
+        }
+    }
+}
+
+impl Syntax for LocalEvent {
+    fn syntax(&self) -> String {
+        match self {
+            LocalEvent::TimeoutDkg(info) => tuple("LocalEvent::TimeoutDkg", &[info]),
+            LocalEvent::ResourceProofForElderReady(name) => {
+                tuple("LocalEvent::ResourceProofForElderReady", &[name])
+            }
+            LocalEvent::NodeDetectedOffline(node) => {
+                tuple("LocalEvent::NodeDetectedOffline", &[node])
+            }
+            LocalEvent::NodeDetectedBackOnline(node) => {
+                tuple("LocalEvent::NodeDetectedBackOnline", &[node])
+            }
+            LocalEvent::NodeDisconnected(name) => tuple("LocalEvent::NodeDisconnected", &[name]),
+            _ => format!("LocalEvent::{:?}", self),
+        }
+    }
+}
+
+impl Syntax for TestEvent {
+    fn syntax(&self) -> String {
+        match self {
+            TestEvent::SetChurnNeeded(churn) => tuple("TestEvent::SetChurnNeeded", &[churn]),
+            TestEvent::SetShortestPrefix(section) => {
+                tuple("TestEvent::SetShortestPrefix", &[section])
+            }
+            TestEvent::SetWorkUnitEnoughToRelocate(node) => {
+                tuple("TestEvent::SetWorkUnitEnoughToRelocate", &[node])
+            }
+            TestEvent::SetResourceProof(name, source) => {
+                tuple("TestEvent::SetResourceProof", &[name, source])
+            }
+        }
+    }
+}
+
+impl Syntax for ActionTriggered {
+    fn syntax(&self) -> String {
+        match self {
+            ActionTriggered::MergeInfoStored(info) => {
+                tuple("ActionTriggered::MergeInfoStored", &[info])
+            }
+            ActionTriggered::OurSectionChanged(info) => {
+                tuple("ActionTriggered::OurSectionChanged", &[info])
+            }
+            ActionTriggered::NeighbourUpdated(info) => {
+                tuple("ActionTriggered::NeighbourUpdated", &[info])
+            }
+            ActionTriggered::Scheduled(event) => tuple("ActionTriggered::Scheduled", &[event]),
+            ActionTriggered::ComputeResourceProofForElder(name) => {
+                tuple("ActionTriggered::ComputeResourceProofForElder", &[name])
+            }
+            ActionTriggered::DkgFailed(info) => tuple("ActionTriggered::DkgFailed", &[info]),
+            _ => format!("ActionTriggered::{:?}", self),
+        }
+    }
+}
+
+impl Syntax for Message {
+    fn syntax(&self) -> String {
+        // Node to node Rpcs, as sent by the nodes they name, have a shorthand.
+        let rpc = self.rpc;
+        if rpc.source_node().map(Authority::Node) == Some(self.source)
+            && rpc.destination().map(Authority::Node) == Some(self.destination)
+        {
+            return rpc.syntax();
+        }
+
+        format!(
+            "{}.to_message({}, {})",
+            rpc.syntax(),
+            self.source.syntax(),
+            self.destination.syntax()
+        )
+    }
+}
+
+impl Syntax for Event {
+    fn syntax(&self) -> String {
+        let value = match self {
+            Event::Rpc(message) => message.syntax(),
+            Event::ParsecConsensus(vote) => vote.syntax(),
+            Event::LocalEvent(event) => event.syntax(),
+            Event::TestEvent(event) => event.syntax(),
+            Event::NodeChange(change) => change.syntax(),
+            Event::ActionTriggered(action) => action.syntax(),
+        };
+        format!("{}.to_event()", value)
+    }
+}
@@ -0,0 +1,177 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::{
+    liveness::{LivenessBound, LivenessChecker, Stuck},
+    state::{JoiningState, MemberState},
+    utilities::{
+        is_quorum, ActionTriggered, Authority, Event, LocalEvent, Message, Name, ParsecVote,
+    },
+};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+// Guard against event loops that never settle.
+const MAX_STEPS: usize = 10_000;
+
+// A minimal network of the elders of one section and the nodes joining it:
+// Rpc are delivered to their destination, a ParsecVote reaches consensus once a quorum of
+// elders voted for it (or straight away for votes naming their voter), and resource proofs
+// are ready as soon as requested.
+// Scheduled events are never fired: the test delivers them explicitly.
+#[derive(Debug, Default)]
+pub struct Simulation {
+    pub elders: BTreeMap<Name, MemberState>,
+    pub joining: BTreeMap<Name, JoiningState>,
+    // Every event delivered so far, with the node it was delivered to
+    pub delivered: Vec<(Name, Event)>,
+    // Every event delivered, each followed by the events its node output handling it
+    pub trace: Vec<Event>,
+    // Work joining nodes did computing resource proofs
+    pub proof_work: i64,
+    // Virtual time, advanced by the test as it delivers timeouts
+    pub now: u64,
+    liveness: LivenessChecker,
+    pending: VecDeque<(Name, Event)>,
+    // Each voting round with the elders who voted in it
+    votes: Vec<(ParsecVote, BTreeSet<Name>)>,
+}
+
+impl Simulation {
+    pub fn add_elder(&mut self, state: MemberState) {
+        let _ = self.elders.insert(state.action.our_name(), state);
+        self.observe_liveness();
+    }
+
+    pub fn add_joining(&mut self, state: JoiningState) {
+        let name = state.action.our_name();
+        let _ = self.joining.insert(name, state);
+        self.route_events(name);
+        self.observe_liveness();
+    }
+
+    pub fn elapse(&mut self, time: u64) {
+        self.now += time;
+    }
+
+    // Entities waiting for progress that did not advance within `bound`.
+    pub fn stuck(&self, bound: LivenessBound) -> Vec<Stuck> {
+        self.liveness
+            .stuck(bound, self.delivered.len(), self.now, &self.delivered)
+    }
+
+    // Delivers a scheduled event, e.g. a timeout, once the test decides it fired.
+    pub fn fire(&mut self, destination: Name, event: LocalEvent) {
+        self.pending.push_back((destination, event.to_event()));
+    }
+
+    pub fn consensus(&mut self, vote: ParsecVote) {
+        for name in self.elders.keys() {
+            self.pending.push_back((*name, vote.to_event()));
+        }
+    }
+
+    pub fn run(&mut self) {
+        let mut steps = 0;
+        while let Some((destination, event)) = self.pending.pop_front() {
+            steps += 1;
+            assert!(steps < MAX_STEPS, "Simulation did not settle");
+
+            if let Some(state) = self.elders.get_mut(&destination) {
+                let _ = state.try_next(event);
+            } else if let Some(state) = self.joining.get_mut(&destination) {
+                let _ = state.try_next(event);
+            } else {
+                continue;
+            }
+
+            self.delivered.push((destination, event));
+            self.trace.push(event);
+            self.route_events(destination);
+            self.observe_liveness();
+        }
+    }
+
+    fn observe_liveness(&mut self) {
+        self.liveness
+            .observe(self.delivered.len(), self.now, &self.elders, &self.joining);
+    }
+
+    pub fn was_delivered(&self, destination: Name, predicate: impl Fn(&Event) -> bool) -> bool {
+        self.delivered
+            .iter()
+            .any(|(name, event)| *name == destination && predicate(event))
+    }
+
+    fn route_events(&mut self, source: Name) {
+        let events = if let Some(state) = self.elders.get(&source) {
+            state.action.take_our_events()
+        } else if let Some(state) = self.joining.get(&source) {
+            state.action.take_our_events()
+        } else {
+            return;
+        };
+
+        for event in events {
+            self.trace.push(event);
+            match event {
+                // Only nodes are addressed directly: a single section is simulated.
+                Event::Rpc(Message {
+                    destination: Authority::Node(destination),
+                    ..
+                }) => self.pending.push_back((destination, event)),
+                Event::ParsecConsensus(vote) if self.elders.contains_key(&source) => {
+                    self.vote(source, vote)
+                }
+                Event::ActionTriggered(ActionTriggered::ComputeResourceProofForElder(elder)) => {
+                    self.compute_resource_proof(source, elder)
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn vote(&mut self, voter: Name, vote: ParsecVote) {
+        if vote.voter().is_some() {
+            self.consensus(vote);
+            return;
+        }
+
+        let round = self
+            .votes
+            .iter()
+            .position(|(round_vote, voters)| *round_vote == vote && !voters.contains(&voter));
+        let round = round.unwrap_or_else(|| {
+            self.votes.push((vote, BTreeSet::new()));
+            self.votes.len() - 1
+        });
+
+        let voters = &mut self.votes[round].1;
+        let _ = voters.insert(voter);
+
+        // Consensus only once per round, when the quorum is first reached.
+        let count = voters.len();
+        let elder_count = self.elders.len();
+        if is_quorum(count, elder_count) && !is_quorum(count - 1, elder_count) {
+            self.consensus(vote);
+        }
+    }
+
+    fn compute_resource_proof(&mut self, source: Name, elder: Name) {
+        if let Some(state) = self.joining.get(&source) {
+            if let Some(proof) = state.action.inner().resource_proofs_for_elder.get(&elder) {
+                self.proof_work += proof.request.cost();
+            }
+        }
+
+        self.pending.push_back((
+            source,
+            LocalEvent::ResourceProofForElderReady(elder).to_event(),
+        ));
+    }
+}
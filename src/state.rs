@@ -9,16 +9,18 @@
 
 use crate::{
     actions::Action,
-    flows_dst::{RespondToRelocateRequests, StartResourceProof},
+    coverage,
+    flows_dst::{RespondToRelocateRequests, StartRelocatedNodeConnection, StartResourceProof},
     flows_elder::{
         CheckOnlineOffline, ProcessElderChange, ProcessMerge, ProcessSplit,
         StartMergeSplitAndChangeElders,
     },
-    flows_node::JoiningRelocateCandidate,
+    flows_node::{JoiningRelocateCandidate, StartConnectionHandler},
     flows_src::{StartDecidesOnNodeToRelocate, StartRelocateSrc},
     utilities::{
-        ActionTriggered, Candidate, CandidateInfo, ChangeElder, Event, GenesisPfxInfo, Name,
-        ParsecVote, RelocatedInfo, Rpc, TryResult, WaitedEvent,
+        ActionTriggered, Authority, Candidate, CandidateInfo, ChangeElder, Event, GenesisPfxInfo,
+        JoiningFailure, Name, ParsecVote, Proof, ProofRequest, RelocatedInfo, Section, SectionInfo,
+        TryResult, WaitedEvent,
     },
 };
 use std::{
@@ -32,6 +34,10 @@ pub struct ProcessElderChangeState {
     pub is_active: bool,
     pub wait_votes: Vec<ParsecVote>,
     pub change_elder: Option<ChangeElder>,
+    // Key generation among the new elders, started once their membership is consensused
+    pub dkg_started: bool,
+    pub dkg_complete: bool,
+    pub dkg_contributions: BTreeSet<Name>,
 }
 
 #[derive(Debug, PartialEq, Default, Clone)]
@@ -45,24 +51,36 @@ pub struct StartMergeSplitAndChangeEldersState {
     pub sub_routine_process_split: ProcessSplitState,
     pub sub_routine_process_elder_change: ProcessElderChangeState,
     pub sub_routine_process_merge_active: bool,
+    // Key generation contributions from elders that started an elder change before us
+    pub early_dkg_contributions: BTreeSet<(SectionInfo, Name)>,
 }
 
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct StartResourceProofState {
     pub candidate_info: Option<CandidateInfo>,
     pub candidate: Option<(Name, Candidate)>,
+    // Challenge sent to the candidate, and how many of its chunks were checked
+    pub proof_request: Option<ProofRequest>,
+    pub proof_parts_checked: i32,
     pub voted_online: bool,
+    // Consensused Online votes for the candidate, by voter
+    pub online_votes: BTreeMap<Name, Candidate>,
 }
 
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct StartRelocateSrcState {
     pub already_relocating: BTreeMap<Candidate, i32>,
+    // The section we sent ExpectCandidate to: only it may answer for the candidate.
+    pub relocation_targets: BTreeMap<Candidate, Section>,
 }
 
+// Relocated nodes are keyed by their new public id name
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct StartRelocatedNodeConnectionState {
+    // Candidates that sent us a valid CandidateInfo and are connected
     pub candidates: BTreeSet<Name>,
     pub candidates_info: BTreeMap<Name, CandidateInfo>,
+    // Candidates we voted Parsec::CandidateConnected for
     pub candidates_voted: BTreeSet<Name>,
 }
 
@@ -79,12 +97,23 @@ pub struct MemberState {
 
 impl MemberState {
     pub fn try_next(&mut self, event: Event) -> TryResult {
+        let _steps = coverage::EventSteps::start(Some(event));
         if let Some(test_event) = event.to_test_event() {
             self.action.process_test_events(test_event);
             return TryResult::Handled;
         }
 
-        let event = unwrap!(event.to_waited_event());
+        let event = unwrap!(event.to_waited_event(), "Output event delivered");
+
+        if let WaitedEvent::Rpc(message) = event {
+            if !message.is_from_claimed_source()
+                || !self.action.is_our_destination(message.destination)
+            {
+                self.action
+                    .action_triggered(ActionTriggered::UnexpectedAuthorityRejected);
+                return TryResult::Handled;
+            }
+        }
 
         if let TryResult::Handled = self.as_check_online_offline().try_next(event) {
             return TryResult::Handled;
@@ -134,6 +163,14 @@ impl MemberState {
             return TryResult::Handled;
         }
 
+        if let TryResult::Handled = self.as_start_relocated_node_connection().try_next(event) {
+            return TryResult::Handled;
+        }
+
+        if let TryResult::Handled = self.as_start_connection_handler().try_next(event) {
+            return TryResult::Handled;
+        }
+
         if let TryResult::Handled = self.as_start_resource_proof().try_next(event) {
             return TryResult::Handled;
         }
@@ -143,11 +180,6 @@ impl MemberState {
         }
 
         match event {
-            WaitedEvent::Rpc(Rpc::ConnectionInfoResponse { .. }) => {
-                self.action
-                    .action_triggered(ActionTriggered::NotYetImplementedErrorTriggered);
-                TryResult::Handled
-            }
             // These should only happen if a routine started them, so it should have
             // handled them too, but other routine are not there yet and we want to test
             // these do not fail.
@@ -167,6 +199,14 @@ impl MemberState {
         RespondToRelocateRequests(self)
     }
 
+    pub fn as_start_relocated_node_connection(&mut self) -> StartRelocatedNodeConnection<'_> {
+        StartRelocatedNodeConnection(self)
+    }
+
+    pub fn as_start_connection_handler(&mut self) -> StartConnectionHandler<'_> {
+        StartConnectionHandler(&self.action)
+    }
+
     pub fn as_start_resource_proof(&mut self) -> StartResourceProof {
         StartResourceProof(self)
     }
@@ -272,13 +312,29 @@ impl Display for MemberState {
     }
 }
 
+// How far the resource proof for one of the destination elders has got
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum JoiningProofProgress {
+    // Received Rpc::ResourceProof: computing the proof
+    Computing,
+    // Last part sent to the elder
+    Sent(Proof),
+    // Received the receipt for the end part
+    Completed,
+}
+
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct JoiningRelocateCandidateState {
     pub relocated_info: Option<RelocatedInfo>,
-    pub connected: bool,
+    // Connection to each elder is tracked in InnerAction::connected
+    pub proofs_progress: BTreeMap<Name, JoiningProofProgress>,
+    // Elders with a part sent but no receipt: resend on JoiningTimeoutResendInfo
     pub need_resend_proofs: BTreeSet<Name>,
+    // First NodeApproval received from each elder
+    pub approvals: BTreeMap<Name, GenesisPfxInfo>,
 
     pub routine_complete_output: Option<GenesisPfxInfo /*output*/>,
+    pub routine_failure_output: Option<JoiningFailure /*output*/>,
 }
 
 // The very top level event loop deciding how the sub event loops are processed
@@ -291,17 +347,34 @@ pub struct JoiningState {
 
 impl JoiningState {
     pub fn start(&mut self, relocated_info: RelocatedInfo) {
+        let _steps = coverage::EventSteps::start(None);
         self.as_joining_relocate_candidate()
             .start_event_loop(relocated_info)
     }
 
     pub fn try_next(&mut self, event: Event) -> TryResult {
+        let _steps = coverage::EventSteps::start(Some(event));
         if let Some(test_event) = event.to_test_event() {
             self.action.process_test_events(test_event);
             return TryResult::Handled;
         }
 
-        let event = unwrap!(event.to_waited_event());
+        let event = unwrap!(event.to_waited_event(), "Output event delivered");
+
+        if let WaitedEvent::Rpc(message) = event {
+            // Until we join, only messages to us as a node reach us.
+            if !message.is_from_claimed_source()
+                || message.destination != Authority::Node(self.action.our_name())
+            {
+                self.action
+                    .action_triggered(ActionTriggered::UnexpectedAuthorityRejected);
+                return TryResult::Handled;
+            }
+        }
+
+        if let TryResult::Handled = self.as_start_connection_handler().try_next(event) {
+            return TryResult::Handled;
+        }
 
         if let TryResult::Handled = self.as_joining_relocate_candidate().try_next(event) {
             return TryResult::Handled;
@@ -310,6 +383,10 @@ impl JoiningState {
         TryResult::Unhandled
     }
 
+    pub fn as_start_connection_handler(&mut self) -> StartConnectionHandler<'_> {
+        StartConnectionHandler(&self.action)
+    }
+
     pub fn as_joining_relocate_candidate(&mut self) -> JoiningRelocateCandidate {
         JoiningRelocateCandidate(self)
     }
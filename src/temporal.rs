@@ -0,0 +1,218 @@
+// Copyright 2020 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under the MIT license <LICENSE-MIT
+// http://opensource.org/licenses/MIT> or the Modified BSD license <LICENSE-BSD
+// https://opensource.org/licenses/BSD-3-Clause>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms. Please review the Licences for the
+// specific language governing permissions and limitations relating to use of the SAFE Network
+// Software.
+
+use crate::utilities::{Event, Message, ParsecVote, Rpc};
+use std::{
+    fmt::{self, Display, Formatter},
+    rc::Rc,
+};
+
+// Temporal logic over a finite trace of events: each formula holds or not at a position.
+#[derive(Clone)]
+pub enum Formula {
+    // The event at the position matches
+    Event(&'static str, Rc<dyn Fn(&Event) -> bool>),
+    Not(Box<Formula>),
+    Or(Box<Formula>, Box<Formula>),
+    Implies(Box<Formula>, Box<Formula>),
+    // At this position and every later one
+    Always(Box<Formula>),
+    // At this position or a later one
+    Eventually(Box<Formula>),
+    // The second eventually holds, and the first holds at every position before that
+    Until(Box<Formula>, Box<Formula>),
+    // The first does not hold before the second first holds, if ever
+    NeverBefore(Box<Formula>, Box<Formula>),
+}
+
+pub fn event(name: &'static str, predicate: impl Fn(&Event) -> bool + 'static) -> Formula {
+    Formula::Event(name, Rc::new(predicate))
+}
+
+pub fn always(formula: Formula) -> Formula {
+    Formula::Always(Box::new(formula))
+}
+
+pub fn eventually(formula: Formula) -> Formula {
+    Formula::Eventually(Box::new(formula))
+}
+
+pub fn until(holding: Formula, until: Formula) -> Formula {
+    Formula::Until(Box::new(holding), Box::new(until))
+}
+
+pub fn never_before(never: Formula, before: Formula) -> Formula {
+    Formula::NeverBefore(Box::new(never), Box::new(before))
+}
+
+impl Formula {
+    pub fn not(self) -> Formula {
+        Formula::Not(Box::new(self))
+    }
+
+    pub fn or(self, other: Formula) -> Formula {
+        Formula::Or(Box::new(self), Box::new(other))
+    }
+
+    pub fn implies(self, other: Formula) -> Formula {
+        Formula::Implies(Box::new(self), Box::new(other))
+    }
+
+    pub fn holds_at(&self, trace: &[Event], position: usize) -> bool {
+        let later = position..trace.len();
+        match self {
+            Formula::Event(_, predicate) => {
+                trace.get(position).map(|event| predicate(event)) == Some(true)
+            }
+            Formula::Not(formula) => !formula.holds_at(trace, position),
+            Formula::Or(left, right) => {
+                left.holds_at(trace, position) || right.holds_at(trace, position)
+            }
+            Formula::Implies(left, right) => {
+                !left.holds_at(trace, position) || right.holds_at(trace, position)
+            }
+            Formula::Always(formula) => later.clone().all(|index| formula.holds_at(trace, index)),
+            Formula::Eventually(formula) => {
+                later.clone().any(|index| formula.holds_at(trace, index))
+            }
+            Formula::Until(holding, until) => later
+                .clone()
+                .find(|index| until.holds_at(trace, *index))
+                .map(|end| (position..end).all(|index| holding.holds_at(trace, index)))
+                .unwrap_or(false),
+            Formula::NeverBefore(never, before) => {
+                let end = later
+                    .clone()
+                    .find(|index| before.holds_at(trace, *index))
+                    .unwrap_or(trace.len());
+                (position..end).all(|index| !never.holds_at(trace, index))
+            }
+        }
+    }
+
+    // Checks the formula from the start of the trace, explaining where it fails.
+    pub fn check(&self, trace: &[Event]) -> Result<(), String> {
+        if self.holds_at(trace, 0) {
+            return Ok(());
+        }
+
+        let position = self.failing_position(trace, 0);
+        let at = trace
+            .get(position)
+            .map(|event| format!("{:?}", event))
+            .unwrap_or_else(|| "end of trace".to_string());
+        Err(format!(
+            "'{}' does not hold: fails at event {} of {}: {}",
+            self,
+            position,
+            trace.len(),
+            at
+        ))
+    }
+
+    // Where the formula, known not to hold at `position`, is first broken.
+    fn failing_position(&self, trace: &[Event], position: usize) -> usize {
+        match self {
+            Formula::Always(formula) => unwrap_position(
+                (position..trace.len()).find(|index| !formula.holds_at(trace, *index)),
+                trace,
+            ),
+            Formula::NeverBefore(never, _) => unwrap_position(
+                (position..trace.len()).find(|index| never.holds_at(trace, *index)),
+                trace,
+            ),
+            Formula::Until(holding, until) => {
+                match (position..trace.len()).find(|index| until.holds_at(trace, *index)) {
+                    Some(end) => unwrap_position(
+                        (position..end).find(|index| !holding.holds_at(trace, *index)),
+                        trace,
+                    ),
+                    None => trace.len(),
+                }
+            }
+            Formula::Eventually(_) => trace.len(),
+            _ => position,
+        }
+    }
+}
+
+fn unwrap_position(position: Option<usize>, trace: &[Event]) -> usize {
+    position.unwrap_or(trace.len())
+}
+
+impl Display for Formula {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        match self {
+            Formula::Event(name, _) => write!(formatter, "{}", name),
+            Formula::Not(formula) => write!(formatter, "not {}", formula),
+            Formula::Or(left, right) => write!(formatter, "({} or {})", left, right),
+            Formula::Implies(left, right) => write!(formatter, "({} implies {})", left, right),
+            Formula::Always(formula) => write!(formatter, "always {}", formula),
+            Formula::Eventually(formula) => write!(formatter, "eventually {}", formula),
+            Formula::Until(holding, until) => write!(formatter, "({} until {})", holding, until),
+            Formula::NeverBefore(never, before) => {
+                write!(formatter, "(never {} before {})", never, before)
+            }
+        }
+    }
+}
+
+//
+// Properties of the model
+//
+
+// Every consensused ExpectCandidate is answered to its source section.
+pub fn expect_candidate_answered() -> Formula {
+    always(
+        event("ExpectCandidate", |event| {
+            matches!(
+                event,
+                Event::ParsecConsensus(ParsecVote::ExpectCandidate(_))
+            )
+        })
+        .implies(eventually(
+            event("RelocateResponse", |event| {
+                matches!(
+                    event,
+                    Event::Rpc(Message {
+                        rpc: Rpc::RelocateResponse(_),
+                        ..
+                    })
+                )
+            })
+            .or(event("RefuseCandidate", |event| {
+                matches!(
+                    event,
+                    Event::Rpc(Message {
+                        rpc: Rpc::RefuseCandidate(_),
+                        ..
+                    })
+                )
+            })),
+        )),
+    )
+}
+
+// A candidate is only approved once it is consensused Online.
+pub fn no_approval_before_online() -> Formula {
+    never_before(
+        event("NodeApproval", |event| {
+            matches!(
+                event,
+                Event::Rpc(Message {
+                    rpc: Rpc::NodeApproval { .. },
+                    ..
+                })
+            )
+        }),
+        event("Online", |event| {
+            matches!(event, Event::ParsecConsensus(ParsecVote::Online(..)))
+        }),
+    )
+}
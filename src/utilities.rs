@@ -11,8 +11,8 @@ use rand::{
     distributions::{Distribution, Standard},
     Rng,
 };
-use std::cmp::Ordering;
 use std::fmt::{self, Debug, Formatter};
+use unwrap::unwrap;
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Eq, Ord)]
 pub struct Name(pub i32);
@@ -42,6 +42,8 @@ impl Age {
 pub struct Attributes {
     pub age: Age,
     pub name: Name,
+    // Public key of the identity: only the node holding its SecretKey signs for it.
+    pub key: PublicKey,
 }
 
 impl Debug for Attributes {
@@ -55,6 +57,7 @@ impl Distribution<Attributes> for Standard {
         Attributes {
             age: rng.gen(),
             name: rng.gen(),
+            key: rng.gen::<SecretKey>().public_key(),
         }
     }
 }
@@ -66,6 +69,10 @@ impl Candidate {
     pub fn name(self) -> Name {
         self.0.name
     }
+
+    pub const fn public_key(self) -> PublicKey {
+        (self.0).key
+    }
 }
 
 impl Distribution<Candidate> for Standard {
@@ -74,6 +81,88 @@ impl Distribution<Candidate> for Standard {
     }
 }
 
+// Local stand-in for a signature scheme: Schnorr signatures in the integers modulo a prime
+// small enough for i64 arithmetic. The public key does not give the secret key away, so only
+// its holder signs, but the group is far too small to be cryptography.
+const GROUP_MODULUS: i64 = 2_147_483_647;
+const GROUP_GENERATOR: i64 = 7;
+const EXPONENT_MODULUS: i64 = GROUP_MODULUS - 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Eq, Ord)]
+pub struct PublicKey(pub i32);
+
+impl PublicKey {
+    pub const fn verify(self, message: &[i32], signature: Signature) -> bool {
+        let Signature(challenge, response) = signature;
+        let commitment = group_power(GROUP_GENERATOR, exponent(response))
+            * group_power(self.0 as i64, EXPONENT_MODULUS - exponent(challenge))
+            % GROUP_MODULUS;
+        challenge == signature_challenge(commitment, message)
+    }
+}
+
+// Only held by the node owning the identity, drawn independently of its other attributes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Eq, Ord)]
+pub struct SecretKey(pub i32);
+
+impl SecretKey {
+    pub const fn public_key(self) -> PublicKey {
+        PublicKey(group_power(GROUP_GENERATOR, exponent(self.0)) as i32)
+    }
+
+    pub const fn sign(self, message: &[i32]) -> Signature {
+        let nonce = exponent(signature_digest(self.0, message));
+        let challenge = signature_challenge(group_power(GROUP_GENERATOR, nonce), message);
+        let response = (nonce + exponent(self.0) * exponent(challenge)) % EXPONENT_MODULUS;
+        Signature(challenge, response as i32)
+    }
+}
+
+impl Distribution<SecretKey> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> SecretKey {
+        SecretKey(rng.gen())
+    }
+}
+
+// The challenge and the response of a Schnorr signature.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
+pub struct Signature(pub i32, pub i32);
+
+const fn exponent(value: i32) -> i64 {
+    (value as i64).rem_euclid(EXPONENT_MODULUS)
+}
+
+const fn group_power(base: i64, exponent: i64) -> i64 {
+    let mut base = base.rem_euclid(GROUP_MODULUS);
+    let mut exponent = exponent;
+    let mut value = 1;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            value = value * base % GROUP_MODULUS;
+        }
+        base = base * base % GROUP_MODULUS;
+        exponent >>= 1;
+    }
+    value
+}
+
+const fn signature_challenge(commitment: i64, message: &[i32]) -> i32 {
+    exponent(signature_digest(commitment as i32, message)) as i32
+}
+
+const fn signature_digest(key: i32, message: &[i32]) -> i32 {
+    let mut value = key;
+    let mut index = 0;
+    while index < message.len() {
+        value = value
+            .wrapping_mul(1_103_515_245)
+            .wrapping_add(message[index]);
+        value ^= value >> 16;
+        index += 1;
+    }
+    value
+}
+
 #[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Eq, Ord)]
 pub struct Node(pub Attributes);
 
@@ -110,6 +199,9 @@ pub struct RelocatedInfo {
     pub expected_age: Age,
     pub target_interval_centre: Name,
     pub section_info: SectionInfo,
+    // Quorum of signature for the candidate to prove the source section relocated it.
+    // None until the source section reaches consensus on the relocation.
+    pub source_proof: Option<SectionProof>,
 }
 
 impl RelocatedInfo {
@@ -117,6 +209,67 @@ impl RelocatedInfo {
     pub fn old_public_id(&self) -> Candidate {
         self.candidate
     }
+
+    // Sign as the source section with the given elders.
+    pub const fn signed_by(self, source_section: SectionInfo, source_elders: &[Node]) -> Self {
+        Self {
+            source_proof: Some(SectionProof::new(
+                source_section,
+                source_elders,
+                &self.signed_values(),
+            )),
+            ..self
+        }
+    }
+
+    // The relocation decision the source proof is over: everything but the proof itself.
+    pub const fn signed_values(self) -> [i32; 6] {
+        [
+            (self.candidate.0).name.0,
+            (self.candidate.0).age.0,
+            self.expected_age.0,
+            self.target_interval_centre.0,
+            (self.section_info.0).0,
+            self.section_info.1,
+        ]
+    }
+}
+
+// Simulated threshold signature of a section: produced once a quorum of its elders agreed
+// (i.e on consensus), and verified with the key derived from the section's elder set.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
+pub struct SectionProof {
+    pub section_info: SectionInfo,
+    pub signature: Signature,
+}
+
+impl SectionProof {
+    pub const fn new(section_info: SectionInfo, elders: &[Node], message: &[i32]) -> Self {
+        Self {
+            section_info,
+            signature: section_secret_key(elders).sign(message),
+        }
+    }
+
+    pub fn verify(self, elders: &[Node], message: &[i32]) -> bool {
+        section_secret_key(elders)
+            .public_key()
+            .verify(message, self.signature)
+    }
+}
+
+// Spreads the elder names over the section key.
+const KEY_MASK: i32 = 0x5bd1_e995;
+
+// Independent of the order the elders are listed in.
+const fn section_secret_key(elders: &[Node]) -> SecretKey {
+    let mut key = 0i32;
+    let mut index = 0;
+    while index < elders.len() {
+        key = key.wrapping_add(signature_digest(KEY_MASK, &[(elders[index].0).name.0]));
+        index += 1;
+    }
+    SecretKey(key)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
@@ -192,13 +345,178 @@ impl Default for NodeState {
 #[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Ord, Eq)]
 pub struct Section(pub i32);
 
+// Number of elders in a section
+pub const ELDER_SIZE: usize = 3;
+
+// Number of incoming relocations a section processes at a time
+pub const MAX_WAITING_CANDIDATES: usize = 1;
+
+// Elders of one section version, and the section key they can sign with once a quorum of
+// them agree (see SectionProof).
+#[derive(Clone, Copy, PartialEq, PartialOrd, Ord, Eq)]
+pub struct SectionElders {
+    nodes: [Node; ELDER_SIZE],
+    len: usize,
+    pub key: PublicKey,
+}
+
+impl SectionElders {
+    pub const fn new(elders: &[Node]) -> Self {
+        assert!(elders.len() <= ELDER_SIZE, "More elders than ELDER_SIZE");
+
+        // Sorted by name so the same elders always give the same SectionElders.
+        let mut nodes = [Node(Attributes {
+            age: Age(0),
+            name: Name(0),
+            key: PublicKey(0),
+        }); ELDER_SIZE];
+        let mut index = 0;
+        while index < elders.len() {
+            let mut position = index;
+            while position > 0 && (nodes[position - 1].0).name.0 > (elders[index].0).name.0 {
+                nodes[position] = nodes[position - 1];
+                position -= 1;
+            }
+            nodes[position] = elders[index];
+            index += 1;
+        }
+
+        Self {
+            nodes,
+            len: elders.len(),
+            key: section_secret_key(elders).public_key(),
+        }
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes[..self.len]
+    }
+
+    pub fn contains(&self, name: Name) -> bool {
+        self.nodes().iter().any(|node| node.name() == name)
+    }
+}
+
+impl Default for SectionElders {
+    fn default() -> Self {
+        Self::new(&[])
+    }
+}
+
+impl Debug for SectionElders {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "{:?}", self.nodes())
+    }
+}
+
+impl Distribution<SectionElders> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> SectionElders {
+        SectionElders::new(&[rng.gen(), rng.gen(), rng.gen()])
+    }
+}
+
 #[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Ord, Eq)]
-pub struct SectionInfo(pub Section, pub i32 /*contain full membership */);
+pub struct SectionInfo(
+    pub Section,
+    pub i32, /*contain full membership */
+    pub SectionElders,
+);
+
+impl SectionInfo {
+    // What the previous elders sign to authorise this version: the key commits to the elders.
+    pub fn signed_values(self) -> [i32; 3] {
+        [(self.0).0, self.1, (self.2).key.0]
+    }
+
+    // Our elders' signature over the section info that follows us (split, merge or elder
+    // change). Only produced on consensus, i.e by a quorum of our elders.
+    pub fn sign_successor(self, next: SectionInfo) -> SectionProof {
+        SectionProof::new(self, self.2.nodes(), &next.signed_values())
+    }
+
+    // A quorum of our elders authorised `next` to follow us.
+    pub fn is_signed_successor(self, next: SectionInfo, proof: SectionProof) -> bool {
+        proof.section_info == self && (self.2).key.verify(&next.signed_values(), proof.signature)
+    }
+}
+
+// A section's history from a trusted SectionInfo: each later version comes with the signature
+// of the elders of the version before it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SectionProofChain {
+    first: SectionInfo,
+    links: Vec<(SectionInfo, SectionProof)>,
+}
+
+impl SectionProofChain {
+    pub fn new(first: SectionInfo) -> Self {
+        Self {
+            first,
+            links: Vec::new(),
+        }
+    }
+
+    pub fn first(&self) -> SectionInfo {
+        self.first
+    }
+
+    pub fn last(&self) -> SectionInfo {
+        self.links.last().map_or(self.first, |(info, _)| *info)
+    }
+
+    pub fn contains(&self, info: SectionInfo) -> bool {
+        self.first == info || self.links.iter().any(|(linked, _)| *linked == info)
+    }
+
+    pub fn links(&self) -> &[(SectionInfo, SectionProof)] {
+        &self.links
+    }
+
+    // Append `next` if the elders of our last version signed it.
+    pub fn extend(&mut self, next: SectionInfo, proof: SectionProof) -> bool {
+        if !self.last().is_signed_successor(next, proof) {
+            return false;
+        }
+
+        self.links.push((next, proof));
+        true
+    }
+
+    pub fn verify(&self) -> bool {
+        let mut previous = self.first;
+        for (info, proof) in &self.links {
+            if !previous.is_signed_successor(*info, *proof) {
+                return false;
+            }
+            previous = *info;
+        }
+        true
+    }
+
+    // The part of the chain someone trusting `trusted` needs to catch up.
+    pub fn trimmed_to(&self, trusted: SectionInfo) -> Option<SectionProofChain> {
+        if self.first == trusted {
+            return Some(self.clone());
+        }
+
+        self.links
+            .iter()
+            .position(|(info, _)| *info == trusted)
+            .map(|index| Self {
+                first: trusted,
+                links: self.links[index + 1..].to_vec(),
+            })
+    }
+}
 
 impl Distribution<SectionInfo> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> SectionInfo {
         // Avoid randomly generating default `Section(0)`.
-        SectionInfo(Section(rng.gen_range(1, i32::max_value())), rng.gen())
+        SectionInfo(
+            Section(rng.gen_range(1, i32::max_value())),
+            rng.gen(),
+            rng.gen(),
+        )
     }
 }
 
@@ -217,62 +535,329 @@ pub struct ChangeElder {
     pub new_section: SectionInfo,
 }
 
+// Parts of a resource proof at the lowest difficulty
+pub const RESOURCE_PROOF_DATA_SIZE: i32 = 2;
+// Rounds of work needed to compute one part of a seeded resource proof at the lowest difficulty
+pub const RESOURCE_PROOF_DIFFICULTY: i32 = 64;
+// Each time the section grows by this many nodes, resource proofs get one step harder
+pub const NODES_PER_DIFFICULTY_STEP: i32 = 16;
+
+// How busy the section asking for a resource proof is: a busier section asks for harder proofs.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SectionLoad {
+    pub section_size: i32,
+}
+
+impl SectionLoad {
+    // 1 for a small section, one more for each NODES_PER_DIFFICULTY_STEP nodes. Candidates are
+    // proved one at a time (MAX_WAITING_CANDIDATES), so they do not add to the load.
+    pub fn difficulty_factor(self) -> i32 {
+        1 + self.section_size / NODES_PER_DIFFICULTY_STEP
+    }
+}
+
+// How an elder challenges a candidate and checks its answer, and how the candidate computes it.
+pub trait ResourceProofScheme {
+    fn request(&self, source: Name, candidate: Name, nonce: i32, load: SectionLoad)
+        -> ProofRequest;
+
+    fn chunk(&self, request: ProofRequest, index: i32) -> Proof;
+
+    // Work the candidate does to compute the whole proof
+    fn cost(&self, request: ProofRequest) -> i64;
+
+    fn is_valid(&self, request: ProofRequest, proof: Proof) -> bool {
+        0 <= proof.index
+            && proof.index < request.data_size
+            && self.chunk(request, proof.index) == proof
+    }
+}
+
+// Cheap default: each part is the seed plus its index. A busier section asks for more parts.
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub struct ProofRequest {
-    pub value: i32,
+pub struct CountingScheme;
+
+impl ResourceProofScheme for CountingScheme {
+    fn request(
+        &self,
+        source: Name,
+        candidate: Name,
+        nonce: i32,
+        load: SectionLoad,
+    ) -> ProofRequest {
+        let difficulty = load.difficulty_factor();
+        ProofRequest {
+            scheme: ProofScheme::Counting,
+            nonce,
+            seed: ProofRequest::seed(source, candidate, nonce),
+            difficulty,
+            data_size: RESOURCE_PROOF_DATA_SIZE * difficulty,
+        }
+    }
+
+    fn chunk(&self, request: ProofRequest, index: i32) -> Proof {
+        Proof {
+            nonce: request.nonce,
+            index,
+            value: request.seed.wrapping_add(index),
+        }
+    }
+
+    fn cost(&self, request: ProofRequest) -> i64 {
+        i64::from(request.data_size)
+    }
 }
 
+// Each part takes `difficulty` rounds of mixing from the seed. A busier section asks for more
+// rounds.
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Proof {
-    ValidPart,
-    ValidEnd,
-    Invalid,
+pub struct SeededScheme;
+
+impl ResourceProofScheme for SeededScheme {
+    fn request(
+        &self,
+        source: Name,
+        candidate: Name,
+        nonce: i32,
+        load: SectionLoad,
+    ) -> ProofRequest {
+        ProofRequest {
+            scheme: ProofScheme::Seeded,
+            nonce,
+            seed: ProofRequest::seed(source, candidate, nonce),
+            difficulty: RESOURCE_PROOF_DIFFICULTY * load.difficulty_factor(),
+            data_size: RESOURCE_PROOF_DATA_SIZE,
+        }
+    }
+
+    fn chunk(&self, request: ProofRequest, index: i32) -> Proof {
+        let value = (0..request.difficulty).fold(request.seed.wrapping_add(index), |value, _| {
+            value.wrapping_mul(1_103_515_245).wrapping_add(12_345) ^ (value >> 16)
+        });
+        Proof {
+            nonce: request.nonce,
+            index,
+            value,
+        }
+    }
+
+    fn cost(&self, request: ProofRequest) -> i64 {
+        i64::from(request.difficulty) * i64::from(request.data_size)
+    }
+}
+
+// The scheme a section uses: carried in the ProofRequest so the candidate uses the same one.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ProofScheme {
+    #[default]
+    Counting,
+    Seeded,
 }
 
-impl Proof {
-    pub fn is_valid(self) -> bool {
+impl ProofScheme {
+    pub fn scheme(self) -> &'static dyn ResourceProofScheme {
         match self {
-            Proof::ValidPart | Proof::ValidEnd => true,
-            Proof::Invalid => false,
+            ProofScheme::Counting => &CountingScheme,
+            ProofScheme::Seeded => &SeededScheme,
         }
     }
 }
 
-#[derive(Debug, PartialEq, Default, Copy, Clone)]
-pub struct ProofSource(pub i32);
+// Challenge an elder sends a candidate: compute `data_size` parts from `seed` using `scheme`
+// at the given `difficulty`. `nonce` identifies the elder's resource proof session.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProofRequest {
+    pub scheme: ProofScheme,
+    pub nonce: i32,
+    pub seed: i32,
+    pub difficulty: i32,
+    pub data_size: i32,
+}
+
+impl ProofRequest {
+    // Seed of the challenge the elder `source` sends to `candidate` in session `nonce`.
+    pub fn seed(source: Name, candidate: Name, nonce: i32) -> i32 {
+        source
+            .0
+            .wrapping_mul(7919)
+            .wrapping_add(candidate.0)
+            .wrapping_add(nonce.wrapping_mul(104_729))
+    }
+
+    pub fn chunk(self, index: i32) -> Proof {
+        self.scheme.scheme().chunk(self, index)
+    }
+
+    pub fn is_valid(self, proof: Proof) -> bool {
+        self.scheme.scheme().is_valid(self, proof)
+    }
+
+    pub fn cost(self) -> i64 {
+        self.scheme.scheme().cost(self)
+    }
+}
+
+// One chunk of a resource proof, for the session `nonce`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Proof {
+    pub nonce: i32,
+    pub index: i32,
+    pub value: i32,
+}
+
+// The resource proof a candidate computes for one elder, one chunk at a time
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ProofSource {
+    pub request: ProofRequest,
+    // Chunks computed so far
+    pub computed: i32,
+}
 
 impl ProofSource {
+    pub fn new(request: ProofRequest) -> Self {
+        Self {
+            request,
+            computed: 0,
+        }
+    }
+
     pub fn next_part(&mut self) -> Option<Proof> {
-        if self.0 > -1 {
-            self.0 -= 1;
+        if self.computed == self.request.data_size {
+            return None;
         }
 
+        self.computed += 1;
         self.resend()
     }
 
-    fn resend(self) -> Option<Proof> {
-        match self.0.cmp(&0) {
-            Ordering::Greater => Some(Proof::ValidPart),
-            Ordering::Equal => Some(Proof::ValidEnd),
-            Ordering::Less => None,
+    pub fn resend(self) -> Option<Proof> {
+        if self.computed == 0 {
+            return None;
         }
+
+        Some(self.request.chunk(self.computed - 1))
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoiningFailure {
+    // JoiningTimeoutProofRefused expired before we got NodeApproval: Rebootstrap with new identity
+    ProofRefusedTimeout,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct CandidateInfo {
     pub old_public_id: Candidate,
     pub new_public_id: Candidate,
     pub destination: Name,
     pub waiting_candidate_name: Name,
-    pub valid: bool,
+    // Signed with the old identity's key over both public ids and waiting_candidate_name, which
+    // ties it to one relocation.
+    pub signature_using_old: Signature,
+    // Signed with the new identity's key over the same values and signature_using_old.
+    pub signature_using_new: Signature,
+    // Copied from the RelocatedInfo the source section sent us.
+    pub source_proof: Option<SectionProof>,
+}
+
+impl CandidateInfo {
+    // Info signed by the relocated node, which holds the secret keys of both identities.
+    pub const fn new(
+        old_public_id: Candidate,
+        new_public_id: Candidate,
+        destination: Name,
+        waiting_candidate_name: Name,
+        source_proof: Option<SectionProof>,
+        old_secret_key: SecretKey,
+        new_secret_key: SecretKey,
+    ) -> Self {
+        let signature_using_old = old_secret_key.sign(&Self::message_using_old(
+            old_public_id,
+            new_public_id,
+            waiting_candidate_name,
+        ));
+        let signature_using_new = new_secret_key.sign(&Self::message_using_new(
+            old_public_id,
+            new_public_id,
+            waiting_candidate_name,
+            signature_using_old,
+        ));
+
+        Self {
+            old_public_id,
+            new_public_id,
+            destination,
+            waiting_candidate_name,
+            signature_using_old,
+            signature_using_new,
+            source_proof,
+        }
+    }
+
+    pub fn has_valid_signatures(self) -> bool {
+        let message_using_old = Self::message_using_old(
+            self.old_public_id,
+            self.new_public_id,
+            self.waiting_candidate_name,
+        );
+        let message_using_new = Self::message_using_new(
+            self.old_public_id,
+            self.new_public_id,
+            self.waiting_candidate_name,
+            self.signature_using_old,
+        );
+
+        self.old_public_id
+            .public_key()
+            .verify(&message_using_old, self.signature_using_old)
+            && self
+                .new_public_id
+                .public_key()
+                .verify(&message_using_new, self.signature_using_new)
+    }
+
+    const fn message_using_old(
+        old_public_id: Candidate,
+        new_public_id: Candidate,
+        waiting_candidate_name: Name,
+    ) -> [i32; 7] {
+        [
+            (old_public_id.0).name.0,
+            (old_public_id.0).age.0,
+            (old_public_id.0).key.0,
+            (new_public_id.0).name.0,
+            (new_public_id.0).age.0,
+            (new_public_id.0).key.0,
+            waiting_candidate_name.0,
+        ]
+    }
+
+    const fn message_using_new(
+        old_public_id: Candidate,
+        new_public_id: Candidate,
+        waiting_candidate_name: Name,
+        signature_using_old: Signature,
+    ) -> [i32; 9] {
+        let ids = Self::message_using_old(old_public_id, new_public_id, waiting_candidate_name);
+        [
+            ids[0],
+            ids[1],
+            ids[2],
+            ids[3],
+            ids[4],
+            ids[5],
+            ids[6],
+            signature_using_old.0,
+            signature_using_old.1,
+        ]
+    }
 }
 
 // Event passed to get out of "Wait for" state in flow diagram:
 // Pass to try_next to the implementations.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum WaitedEvent {
-    Rpc(Rpc),
+    Rpc(Message),
     ParsecConsensus(ParsecVote),
     LocalEvent(LocalEvent),
 }
@@ -280,7 +865,7 @@ pub enum WaitedEvent {
 // Event allowing to drive the tests and collect output, a superset of WaitedEvent.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Event {
-    Rpc(Rpc),
+    Rpc(Message),
     ParsecConsensus(ParsecVote),
     LocalEvent(LocalEvent),
     TestEvent(TestEvent),
@@ -292,7 +877,7 @@ pub enum Event {
 impl Event {
     pub fn to_waited_event(&self) -> Option<WaitedEvent> {
         match *self {
-            Event::Rpc(rpc) => Some(WaitedEvent::Rpc(rpc)),
+            Event::Rpc(message) => Some(WaitedEvent::Rpc(message)),
             Event::ParsecConsensus(parsec_vote) => Some(WaitedEvent::ParsecConsensus(parsec_vote)),
             Event::LocalEvent(local_event) => Some(WaitedEvent::LocalEvent(local_event)),
             Event::TestEvent(_) | Event::NodeChange(_) | Event::ActionTriggered(_) => None,
@@ -307,6 +892,64 @@ impl Event {
     }
 }
 
+// Who sends or receives a message: a single node, or elders acting for their section.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord)]
+pub enum Authority {
+    Node(Name),
+    Section(Section),
+    // The section covering a name, when the sender does not know which section that is.
+    SectionOf(Name),
+    // The sections next to a section, e.g. the sibling it merges with.
+    Neighbours(Section),
+}
+
+impl Authority {
+    // Only a section sends as a section: SectionOf and Neighbours address the sections receiving.
+    pub fn is_section(&self) -> bool {
+        match self {
+            Authority::Section(_) => true,
+            Authority::Node(_) | Authority::SectionOf(_) | Authority::Neighbours(_) => false,
+        }
+    }
+}
+
+// Counted by the sending node, so every message it sends has its own id, resends included.
+// Messages built outside a node, as the tests do, have the default id.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Eq, Ord)]
+pub struct MessageId(pub u64);
+
+// The envelope every Rpc travels in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Message {
+    pub source: Authority,
+    pub destination: Authority,
+    pub id: MessageId,
+    pub rpc: Rpc,
+}
+
+impl Message {
+    pub fn new(source: Authority, destination: Authority, id: MessageId, rpc: Rpc) -> Self {
+        Self {
+            source,
+            destination,
+            id,
+            rpc,
+        }
+    }
+
+    pub fn to_event(self) -> Event {
+        Event::Rpc(self)
+    }
+
+    // A message naming its sender in the content must come from that sender.
+    pub fn is_from_claimed_source(&self) -> bool {
+        self.rpc
+            .claimed_source()
+            .map(|claimed| claimed == self.source)
+            .unwrap_or(true)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Rpc {
     RefuseCandidate(Candidate),
@@ -323,8 +966,24 @@ pub enum Rpc {
     ResourceProofReceipt {
         candidate: Candidate,
         source: Name,
+        index: i32,
+    },
+    NodeApproval {
+        candidate: Candidate,
+        source: Name,
+        info: GenesisPfxInfo,
+    },
+    // One link of our SectionProofChain, for a node to catch up with our SectionInfo.
+    SectionUpdate {
+        destination: Name,
+        info: SectionInfo,
+        proof: SectionProof,
+    },
+    // One link of a neighbour's SectionProofChain, sent to its neighbours when it changes.
+    NeighbourUpdate {
+        info: SectionInfo,
+        proof: SectionProof,
     },
-    NodeApproval(Candidate, GenesisPfxInfo),
 
     ResourceProofResponse {
         candidate: Candidate,
@@ -345,11 +1004,59 @@ pub enum Rpc {
     },
 
     Merge(SectionInfo),
+
+    // Our share of the key generation for the elders of `info`, sent to each other one of them.
+    DkgContribution {
+        source: Name,
+        destination: Name,
+        info: SectionInfo,
+    },
 }
 
 impl Rpc {
+    // Node to node Rpc: both ends are named in the content.
     pub fn to_event(&self) -> Event {
-        Event::Rpc(*self)
+        let source = unwrap!(self.source_node(), "Section Rpc needs explicit authorities");
+        let destination = unwrap!(self.destination());
+        self.to_message(Authority::Node(source), Authority::Node(destination))
+            .to_event()
+    }
+
+    pub fn to_message(self, source: Authority, destination: Authority) -> Message {
+        Message::new(source, destination, MessageId::default(), self)
+    }
+
+    // The node sending a node to node Rpc.
+    pub fn source_node(&self) -> Option<Name> {
+        match self {
+            Rpc::RefuseCandidate(_)
+            | Rpc::RelocateResponse(_)
+            | Rpc::RelocatedInfo(_)
+            | Rpc::ExpectCandidate(_)
+            | Rpc::Merge(_)
+            | Rpc::SectionUpdate { .. }
+            | Rpc::NeighbourUpdate { .. } => None,
+
+            Rpc::ResourceProofResponse { candidate, .. } => Some(candidate.name()),
+            Rpc::CandidateInfo(info) => Some(info.new_public_id.name()),
+
+            Rpc::ResourceProof { source, .. }
+            | Rpc::ResourceProofReceipt { source, .. }
+            | Rpc::NodeApproval { source, .. }
+            | Rpc::ConnectionInfoRequest { source, .. }
+            | Rpc::ConnectionInfoResponse { source, .. }
+            | Rpc::DkgContribution { source, .. } => Some(*source),
+        }
+    }
+
+    // The authority the content says it comes from, if it names one.
+    pub fn claimed_source(&self) -> Option<Authority> {
+        match self {
+            Rpc::Merge(info) | Rpc::NeighbourUpdate { info, .. } => {
+                Some(Authority::Section(info.0))
+            }
+            _ => self.source_node().map(Authority::Node),
+        }
     }
 
     pub fn destination(&self) -> Option<Name> {
@@ -358,16 +1065,19 @@ impl Rpc {
             | Rpc::RelocateResponse(_)
             | Rpc::RelocatedInfo(_)
             | Rpc::ExpectCandidate(_)
-            | Rpc::Merge(_) => None,
+            | Rpc::Merge(_)
+            | Rpc::NeighbourUpdate { .. } => None,
 
-            Rpc::NodeApproval(candidate, _)
+            Rpc::NodeApproval { candidate, .. }
             | Rpc::ResourceProof { candidate, .. }
             | Rpc::ResourceProofReceipt { candidate, .. } => Some(candidate.0.name),
 
             Rpc::ResourceProofResponse { destination, .. }
             | Rpc::CandidateInfo(CandidateInfo { destination, .. })
             | Rpc::ConnectionInfoRequest { destination, .. }
-            | Rpc::ConnectionInfoResponse { destination, .. } => Some(*destination),
+            | Rpc::ConnectionInfoResponse { destination, .. }
+            | Rpc::SectionUpdate { destination, .. }
+            | Rpc::DkgContribution { destination, .. } => Some(*destination),
         }
     }
 }
@@ -375,14 +1085,18 @@ impl Rpc {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ParsecVote {
     ExpectCandidate(Candidate),
+    CandidateConnected(CandidateInfo),
 
-    Online(Candidate, Candidate),
+    // Each elder votes on its own judgement of the proof: online once a quorum agree
+    Online(Candidate, Candidate, Name /*voter*/),
     PurgeCandidate(Candidate),
     CheckResourceProof,
 
     AddElderNode(Node),
     RemoveElderNode(Node),
     NewSectionInfo(SectionInfo),
+    // A new elder did not contribute to the key of this SectionInfo in time
+    DkgFailed(SectionInfo),
 
     WorkUnitIncrement,
     CheckRelocate,
@@ -403,18 +1117,29 @@ impl ParsecVote {
         Event::ParsecConsensus(*self)
     }
 
+    // Votes tallied by the routines rather than by Parsec: each is consensused on its own.
+    pub fn voter(&self) -> Option<Name> {
+        match self {
+            ParsecVote::Online(_, _, voter) => Some(*voter),
+            _ => None,
+        }
+    }
+
     pub fn candidate(&self) -> Option<Candidate> {
         match self {
             ParsecVote::ExpectCandidate(candidate)
-            | ParsecVote::Online(candidate, _)
+            | ParsecVote::Online(candidate, _, _)
             | ParsecVote::PurgeCandidate(candidate)
             | ParsecVote::RefuseCandidate(candidate)
             | ParsecVote::RelocateResponse(RelocatedInfo { candidate, .. }) => Some(*candidate),
 
+            ParsecVote::CandidateConnected(info) => Some(info.old_public_id),
+
             ParsecVote::CheckResourceProof
             | ParsecVote::AddElderNode(_)
             | ParsecVote::RemoveElderNode(_)
             | ParsecVote::NewSectionInfo(_)
+            | ParsecVote::DkgFailed(_)
             | ParsecVote::WorkUnitIncrement
             | ParsecVote::CheckRelocate
             | ParsecVote::RelocatedInfo(_)
@@ -435,11 +1160,14 @@ pub enum LocalEvent {
     TimeoutCheckRelocate,
 
     TimeoutCheckElder,
+    // Key generation for the elders of this SectionInfo has not completed in time
+    TimeoutDkg(SectionInfo),
     JoiningTimeoutResendInfo,
     JoiningTimeoutProofRefused,
     ResourceProofForElderReady(Name),
     NodeDetectedOffline(Node),
     NodeDetectedBackOnline(Node),
+    NodeDisconnected(Name),
 }
 
 impl LocalEvent {
@@ -467,6 +1195,7 @@ pub enum ActionTriggered {
     WorkUnitIncremented,
     MergeInfoStored(SectionInfo),
     OurSectionChanged(SectionInfo),
+    NeighbourUpdated(SectionInfo),
 
     CompleteMerge,
     CompleteSplit,
@@ -475,10 +1204,15 @@ pub enum ActionTriggered {
 
     ComputeResourceProofForElder(Name),
 
-    // WaitedEvent that should be handled by a flow but are not.
-    NotYetImplementedErrorTriggered,
+    // Key generation failed: the elder change for this SectionInfo is abandoned.
+    DkgFailed(SectionInfo),
+
     // Unexpected event ignored.
     UnexpectedEventErrorTriggered,
+    // Duplicate, or left from an earlier resource proof session: ignored.
+    ReplayedMessageRejected,
+    // Message from an authority that may not send it to us: ignored.
+    UnexpectedAuthorityRejected,
 }
 
 impl ActionTriggered {
@@ -487,6 +1221,11 @@ impl ActionTriggered {
     }
 }
 
+// More than half of the elders must agree.
+pub fn is_quorum(count: usize, elder_count: usize) -> bool {
+    count * 2 > elder_count
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TryResult {
     Handled,